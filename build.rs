@@ -0,0 +1,83 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Records what --version reports about the build: the git commit that was built, the date of the build, and the
+// features enabled, so that it can be told which binary is running on a machine.
+
+// The short hash of the commit that HEAD is at, or unknown if this isn't a git checkout (such as a packaged source)
+fn git_commit() -> String
+{
+    Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .filter(|commit| !commit.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// The date of the build as YYYY-MM-DD in UTC, or of SOURCE_DATE_EPOCH if it is set, for reproducible builds
+fn build_date() -> String
+{
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0));
+
+    // The civil date of a number of days since 1970-01-01, by Howard Hinnant's algorithm, which counts 400 year eras
+    // of years starting on March 1st so that leap days fall at the end of each year
+    let days = (seconds / 86400) + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era = (day_of_era - (day_of_era / 1460) + (day_of_era / 36524) - (day_of_era / 146096)) / 365;
+    let day_of_year = day_of_era - ((365 * year_of_era) + (year_of_era / 4) - (year_of_era / 100));
+    let month = ((5 * day_of_year) + 2) / 153;
+    let day = day_of_year - (((153 * month) + 2) / 5) + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + (era * 400) + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// The features enabled, as Cargo gives them to build scripts, in lower case with - rather than _
+fn features() -> String
+{
+    let mut features = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_").map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<String>>();
+    features.sort();
+
+    if features.is_empty() {
+        "none".to_string()
+    }
+    else {
+        features.join(", ")
+    }
+}
+
+fn main()
+{
+    println!("cargo:rustc-env=SOLPDA_GIT_COMMIT={}", git_commit());
+    println!("cargo:rustc-env=SOLPDA_BUILD_DATE={}", build_date());
+    println!("cargo:rustc-env=SOLPDA_FEATURES={}", features());
+
+    // The commit changes when HEAD moves to another branch or the branch moves to another commit
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD") {
+        if let Some(branch) = head.trim().strip_prefix("ref: ") {
+            println!("cargo:rerun-if-changed=.git/{}", branch);
+        }
+    }
+    // A missing file would be taken to have changed on every build
+    if std::path::Path::new(".git/packed-refs").exists() {
+        println!("cargo:rerun-if-changed=.git/packed-refs");
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
@@ -0,0 +1,154 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use toml::{Table, Value};
+
+use crate::{read_program_id, Pubkey};
+
+// Where the alias book is, relative to the home directory: a TOML file of NAME = "ADDRESS" entries, which may be
+// shared between users
+const ALIASES_PATH : &str = ".config/solpda/aliases.toml";
+
+// The alias book, read when the first alias is looked up, since a batch may look up many
+static ALIASES : OnceLock<Result<Table, String>> = OnceLock::new();
+
+fn aliases_path() -> Result<PathBuf, String>
+{
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(ALIASES_PATH))
+        .ok_or_else(|| "HOME is not set, so the alias book cannot be found".to_string())
+}
+
+// Reads the alias book, which is empty if it doesn't exist yet
+fn read_aliases() -> Result<Table, String>
+{
+    let path = aliases_path()?;
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => contents.parse::<Table>().map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Table::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e))
+    }
+}
+
+fn write_aliases(aliases : &Table) -> Result<(), String>
+{
+    let path = aliases_path()?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+
+    std::fs::write(&path, aliases.to_string()).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Alias names are limited to characters that can't be part of an address or a file name extension, so that @NAME is
+// never mistaken for anything else
+fn is_alias_name(name : &str) -> bool
+{
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || (c == '_') || (c == '-'))
+}
+
+// Whether the alias book has an alias named name
+pub fn is_alias(name : &str) -> bool
+{
+    is_alias_name(name) &&
+        ALIASES.get_or_init(read_aliases).as_ref().map(|aliases| aliases.contains_key(name)).unwrap_or(false)
+}
+
+// The address of the alias named name, as @NAME gives it
+pub fn lookup(name : &str) -> Result<Pubkey, String>
+{
+    let aliases =
+        ALIASES.get_or_init(read_aliases).as_ref().map_err(|e| format!("cannot read the alias book: {}", e))?;
+
+    match aliases.get(name) {
+        Some(Value::String(address)) => {
+            Pubkey::from_str(address).map_err(|e| format!("alias {} has an invalid address: {}", name, e))
+        },
+        Some(_) => Err(format!("alias {} is not a string", name)),
+        None => Err(format!("there is no alias named {}", name))
+    }
+}
+
+pub fn alias_command() -> Command
+{
+    Command::new("alias")
+        .about("Manages the alias book, whose names may be given as @NAME wherever an address is")
+        .subcommand_required(true)
+        .subcommand(
+            Command::new("add")
+                .about("Adds an alias for an address")
+                .arg(Arg::new("name").value_name("NAME").required(true).help("The name of the alias"))
+                .arg(
+                    Arg::new("address")
+                        .value_name("ADDRESS")
+                        .required(true)
+                        .help("The address, which may be given in any way that a program id may")
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .action(ArgAction::SetTrue)
+                        .help("Replace the alias if it already exists")
+                )
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Removes an alias")
+                .arg(Arg::new("name").value_name("NAME").required(true).help("The name of the alias"))
+        )
+        .subcommand(Command::new("list").about("Lists the aliases and their addresses"))
+}
+
+pub fn alias(matches : &ArgMatches)
+{
+    let mut aliases = read_aliases().unwrap_or_else(|e| {
+        eprintln!("Cannot read the alias book: {}", e);
+        std::process::exit(-1);
+    });
+
+    match matches.subcommand() {
+        Some(("add", matches)) => {
+            let name = matches.get_one::<String>("name").unwrap();
+            if !is_alias_name(name) {
+                eprintln!("Invalid alias name {}: names may only contain letters, digits, _, and -", name);
+                std::process::exit(-1);
+            }
+            if aliases.contains_key(name) && !matches.get_flag("force") {
+                eprintln!("There is already an alias named {}; give --force to replace it", name);
+                std::process::exit(-1);
+            }
+            let address = read_program_id(matches.get_one::<String>("address").unwrap()).unwrap_or_else(|e| {
+                eprintln!("Invalid address: {}", e);
+                std::process::exit(-1);
+            });
+            aliases.insert(name.clone(), Value::String(address.to_string()));
+        },
+
+        Some(("remove", matches)) => {
+            let name = matches.get_one::<String>("name").unwrap();
+            if aliases.remove(name).is_none() {
+                eprintln!("There is no alias named {}", name);
+                std::process::exit(1);
+            }
+        },
+
+        _ => {
+            for (name, address) in &aliases {
+                println!("{} {}", name, address.as_str().unwrap_or("(invalid)"));
+            }
+            return;
+        }
+    }
+
+    write_aliases(&aliases).unwrap_or_else(|e| {
+        eprintln!("Cannot write the alias book: {}", e);
+        std::process::exit(-1);
+    });
+}
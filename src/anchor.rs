@@ -0,0 +1,204 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::{Table, Value};
+use zeroize::Zeroizing;
+
+use crate::{key_file_pubkey, Pubkey};
+
+// A program address declared in an Anchor.toml, in a [programs.<CLUSTER>] table
+struct Declared
+{
+    cluster : String,
+
+    name : String,
+
+    address : String
+}
+
+// Finds the Anchor.toml of the workspace containing dir, which is in dir or one of its ancestors
+fn find_anchor_toml(dir : &Path) -> Option<PathBuf>
+{
+    dir.ancestors().map(|dir| dir.join("Anchor.toml")).find(|path| path.is_file())
+}
+
+fn read_anchor_toml(path : &Path) -> Result<Table, String>
+{
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    contents.parse::<Table>().map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Reads the program addresses declared in an Anchor.toml, which are either strings or tables with an address
+fn declared_programs(anchor_toml : &Table) -> Vec<Declared>
+{
+    let mut declared = vec![];
+
+    let clusters = anchor_toml.get("programs").and_then(|programs| programs.as_table());
+
+    for (cluster, programs) in clusters.into_iter().flatten() {
+        for (name, value) in programs.as_table().into_iter().flatten() {
+            let address = match value {
+                Value::String(address) => address,
+                Value::Table(table) => match table.get("address").and_then(|address| address.as_str()) {
+                    Some(address) => address,
+                    None => continue
+                },
+                _ => continue
+            };
+            declared.push(Declared { cluster : cluster.clone(), name : name.clone(), address : address.to_string() });
+        }
+    }
+
+    declared
+}
+
+// Warns about a program id read from the key file at path that looks like the wrong deploy keypair: one named
+// <NAME>-keypair.json, as Anchor names them, whose address isn't any that the workspace's Anchor.toml declares for
+// <NAME>, or otherwise one whose address Anchor.toml doesn't declare at all.  Clusters may legitimately declare
+// different addresses for the same program, so matching any of them is enough.
+pub fn key_file_warnings(
+    path : &str,
+    pubkey : &Pubkey
+) -> Vec<String>
+{
+    let path = match std::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return vec![]
+    };
+
+    let anchor_toml = match path.parent().and_then(find_anchor_toml) {
+        Some(anchor_toml) => anchor_toml,
+        None => return vec![]
+    };
+
+    let declared = read_anchor_toml(&anchor_toml).map(|table| declared_programs(&table)).unwrap_or_default();
+
+    let is_pubkey =
+        |declared : &&Declared| Pubkey::from_str(&declared.address).map(|address| address == *pubkey).unwrap_or(false);
+
+    let name = path.file_name().and_then(|name| name.to_str()).and_then(|name| name.strip_suffix("-keypair.json"));
+
+    let named = declared.iter().filter(|declared| Some(declared.name.as_str()) == name).collect::<Vec<&Declared>>();
+
+    if !named.is_empty() {
+        if named.iter().any(is_pubkey) {
+            return vec![];
+        }
+        named
+            .iter()
+            .map(|declared| {
+                format!(
+                    "{} gives the address {}, but {} declares {} as {} for {}; is it the right key file?",
+                    path.display(),
+                    pubkey,
+                    anchor_toml.display(),
+                    declared.name,
+                    declared.address,
+                    declared.cluster
+                )
+            })
+            .collect()
+    }
+    else if !declared.is_empty() && !declared.iter().any(|declared| is_pubkey(&declared)) {
+        vec![format!(
+            "{} gives the address {}, which {} does not declare for any program; is it the right key file?",
+            path.display(),
+            pubkey,
+            anchor_toml.display()
+        )]
+    }
+    else {
+        vec![]
+    }
+}
+
+// Finds the program id of the program named name, or of the only program if name is None, in the Anchor workspace
+// containing the current directory.  The address that Anchor.toml declares for the provider's cluster is used if there
+// is one, else the deploy keypair in target/deploy, else the address declared for any cluster if they all agree.
+pub fn workspace_program_id(name : Option<&str>) -> Result<Pubkey, String>
+{
+    let current_dir = std::env::current_dir().map_err(|e| e.to_string())?;
+    let anchor_toml_path = find_anchor_toml(&current_dir)
+        .ok_or_else(|| format!("no Anchor.toml in {} or any directory above it", current_dir.display()))?;
+    let anchor_toml = read_anchor_toml(&anchor_toml_path)?;
+    let declared = declared_programs(&anchor_toml);
+
+    let deploy_dir = anchor_toml_path.with_file_name("target").join("deploy");
+    let keypair_path = |name : &str| deploy_dir.join(format!("{}-keypair.json", name));
+
+    let mut names = declared.iter().map(|declared| declared.name.clone()).collect::<Vec<String>>();
+    if let Ok(entries) = std::fs::read_dir(&deploy_dir) {
+        names.extend(entries.filter_map(|entry| {
+            entry.ok()?.file_name().to_str()?.strip_suffix("-keypair.json").map(|name| name.to_string())
+        }));
+    }
+    names.sort();
+    names.dedup();
+
+    let name = match name {
+        Some(name) if names.iter().any(|known| known == name) => name.to_string(),
+        Some(name) => {
+            return Err(format!(
+                "{} has no program named {}; its programs are: {}",
+                anchor_toml_path.display(),
+                name,
+                names.join(", ")
+            ))
+        },
+        None if names.len() == 1 => names.remove(0),
+        None if names.is_empty() => {
+            return Err(format!(
+                "{} has no programs, nor are there any keypairs in target/deploy",
+                anchor_toml_path.display()
+            ))
+        },
+        None => {
+            return Err(format!(
+                "{} has more than one program, so give the name of one: {}",
+                anchor_toml_path.display(),
+                names.join(", ")
+            ))
+        },
+    };
+
+    let addresses = declared.iter().filter(|declared| declared.name == name).collect::<Vec<&Declared>>();
+    let parse = |declared : &Declared| {
+        Pubkey::from_str(&declared.address).map_err(|e| {
+            format!("{} declares an invalid address for {}: {}", anchor_toml_path.display(), declared.name, e)
+        })
+    };
+
+    let cluster = anchor_toml
+        .get("provider")
+        .and_then(|provider| provider.get("cluster"))
+        .and_then(|cluster| cluster.as_str())
+        .map(|cluster| cluster.to_lowercase());
+    if let Some(declared) = addresses.iter().find(|declared| Some(&declared.cluster) == cluster.as_ref()) {
+        return parse(declared);
+    }
+
+    let keypair_path = keypair_path(&name);
+    if keypair_path.is_file() {
+        let keypair_path = keypair_path.to_string_lossy();
+        let contents = std::fs::read_to_string(keypair_path.as_ref())
+            .map(Zeroizing::new)
+            .map_err(|e| format!("{}: {}", keypair_path, e))?;
+        return key_file_pubkey(&keypair_path, &contents);
+    }
+
+    let pubkeys = addresses.iter().map(|declared| parse(declared)).collect::<Result<Vec<Pubkey>, String>>()?;
+    match pubkeys.first() {
+        Some(pubkey) if pubkeys.iter().all(|other| other == pubkey) => Ok(*pubkey),
+        None => Err(format!("{} has no keypair for {}", deploy_dir.display(), name)),
+        _ => Err(format!(
+            "{} declares different addresses for {} for different clusters, none of which is the provider's, and \
+             there is no keypair for it in {}",
+            anchor_toml_path.display(),
+            name,
+            deploy_dir.display()
+        ))
+    }
+}
@@ -0,0 +1,1219 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::str::FromStr;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use flate2::bufread::MultiGzDecoder;
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use serde_json::{json, Value};
+
+use crate::config;
+use crate::parallel::{self, default_jobs};
+use crate::{
+    derive_pda, no_pda_error, one_of, parse_positive, parse_seeds, read_program_id_as, split_seeds, Derived,
+    Normalization, ProgramIdFormat, Pubkey, SeedLimits, NO_BUMP_SEED_STATUS
+};
+
+// The number of records that may be read ahead of those being derived
+const RECORD_BUFFER_SIZE : usize = 1024;
+
+// The number of program ids to remember before starting over, to bound memory when there are many distinct ones
+const PROGRAM_ID_CACHE_SIZE : usize = 1024;
+
+// The width of the longest bar in the --stats bump seed histogram
+const STATS_BAR_WIDTH : u64 = 40;
+
+// A single derivation to perform
+struct Record
+{
+    program_id : String,
+
+    seeds : Vec<String>,
+
+    label : Option<String>,
+
+    // The PDA, and optionally bump seed, that the record is expected to derive
+    expected : Option<(Pubkey, Option<u8>)>
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format
+{
+    Text,
+    Json,
+    Csv
+}
+
+impl Format
+{
+    pub fn from_str(s : &str) -> Option<Self>
+    {
+        match s {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> &'static str
+    {
+        match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Csv => "csv"
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SortKey
+{
+    Pda,
+    Label,
+    Bump
+}
+
+impl SortKey
+{
+    fn from_str(s : &str) -> Option<Self>
+    {
+        match s {
+            "pda" => Some(SortKey::Pda),
+            "label" => Some(SortKey::Label),
+            "bump" => Some(SortKey::Bump),
+            _ => None
+        }
+    }
+}
+
+// A derived record and its result, as output
+type Entry = (usize, Option<Record>, Result<Derived, String>);
+
+// How often the state file of a resumable run is saved
+const STATE_INTERVAL : Duration = Duration::from_secs(10);
+
+// The state of a resumable batch run, saved in a file made up of lines of the form:
+//   input <PATH>
+//   format <FORMAT>
+//   no_bump_seed       (only if --no-bump-seed was given)
+//   no_limit_check     (only if --no-limit-check was given)
+//   allow_large_seeds  (only if --allow-large-seeds was given)
+//   nul                (only if -0 was given)
+//   shard <I>/<N>      (only if --shard was given)
+//   output <PATH>
+//   records <N>        (the number of records output, or verified and left out, so far)
+//   written <N>        (how many of them were output)
+//   succeeded <N>      (how many of them were derived)
+//   failed <N>         (how many of them failed)
+//   offset <N>         (the length of the output so far, in bytes)
+//   status <N>         (the exit status so far)
+struct State
+{
+    input : String,
+
+    format : Format,
+
+    no_bump_seed : bool,
+
+    limits : SeedLimits,
+
+    nul : bool,
+
+    shard : Option<(usize, usize)>,
+
+    output : String,
+
+    records : usize,
+
+    written : usize,
+
+    succeeded : usize,
+
+    failed : usize,
+
+    offset : u64,
+
+    status : i32
+}
+
+impl State
+{
+    fn load(path : &str) -> Result<State, String>
+    {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        let mut input = None;
+        let mut format = None;
+        let mut no_bump_seed = false;
+        let mut limit_check = true;
+        let mut allow_large_seeds = false;
+        let mut nul = false;
+        let mut shard = None;
+        let mut output = None;
+        let mut records = None;
+        let mut written = None;
+        let mut succeeded = 0;
+        let mut failed = 0;
+        let mut offset = None;
+        let mut status = 0;
+
+        for (i, line) in contents.lines().enumerate() {
+            let invalid = |e : String| format!("line {}: {}", i + 1, e);
+            match line.split_once(' ').unwrap_or((line, "")) {
+                ("input", value) => input = Some(value.to_string()),
+                ("format", value) => format = Some(Format::from_str(value).ok_or_else(|| invalid(value.to_string()))?),
+                ("no_bump_seed", "") => no_bump_seed = true,
+                ("no_limit_check", "") => limit_check = false,
+                ("allow_large_seeds", "") => allow_large_seeds = true,
+                ("nul", "") => nul = true,
+                ("shard", value) => shard = Some(parse_shard(value).map_err(invalid)?),
+                ("output", value) => output = Some(value.to_string()),
+                ("records", value) => records = Some(value.parse::<usize>().map_err(|e| invalid(e.to_string()))?),
+                ("written", value) => written = Some(value.parse::<usize>().map_err(|e| invalid(e.to_string()))?),
+                ("succeeded", value) => succeeded = value.parse::<usize>().map_err(|e| invalid(e.to_string()))?,
+                ("failed", value) => failed = value.parse::<usize>().map_err(|e| invalid(e.to_string()))?,
+                ("offset", value) => offset = Some(value.parse::<u64>().map_err(|e| invalid(e.to_string()))?),
+                ("status", value) => status = value.parse::<i32>().map_err(|e| invalid(e.to_string()))?,
+                _ => return Err(invalid(format!("unexpected contents: {}", line)))
+            }
+        }
+
+        Ok(State {
+            input : input.ok_or("missing input")?,
+            format : format.ok_or("missing format")?,
+            no_bump_seed,
+            limits : SeedLimits::new(limit_check, allow_large_seeds),
+            nul,
+            shard,
+            output : output.ok_or("missing output")?,
+            records : records.ok_or("missing records")?,
+            written : written.ok_or("missing written")?,
+            succeeded,
+            failed,
+            offset : offset.ok_or("missing offset")?,
+            status
+        })
+    }
+
+    // Writes to a temporary file first and renames it into place, so that an interrupted write can never destroy the
+    // previous state
+    fn save(
+        &self,
+        path : &str
+    ) -> Result<(), String>
+    {
+        let mut contents = format!("input {}\nformat {}\n", self.input, self.format.name());
+        if self.no_bump_seed {
+            contents.push_str("no_bump_seed\n");
+        }
+        if self.limits != SeedLimits::Solana {
+            contents.push_str("no_limit_check\n");
+        }
+        if self.limits == SeedLimits::None {
+            contents.push_str("allow_large_seeds\n");
+        }
+        if self.nul {
+            contents.push_str("nul\n");
+        }
+        if let Some((index, count)) = self.shard {
+            contents.push_str(&format!("shard {}/{}\n", index, count));
+        }
+        contents.push_str(&format!(
+            "output {}\nrecords {}\nwritten {}\nsucceeded {}\nfailed {}\noffset {}\nstatus {}\n",
+            self.output, self.records, self.written, self.succeeded, self.failed, self.offset, self.status
+        ));
+
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, contents).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+    }
+}
+
+// Parses I/N, where 1 <= I <= N
+fn parse_shard(s : &str) -> Result<(usize, usize), String>
+{
+    let (index, count) = s.split_once('/').ok_or("expected I/N")?;
+    let index = index.parse::<usize>().map_err(|e| format!("invalid I {}: {}", index, e))?;
+    let count = count.parse::<usize>().map_err(|e| format!("invalid N {}: {}", count, e))?;
+
+    if (index == 0) || (index > count) {
+        return Err("I must be from 1 to N".to_string());
+    }
+
+    Ok((index, count))
+}
+
+// Reads one row of CSV, returning None at the end of input.  Fields may be quoted with double quotes, within which
+// commas and newlines are literal and a doubled double quote is a literal double quote.
+fn read_csv_row(input : &mut dyn BufRead) -> Result<Option<Vec<String>>, String>
+{
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if input.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            if quoted {
+                return Err("unterminated quoted field".to_string());
+            }
+            if fields.is_empty() && field.is_empty() {
+                return Ok(None);
+            }
+            fields.push(field);
+            return Ok(Some(fields));
+        }
+
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') => {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    }
+                    else {
+                        quoted = false;
+                    }
+                },
+                (true, c) => field.push(c),
+                (false, '"') => quoted = true,
+                (false, ',') => fields.push(std::mem::take(&mut field)),
+                (false, '\r') if chars.peek() == Some(&'\n') => (),
+                (false, '\n') => {
+                    fields.push(field);
+                    return Ok(Some(fields));
+                },
+                (false, c) => field.push(c)
+            }
+        }
+    }
+}
+
+fn csv_field(s : &str) -> String
+{
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    }
+    else {
+        s.to_string()
+    }
+}
+
+// Parses the expected PDA and bump seed of a record, either of which may be missing
+fn expected(
+    pda : Option<&str>,
+    bump : Option<&str>
+) -> Result<Option<(Pubkey, Option<u8>)>, String>
+{
+    let bump = bump.map(|bump| bump.parse::<u8>().map_err(|e| format!("invalid expected_bump {}: {}", bump, e)));
+    match (pda, bump) {
+        (Some(pda), bump) => Ok(Some((
+            Pubkey::from_str(pda).map_err(|e| format!("invalid expected_pda {}: {}", pda, e))?,
+            bump.transpose()?
+        ))),
+        (None, Some(_)) => Err("expected_bump requires expected_pda".to_string()),
+        (None, None) => Ok(None)
+    }
+}
+
+// Reads records from CSV with a header row naming its columns: program_id and seeds are required, and label,
+// expected_pda, and expected_bump are optional.  seeds is a list of seeds separated by spaces, as given on the command
+// line.
+fn csv_records(mut input : Box<dyn BufRead + Send>) -> Box<dyn Iterator<Item = Result<Record, String>> + Send>
+{
+    let header = match read_csv_row(&mut input) {
+        Ok(Some(header)) => header,
+        Ok(None) => return Box::new(std::iter::empty()),
+        Err(e) => {
+            eprintln!("Invalid CSV header: {}", e);
+            std::process::exit(-1);
+        }
+    };
+
+    let column = |name : &str| header.iter().position(|column| column.trim() == name);
+
+    let (program_id_column, seeds_column) = match (column("program_id"), column("seeds")) {
+        (Some(program_id_column), Some(seeds_column)) => (program_id_column, seeds_column),
+        _ => {
+            eprintln!("CSV header must name program_id and seeds columns");
+            std::process::exit(-1);
+        }
+    };
+    let label_column = column("label");
+    let expected_pda_column = column("expected_pda");
+    let expected_bump_column = column("expected_bump");
+
+    Box::new(
+        std::iter::from_fn(move || read_csv_row(&mut input).transpose())
+            // Skip blank lines
+            .filter(|row| row.as_ref().map(|row| row.iter().any(|field| !field.is_empty())).unwrap_or(true))
+            .map(move |row| {
+                let row = row?;
+                let field = |i : usize| row.get(i).cloned().ok_or_else(|| format!("missing column {}", i + 1));
+                // Optional columns may be left empty
+                let optional = |column : Option<usize>| {
+                    column.and_then(|i| row.get(i)).map(|field| field.trim()).filter(|field| !field.is_empty())
+                };
+                Ok(Record {
+                    program_id : field(program_id_column)?,
+                    seeds : split_seeds(&field(seeds_column)?),
+                    label : label_column.and_then(|i| row.get(i).cloned()).filter(|label| !label.is_empty()),
+                    expected : expected(optional(expected_pda_column), optional(expected_bump_column))?
+                })
+            })
+    )
+}
+
+// Calls for_each with each element of a JSON array as it is parsed, so that the array is never held in memory
+struct JsonArrayVisitor<F>(F);
+
+impl<'de, F : FnMut(Value)> Visitor<'de> for JsonArrayVisitor<F>
+{
+    type Value = ();
+
+    fn expecting(
+        &self,
+        formatter : &mut std::fmt::Formatter
+    ) -> std::fmt::Result
+    {
+        formatter.write_str("an array")
+    }
+
+    fn visit_seq<A : SeqAccess<'de>>(
+        mut self,
+        mut seq : A
+    ) -> Result<(), A::Error>
+    {
+        while let Some(entry) = seq.next_element::<Value>()? {
+            (self.0)(entry);
+        }
+        Ok(())
+    }
+}
+
+fn json_record(entry : Value) -> Result<Record, String>
+{
+    let program_id = entry["program_id"].as_str().ok_or("missing program_id")?.to_string();
+    let seeds = match &entry["seeds"] {
+        Value::String(seeds) => split_seeds(seeds),
+        Value::Array(seeds) => seeds
+            .iter()
+            .map(|seed| seed.as_str().map(|seed| seed.to_string()).ok_or("seeds must be strings"))
+            .collect::<Result<Vec<String>, &str>>()?,
+        _ => return Err("missing seeds".to_string())
+    };
+    let label = match &entry["label"] {
+        Value::Null => None,
+        Value::String(label) => Some(label.clone()),
+        label => Some(label.to_string())
+    };
+    let expected_pda = match &entry["expected_pda"] {
+        Value::Null => None,
+        expected_pda => Some(expected_pda.as_str().ok_or("expected_pda must be a string")?)
+    };
+    // The bump seed may be a number, or a string as in CSV, which expected parses
+    let expected_bump = match &entry["expected_bump"] {
+        Value::Null => None,
+        Value::Number(expected_bump) => Some(
+            expected_bump
+                .as_u64()
+                .filter(|bump| *bump <= (u8::MAX as u64))
+                .ok_or_else(|| format!("invalid expected_bump {}: must be from 0 to 255", expected_bump))?
+                .to_string()
+        ),
+        Value::String(expected_bump) => Some(expected_bump.clone()),
+        _ => return Err("expected_bump must be a number or a string".to_string())
+    };
+    let expected = expected(expected_pda, expected_bump.as_deref())?;
+    Ok(Record { program_id, seeds, label, expected })
+}
+
+// Reads records from a JSON array of objects with fields program_id and seeds (an array of seeds, or a string of
+// seeds separated by spaces), and optionally label, expected_pda, and expected_bump.  serde_json can only stream the
+// elements of an array by pushing them to a visitor, so the input is parsed on its own thread, which hands records
+// over through a bounded channel.
+fn json_records(input : Box<dyn BufRead + Send>) -> Box<dyn Iterator<Item = Result<Record, String>> + Send>
+{
+    let (sender, receiver) = mpsc::sync_channel(RECORD_BUFFER_SIZE);
+
+    std::thread::spawn(move || {
+        let mut deserializer = serde_json::Deserializer::from_reader(input);
+        let result = deserializer
+            .deserialize_seq(JsonArrayVisitor(|entry| {
+                // The receiver only goes away when the process is exiting anyway
+                let _ = sender.send(json_record(entry));
+            }))
+            .and_then(|_| deserializer.end());
+        if let Err(e) = result {
+            eprintln!("Invalid JSON input: {}", e);
+            std::process::exit(-1);
+        }
+    });
+
+    Box::new(receiver.into_iter())
+}
+
+struct Output
+{
+    format : Format,
+
+    out : BufWriter<Sink>,
+
+    // What ends each record of text output
+    terminator : char,
+
+    // The number of records output, or verified and left out, so far
+    records : usize,
+
+    // The number of those written
+    written : usize,
+
+    // If sorting, the entries to output at the end
+    sort : Option<SortKey>,
+
+    sorted : Vec<Entry>
+}
+
+impl Output
+{
+    fn begin(&mut self)
+    {
+        if let Format::Csv = self.format {
+            self.line("label,program_id,seeds,pda,bump,error".to_string());
+        }
+    }
+
+    fn line(
+        &mut self,
+        line : String
+    )
+    {
+        self.text(line + "\n");
+    }
+
+    fn text(
+        &mut self,
+        text : String
+    )
+    {
+        if let Err(e) = self.out.write_all(text.as_bytes()) {
+            eprintln!("Failed to write output: {}", e);
+            std::process::exit(-1);
+        }
+    }
+
+    // Outputs an entry, or holds on to it until the end if sorting
+    fn add(
+        &mut self,
+        entry : Entry
+    )
+    {
+        if self.sort.is_some() {
+            self.sorted.push(entry);
+        }
+        else {
+            self.write(entry.0, entry.1.as_ref(), &entry.2);
+        }
+    }
+
+    fn write(
+        &mut self,
+        number : usize,
+        record : Option<&Record>,
+        result : &Result<Derived, String>
+    )
+    {
+        match self.format {
+            Format::Text => match result {
+                Ok(derived) => {
+                    let mut line = match derived.bump_seed {
+                        Some(bump) => format!("{}.{}", derived.pda, bump),
+                        None => derived.pda.to_string()
+                    };
+                    if let Some(label) = record.and_then(|record| record.label.as_ref()) {
+                        line.push(' ');
+                        line.push_str(label);
+                    }
+                    line.push(self.terminator);
+                    self.text(line);
+                },
+                Err(e) => eprintln!("Record {}: {}", number, e)
+            },
+
+            Format::Json => {
+                let mut entry = match record {
+                    Some(record) => json!({
+                        "label" : record.label,
+                        "program_id" : record.program_id,
+                        "seeds" : record.seeds
+                    }),
+                    None => json!({})
+                };
+                match result {
+                    Ok(derived) => {
+                        entry["pda"] = json!(derived.pda.to_string());
+                        entry["bump"] = json!(derived.bump_seed);
+                    },
+                    Err(e) => entry["error"] = json!(e)
+                }
+                // Elements are written as they are derived, so the array is closed only at the end
+                let separator = if self.written == 0 { "[" } else { "," };
+                self.text(format!("{}\n  {}", separator, entry));
+            },
+
+            Format::Csv => {
+                let (label, program_id, seeds) = match record {
+                    Some(record) => {
+                        (record.label.clone().unwrap_or_default(), record.program_id.clone(), record.seeds.join(" "))
+                    },
+                    None => Default::default()
+                };
+                let (pda, bump, error) = match result {
+                    Ok(derived) => (
+                        derived.pda.to_string(),
+                        derived.bump_seed.map(|bump| bump.to_string()).unwrap_or_default(),
+                        "".into()
+                    ),
+                    Err(e) => (String::new(), String::new(), e.clone())
+                };
+                self.line(
+                    [label, program_id, seeds, pda, bump, error]
+                        .iter()
+                        .map(|f| csv_field(f))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                );
+            }
+        }
+
+        self.records += 1;
+        self.written += 1;
+    }
+
+    // Accounts for a record that matched its expected PDA, which isn't output
+    fn verified(&mut self)
+    {
+        self.records += 1;
+    }
+
+    // Writes out everything output so far, ending the current compressed frame if compressing
+    fn flush(&mut self)
+    {
+        let result =
+            self.out.flush().and_then(|_| self.out.get_mut().end_frame()).and_then(|_| self.out.get_mut().flush());
+        if let Err(e) = result {
+            eprintln!("Failed to write output: {}", e);
+            std::process::exit(-1);
+        }
+    }
+
+    // The length of the output so far, as of the last flush
+    fn offset(&self) -> u64
+    {
+        self.out.get_ref().offset()
+    }
+
+    fn end(&mut self)
+    {
+        if let Some(sort) = self.sort {
+            let mut sorted = std::mem::take(&mut self.sorted);
+            // Failed records go last, and ties stay in input order
+            match sort {
+                SortKey::Pda => sorted.sort_by_cached_key(|(_, _, result)| {
+                    result.as_ref().map(|derived| derived.pda.to_string()).map_err(|_| ())
+                }),
+                SortKey::Label => sorted.sort_by(|(_, a, a_result), (_, b, b_result)| {
+                    let key = |record : &Option<Record>, result : &Result<Derived, String>| {
+                        (result.is_err(), record.as_ref().and_then(|record| record.label.clone()).is_none())
+                    };
+                    key(a, a_result)
+                        .cmp(&key(b, b_result))
+                        .then_with(|| a.as_ref().map(|a| &a.label).cmp(&b.as_ref().map(|b| &b.label)))
+                }),
+                SortKey::Bump => sorted
+                    .sort_by_key(|(_, _, result)| result.as_ref().map(|derived| derived.bump_seed).map_err(|_| ()))
+            }
+            for (number, record, result) in sorted {
+                self.write(number, record.as_ref(), &result);
+            }
+        }
+        if let Format::Json = self.format {
+            self.line(if self.written == 0 { "[]" } else { "\n]" }.to_string());
+        }
+        self.flush();
+    }
+}
+
+// Checks a derived PDA and bump seed against those expected, ignoring the bump seed if there isn't one
+fn verify(
+    expected : (Pubkey, Option<u8>),
+    found : Derived
+) -> Result<(), String>
+{
+    if found.pda != expected.0 {
+        return Err(format!("PDA mismatch: expected {}, derived {}", expected.0, found.pda));
+    }
+    match (expected.1, found.bump_seed) {
+        (Some(expected), Some(found)) if expected != found => {
+            Err(format!("Bump seed mismatch: expected {}, derived {}", expected, found))
+        },
+        _ => Ok(())
+    }
+}
+
+// Results of derivations by program id and seeds, which --dedupe uses to avoid repeating them
+type Derivations = Mutex<HashMap<(Pubkey, Vec<Vec<u8>>), Option<Derived>>>;
+
+// Derives the PDA for record, caching parsed program ids since batches typically use only a few, and reusing earlier
+// results if derivations is given
+fn derive(
+    record : &Record,
+    program_ids : &Mutex<HashMap<String, Pubkey>>,
+    derivations : Option<&Derivations>,
+    no_bump_seed : bool,
+    limits : SeedLimits,
+    normalization : Normalization,
+    program_id_format : Option<ProgramIdFormat>
+) -> Result<Option<Derived>, String>
+{
+    let cached = program_ids.lock().unwrap().get(&record.program_id).copied();
+    let program_id = match cached {
+        Some(program_id) => program_id,
+        None => {
+            let program_id = read_program_id_as(&record.program_id, program_id_format)
+                .map_err(|e| format!("Invalid program id: {}", e))?;
+            let mut program_ids = program_ids.lock().unwrap();
+            if program_ids.len() >= PROGRAM_ID_CACHE_SIZE {
+                program_ids.clear();
+            }
+            program_ids.insert(record.program_id.clone(), program_id);
+            program_id
+        }
+    };
+
+    let seeds = parse_seeds(&record.seeds, no_bump_seed, limits, normalization, program_id_format)?;
+
+    let derivations = match derivations {
+        Some(derivations) => derivations,
+        None => return Ok(derive_pda(&program_id, &seeds, no_bump_seed))
+    };
+
+    // The lock isn't held while deriving, so threads may occasionally repeat a derivation that another is in the
+    // middle of, which is harmless
+    let key = (program_id, seeds);
+    if let Some(result) = derivations.lock().unwrap().get(&key) {
+        return Ok(*result);
+    }
+    let result = derive_pda(&program_id, &key.1, no_bump_seed);
+    derivations.lock().unwrap().insert(key, result);
+    Ok(result)
+}
+
+// A writer that counts the bytes written through it
+struct Counted
+{
+    inner : Box<dyn Write>,
+
+    count : u64
+}
+
+impl Write for Counted
+{
+    fn write(
+        &mut self,
+        buf : &[u8]
+    ) -> std::io::Result<usize>
+    {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()>
+    {
+        self.inner.flush()
+    }
+}
+
+// Where output goes, optionally compressed
+enum Sink
+{
+    Plain(Counted),
+
+    // Only None while a frame is being ended
+    Zstd(Option<zstd::Encoder<'static, Counted>>)
+}
+
+impl Sink
+{
+    fn zstd(out : Counted) -> std::io::Result<Self>
+    {
+        Ok(Sink::Zstd(Some(zstd::Encoder::new(out, 0)?)))
+    }
+
+    // Ends the current zstd frame, after which the output is complete as it stands, and anything written after goes in
+    // a new frame; concatenated frames decompress as one
+    fn end_frame(&mut self) -> std::io::Result<()>
+    {
+        if let Sink::Zstd(encoder) = self {
+            let out = encoder.take().unwrap().finish()?;
+            *self = Sink::zstd(out)?;
+        }
+        Ok(())
+    }
+
+    fn offset(&self) -> u64
+    {
+        match self {
+            Sink::Plain(counted) => counted.count,
+            Sink::Zstd(encoder) => encoder.as_ref().unwrap().get_ref().count
+        }
+    }
+}
+
+impl Write for Sink
+{
+    fn write(
+        &mut self,
+        buf : &[u8]
+    ) -> std::io::Result<usize>
+    {
+        match self {
+            Sink::Plain(counted) => counted.write(buf),
+            Sink::Zstd(encoder) => encoder.as_mut().unwrap().write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()>
+    {
+        match self {
+            Sink::Plain(counted) => counted.flush(),
+            Sink::Zstd(encoder) => encoder.as_mut().unwrap().flush()
+        }
+    }
+}
+
+// Wraps input in a decoder if it starts like gzip or zstd compressed data
+fn decompress(mut input : Box<dyn BufRead + Send>) -> Box<dyn BufRead + Send>
+{
+    let start = input.fill_buf().map(|buf| buf.to_vec()).unwrap_or_default();
+
+    if start.starts_with(&[0x1F, 0x8B]) {
+        Box::new(BufReader::new(MultiGzDecoder::new(input)))
+    }
+    else if start.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        Box::new(BufReader::new(zstd::Decoder::with_buffer(input).unwrap_or_else(|e| {
+            eprintln!("Failed to decompress input: {}", e);
+            std::process::exit(-1);
+        })))
+    }
+    else {
+        input
+    }
+}
+
+// Writes the --stats summary to stderr: the number of derivations made and how quickly, and how many PDAs were found
+// with each bump seed, which shows how much of the work went to trying bump seeds below 255
+fn print_stats(
+    derivations : u64,
+    elapsed : Duration,
+    bumps : Option<&[u64; 256]>
+)
+{
+    eprintln!("Derivations: {}", derivations);
+    eprintln!("Wall time: {:.3}s", elapsed.as_secs_f64());
+    eprintln!("Derivations per second: {:.0}", (derivations as f64) / elapsed.as_secs_f64());
+
+    let bumps = match bumps {
+        Some(bumps) => bumps,
+        None => return
+    };
+    let total = bumps.iter().sum::<u64>();
+    let most = bumps.iter().copied().max().unwrap_or(0);
+    if total == 0 {
+        return;
+    }
+
+    eprintln!("Bump seeds:");
+    for bump in (0..256).rev() {
+        let count = bumps[bump];
+        if count > 0 {
+            eprintln!(
+                "  {:>3} {:>12} {:>6.2}% {}",
+                bump,
+                count,
+                ((count as f64) * 100.0) / (total as f64),
+                "#".repeat((count * STATS_BAR_WIDTH).div_ceil(most) as usize)
+            );
+        }
+    }
+}
+
+// Opens the output file, keeping only its first offset bytes
+fn open_output(
+    path : &str,
+    offset : u64
+) -> std::fs::File
+{
+    let failed = |e : std::io::Error| -> ! {
+        eprintln!("Failed to open {}: {}", path, e);
+        std::process::exit(-1);
+    };
+
+    let mut file =
+        std::fs::OpenOptions::new().write(true).create(true).truncate(false).open(path).unwrap_or_else(|e| failed(e));
+    file.set_len(offset).unwrap_or_else(|e| failed(e));
+    file.seek(SeekFrom::End(0)).unwrap_or_else(|e| failed(e));
+    file
+}
+
+pub fn batch_command() -> Command
+{
+    let flag =
+        |name : &'static str, help : &'static str| Arg::new(name).long(name).action(ArgAction::SetTrue).help(help);
+
+    Command::new("batch")
+        .about("Derives the PDAs of the records in a file")
+        .arg(Arg::new("file").value_name("FILE").required(true).help("The records, or - for stdin"))
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(one_of(&["text", "json", "csv"], Format::from_str))
+                .help("The format of the records and of the output (default text)")
+        )
+        .arg(
+            flag("no-bump-seed", "Derive addresses with create_program_address rather than find_program_address")
+                .overrides_with("bump-seed")
+        )
+        .arg(
+            flag("bump-seed", "Derive addresses with find_program_address, whatever the config file says")
+                .overrides_with("no-bump-seed")
+        )
+        .arg(flag("no-limit-check", "Don't check seeds against Solana's limits"))
+        .arg(flag("allow-large-seeds", "With --no-limit-check, allow seeds of more than 32 bytes"))
+        .arg(
+            Arg::new("0")
+                .short('0')
+                .action(ArgAction::SetTrue)
+                .help("Separate text output with NUL rather than newline")
+        )
+        .arg(flag("unordered", "Output results as they are ready rather than in the order of the records"))
+        .arg(flag("dedupe", "Derive each distinct record only once"))
+        .arg(flag("fail-fast", "Stop at the first record that fails").overrides_with("keep-going"))
+        .arg(flag("keep-going", "Derive every record even if some fail (the default)").overrides_with("fail-fast"))
+        .arg(flag("stats", "Report statistics of the results on stderr"))
+        .arg(
+            Arg::new("compress")
+                .long("compress")
+                .value_name("FORMAT")
+                .value_parser(["zst"])
+                .help("Compress the output")
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .value_name("KEY")
+                .value_parser(one_of(&["pda", "label", "bump"], SortKey::from_str))
+                .help("Sort the output by KEY")
+        )
+        .arg(
+            Arg::new("shard")
+                .long("shard")
+                .value_name("I/N")
+                .value_parser(parse_shard)
+                .help("Derive only the Ith of every N records")
+        )
+        .arg(Arg::new("output").long("output").value_name("FILE").help("Write the output to FILE rather than stdout"))
+        .arg(
+            Arg::new("state")
+                .long("state")
+                .value_name("FILE")
+                .help("Record progress in FILE so that an interrupted run can be resumed")
+        )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .value_name("N")
+                .value_parser(parse_positive)
+                .help("The number of records to derive at once (default all cores)")
+        )
+}
+
+pub fn batch(matches : &ArgMatches)
+{
+    let config = config::load();
+
+    let format = matches.get_one::<Format>("format").copied().or(config.format).unwrap_or(Format::Text);
+    let no_bump_seed = matches.get_flag("no-bump-seed") || (config.no_bump_seed && !matches.get_flag("bump-seed"));
+    let limit_check = !(matches.get_flag("no-limit-check") || config.no_limit_check);
+    let allow_large_seeds = matches.get_flag("allow-large-seeds");
+    let nul = matches.get_flag("0");
+    let jobs = matches.get_one::<usize>("jobs").copied().or(config.jobs);
+    // batch has no options of its own for these, but follows the config file and environment as the main command does
+    let normalization = config.normalize.unwrap_or(Normalization::None);
+    let program_id_format = config.program_id_format;
+    let unordered = matches.get_flag("unordered");
+    let dedupe = matches.get_flag("dedupe");
+    let fail_fast = matches.get_flag("fail-fast");
+    let compress = matches.contains_id("compress");
+    let stats = matches.get_flag("stats");
+    let sort = matches.get_one::<SortKey>("sort").copied();
+    let shard = matches.get_one::<(usize, usize)>("shard").copied();
+    let state_path = matches.get_one::<String>("state").cloned();
+    let output_path = matches.get_one::<String>("output").cloned();
+
+    let path = matches.get_one::<String>("file").unwrap();
+
+    if allow_large_seeds && limit_check {
+        eprintln!("--allow-large-seeds can only be used with --no-limit-check");
+        std::process::exit(-1);
+    }
+    let limits = SeedLimits::new(limit_check, allow_large_seeds);
+
+    // Only text output is made up of lines; json and csv already quote whatever the records contain
+    if nul && (format != Format::Text) {
+        eprintln!("-0 can only be used with --format text");
+        std::process::exit(-1);
+    }
+
+    // Only the records output so far are skipped when resuming, so they must be output in order, and anything output
+    // after the state was last saved must be discarded, which is only possible for an output file
+    if state_path.is_some() && (unordered || sort.is_some() || output_path.is_none()) {
+        eprintln!("--state requires --output and cannot be used with --unordered or --sort");
+        std::process::exit(-1);
+    }
+
+    let mut state = State {
+        input : path.clone(),
+        format,
+        no_bump_seed,
+        limits,
+        nul,
+        shard,
+        output : output_path.clone().unwrap_or_default(),
+        records : 0,
+        written : 0,
+        succeeded : 0,
+        failed : 0,
+        offset : 0,
+        status : 0
+    };
+
+    if let Some(state_path) = &state_path {
+        if std::path::Path::new(state_path).exists() {
+            let saved = State::load(state_path).unwrap_or_else(|e| {
+                eprintln!("Invalid state file {}: {}", state_path, e);
+                std::process::exit(-1);
+            });
+            if (saved.input != state.input) ||
+                (saved.format != format) ||
+                (saved.no_bump_seed != no_bump_seed) ||
+                (saved.limits != limits) ||
+                (saved.nul != nul) ||
+                (saved.shard != shard) ||
+                (saved.output != state.output)
+            {
+                eprintln!("State file {} is for a different batch run", state_path);
+                std::process::exit(-1);
+            }
+            state = saved;
+        }
+    }
+
+    let input : Box<dyn BufRead + Send> = if path == "-" {
+        Box::new(BufReader::new(std::io::stdin()))
+    }
+    else {
+        Box::new(BufReader::new(std::fs::File::open(path).unwrap_or_else(|e| {
+            eprintln!("Failed to open {}: {}", path, e);
+            std::process::exit(-1);
+        })))
+    };
+
+    let mut input = decompress(input);
+
+    // Go by the file extension if there is one, otherwise by whether the input looks like a JSON array
+    let name = path.trim_end_matches(".gz").trim_end_matches(".zst");
+    let is_json = if name.ends_with(".json") {
+        true
+    }
+    else if name.ends_with(".csv") {
+        false
+    }
+    else {
+        input.fill_buf().map(|buf| buf.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[')).unwrap_or(false)
+    };
+
+    let records = if is_json { json_records(input) } else { csv_records(input) };
+
+    let out = Counted {
+        inner : match &output_path {
+            Some(output_path) => Box::new(open_output(output_path, state.offset)),
+            None => Box::new(std::io::stdout().lock())
+        },
+        count : state.offset
+    };
+
+    let out = if compress {
+        Sink::zstd(out).unwrap_or_else(|e| {
+            eprintln!("Failed to compress output: {}", e);
+            std::process::exit(-1);
+        })
+    }
+    else {
+        Sink::Plain(out)
+    };
+
+    // When resuming, the output carries on from where it was when the state was saved, so that the result is the same
+    // as that of an uninterrupted run
+    let mut output = Output {
+        format,
+        out : BufWriter::new(out),
+        terminator : if nul { '\0' } else { '\n' },
+        records : state.records,
+        written : state.written,
+        sort,
+        sorted : vec![]
+    };
+    let program_ids = Mutex::new(HashMap::new());
+    let derivations = if dedupe { Some(Derivations::default()) } else { None };
+    let mut last_save = Instant::now();
+    let start = Instant::now();
+    let mut derived = 0_u64;
+    let mut bumps = [0_u64; 256];
+
+    if state.records == 0 {
+        output.begin();
+    }
+
+    parallel::map(
+        records
+            .enumerate()
+            // Shards take every Nth record, so that each gets an even share without knowing the number of records
+            .filter(|(i, _)| shard.map(|(index, count)| (i % count) == (index - 1)).unwrap_or(true))
+            .skip(state.records),
+        jobs.unwrap_or_else(default_jobs),
+        !unordered,
+        |(i, record)| {
+            let result = record.as_ref().map_err(|e| e.clone()).and_then(|record| {
+                derive(
+                    record,
+                    &program_ids,
+                    derivations.as_ref(),
+                    no_bump_seed,
+                    limits,
+                    normalization,
+                    program_id_format
+                )
+            });
+            (i, record, result)
+        },
+        |(i, record, result)| {
+            if result.is_ok() {
+                derived += 1;
+            }
+            let expected = record.as_ref().ok().and_then(|record| record.expected);
+            let result = match (&record, result) {
+                (_, Ok(Some(found))) => {
+                    if let Some(bump_seed) = found.bump_seed {
+                        bumps[bump_seed as usize] += 1;
+                    }
+                    match expected.map(|expected| verify(expected, found)) {
+                        Some(Err(e)) => {
+                            if state.status == 0 {
+                                state.status = 1;
+                            }
+                            Err(e)
+                        },
+                        _ => Ok(found)
+                    }
+                },
+                (_, Ok(None)) => {
+                    // Seeds on the curve take precedence over mismatches, since they are more specific
+                    if no_bump_seed && (state.status >= 0) {
+                        state.status = NO_BUMP_SEED_STATUS;
+                    }
+                    else if state.status == 0 {
+                        state.status = 1;
+                    }
+                    Err(no_pda_error(no_bump_seed, None))
+                },
+                (Ok(_), Err(e)) => {
+                    state.status = -1;
+                    Err(e)
+                },
+                (Err(_), Err(e)) => {
+                    state.status = -1;
+                    Err(format!("Invalid record: {}", e))
+                }
+            };
+            if result.is_ok() {
+                state.succeeded += 1;
+            }
+            else {
+                state.failed += 1;
+            }
+            let proceed = result.is_ok() || !fail_fast;
+            // A record with an expected PDA is only output if it doesn't match
+            if result.is_ok() && expected.is_some() {
+                output.verified();
+            }
+            else {
+                output.add((i + 1, record.ok(), result));
+            }
+
+            if let Some(state_path) = &state_path {
+                if last_save.elapsed() >= STATE_INTERVAL {
+                    // Everything counted in the state must have been output first
+                    output.flush();
+                    state.records = output.records;
+                    state.written = output.written;
+                    state.offset = output.offset();
+                    if let Err(e) = state.save(state_path) {
+                        eprintln!("Failed to save state file {}: {}", state_path, e);
+                    }
+                    last_save = Instant::now();
+                }
+            }
+
+            proceed
+        }
+    );
+
+    output.end();
+
+    let processed = state.succeeded + state.failed;
+    eprintln!(
+        "Processed {} record{}: {} succeeded, {} failed{}",
+        processed,
+        if processed == 1 { "" } else { "s" },
+        state.succeeded,
+        state.failed,
+        if fail_fast && (state.failed > 0) { " (stopped at the first failure)" } else { "" }
+    );
+
+    // There are no bump seeds to count without them
+    if stats {
+        print_stats(derived, start.elapsed(), if no_bump_seed { None } else { Some(&bumps) });
+    }
+
+    // A complete run leaves nothing to resume
+    if let Some(state_path) = &state_path {
+        if let Err(e) = std::fs::remove_file(state_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to remove state file {}: {}", state_path, e);
+            }
+        }
+    }
+
+    std::process::exit(state.status);
+}
@@ -0,0 +1,119 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::hint::black_box;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgMatches, Command};
+use sha2::{Digest, Sha256};
+
+use crate::grind::parse_duration;
+use crate::{curve, parse_seed, Pubkey, SeedHash};
+
+const DEFAULT_DURATION : Duration = Duration::from_secs(2);
+
+// Seeds covering each kind that parse_seed handles
+const SAMPLE_SEEDS : [&str; 8] = [
+    "u8[1,2,3]",
+    "u16[65535]",
+    "u32[4294967295]",
+    "u64[18446744073709551615]",
+    "String[vault]",
+    "String[Hello, world!]",
+    "Pubkey[TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA]",
+    "Sha256[String[metadata]]"
+];
+
+pub fn bench_command() -> Command
+{
+    Command::new("bench").about("Measures how fast this machine derives PDAs").arg(
+        Arg::new("time")
+            .long("time")
+            .value_name("TIME")
+            .value_parser(|s : &str| {
+                parse_duration(s).and_then(|duration| {
+                    if duration.is_zero() {
+                        Err("must not be zero".to_string())
+                    }
+                    else {
+                        Ok(duration)
+                    }
+                })
+            })
+            .help("How long to measure each operation for, in seconds or with a unit of s, m, h, or d")
+    )
+}
+
+// Calls f repeatedly with an increasing counter for about duration, and returns the number of calls per second.  The
+// clock is only checked every so often so that reading it doesn't count against f.
+fn measure(
+    duration : Duration,
+    mut f : impl FnMut(u64)
+) -> f64
+{
+    let start = Instant::now();
+    let mut calls = 0_u64;
+
+    loop {
+        for _ in 0..256 {
+            f(calls);
+            calls += 1;
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            return (calls as f64) / elapsed.as_secs_f64();
+        }
+    }
+}
+
+fn report(
+    name : &str,
+    rate : f64
+)
+{
+    println!("{:<28} {:>14.0} per second", name, rate);
+}
+
+pub fn bench(matches : &ArgMatches)
+{
+    let duration = matches.get_one::<Duration>("time").copied().unwrap_or(DEFAULT_DURATION);
+
+    // Hashes to check for being curve points, computed up front so that hashing isn't measured along with them; about
+    // half are curve points, as for PDA candidates
+    let hashes = (0..1024_u64).map(|i| Sha256::digest(i.to_le_bytes()).into()).collect::<Vec<[u8; 32]>>();
+
+    let program_id = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+    let prefix = SeedHash::new(b"bench");
+
+    report(
+        "find_pda",
+        measure(duration, |i| {
+            let mut hash = prefix.clone();
+            hash.update(&i.to_le_bytes());
+            black_box(hash.find_pda(&program_id));
+        })
+    );
+
+    report(
+        "curve point check",
+        measure(duration, |i| {
+            black_box(curve::is_curve_point(black_box(&hashes[(i as usize) % hashes.len()])));
+        })
+    );
+
+    // The same check done by decompressing the point, as Solana itself does, for comparison
+    report(
+        "curve point decompression",
+        measure(duration, |i| {
+            black_box(ed25519_dalek::PublicKey::from_bytes(black_box(&hashes[(i as usize) % hashes.len()])).is_ok());
+        })
+    );
+
+    report(
+        "seed parser",
+        measure(duration, |i| {
+            black_box(parse_seed(black_box(SAMPLE_SEEDS[(i as usize) % SAMPLE_SEEDS.len()])).ok());
+        })
+    );
+}
@@ -0,0 +1,187 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::path::PathBuf;
+
+use toml::{Table, Value};
+
+use crate::batch::Format;
+use crate::{Normalization, ProgramIdFormat};
+
+// Where solpda's config file is, relative to the home directory
+const CONFIG_PATH : &str = ".config/solpda/config.toml";
+
+// The settings of each kind, each of which the environment variable SOLPDA_<SETTING> in upper case also gives, and
+// which take precedence over the config file.  program_id is not among them, since SOLPDA_PROGRAM_ID predates the
+// config file, and is read along with it.
+const BOOLEAN_SETTINGS : [&str; 5] = ["bytes", "no_bump_seed", "no_limit_check", "strict", "allow_keypair"];
+const NUMBER_SETTINGS : [&str; 2] = ["jobs", "threads"];
+const STRING_SETTINGS : [&str; 3] = ["format", "normalize", "program_id_format"];
+
+// Defaults for options, read from the config file and then the environment, which takes precedence over it.  Options
+// given on the command line always take precedence over both.
+#[derive(Default)]
+pub struct Config
+{
+    // Output addresses as arrays of bytes, as --bytes does
+    pub bytes : bool,
+
+    // Derive PDAs without a bump seed, as --no-bump-seed does, unless --bump gives one
+    pub no_bump_seed : bool,
+
+    // Don't check seeds against Solana's limits, as --no-limit-check does, unless --cross-check is given
+    pub no_limit_check : bool,
+
+    // Fail rather than warn if the program id is a keypair, as --strict does
+    pub strict : bool,
+
+    // Allow a keypair as the program id without a warning, as --allow-keypair does
+    pub allow_keypair : bool,
+
+    // The number of threads that --stdin and batch derive with, as --jobs gives it
+    pub jobs : Option<usize>,
+
+    // The number of threads that grind, grind-key, grind-worker, and crack search with, as --threads gives it
+    pub threads : Option<usize>,
+
+    // The format of batch's records and output, as --format gives it
+    pub format : Option<Format>,
+
+    // The Unicode form to normalize the text of String seeds to, as --normalize gives it
+    pub normalize : Option<Normalization>,
+
+    // The one format to read program ids in, as --program-id-format gives it
+    pub program_id_format : Option<ProgramIdFormat>,
+
+    // The program id to use when none is given and SOLPDA_PROGRAM_ID is not set
+    pub program_id : Option<String>
+}
+
+// Applies a setting to config, whether from the config file or the environment
+fn set(
+    config : &mut Config,
+    key : &str,
+    value : &Value
+) -> Result<(), String>
+{
+    match (key, value) {
+        ("bytes", Value::Boolean(bytes)) => config.bytes = *bytes,
+        ("no_bump_seed", Value::Boolean(no_bump_seed)) => config.no_bump_seed = *no_bump_seed,
+        ("no_limit_check", Value::Boolean(no_limit_check)) => config.no_limit_check = *no_limit_check,
+        ("strict", Value::Boolean(strict)) => config.strict = *strict,
+        ("allow_keypair", Value::Boolean(allow_keypair)) => config.allow_keypair = *allow_keypair,
+        ("jobs", Value::Integer(jobs)) if *jobs > 0 => config.jobs = Some(*jobs as usize),
+        ("threads", Value::Integer(threads)) if *threads > 0 => config.threads = Some(*threads as usize),
+        ("format", Value::String(format)) => {
+            config.format = Some(
+                Format::from_str(format)
+                    .ok_or_else(|| format!("invalid format {} (expected text, json, or csv)", format))?
+            )
+        },
+        ("normalize", Value::String(normalize)) => {
+            config.normalize = Some(
+                Normalization::from_str(normalize)
+                    .ok_or_else(|| format!("invalid normalize {} (expected nfc, nfkc, or none)", normalize))?
+            )
+        },
+        ("program_id_format", Value::String(program_id_format)) => {
+            config.program_id_format = Some(ProgramIdFormat::from_str(program_id_format).ok_or_else(|| {
+                format!(
+                    "invalid program_id_format {} (expected base58, pubkey-bytes, keypair-file, or pubkey-file)",
+                    program_id_format
+                )
+            })?)
+        },
+        ("program_id", Value::String(program_id)) => config.program_id = Some(program_id.clone()),
+        _ if BOOLEAN_SETTINGS.contains(&key) => return Err(format!("{} must be true or false", key)),
+        _ if NUMBER_SETTINGS.contains(&key) => return Err(format!("{} must be a number greater than 0", key)),
+        _ if STRING_SETTINGS.contains(&key) || (key == "program_id") => {
+            return Err(format!("{} must be a string", key))
+        },
+        _ => return Err(format!("unknown setting {}", key))
+    }
+
+    Ok(())
+}
+
+fn parse(contents : &str) -> Result<Config, String>
+{
+    let table = contents.parse::<Table>().map_err(|e| e.to_string())?;
+
+    let mut config = Config::default();
+
+    for (key, value) in &table {
+        set(&mut config, key, value)?;
+    }
+
+    Ok(config)
+}
+
+// Applies the environment variables that are set, and not empty, to config.  Those of true or false settings may also
+// be 1 or 0, as is usual for environment variables.
+fn apply_environment(config : &mut Config) -> Result<(), String>
+{
+    for key in BOOLEAN_SETTINGS.iter().chain(&NUMBER_SETTINGS).chain(&STRING_SETTINGS) {
+        let variable = format!("SOLPDA_{}", key.to_uppercase());
+
+        let value = match std::env::var(&variable) {
+            Ok(value) if !value.is_empty() => value,
+            _ => continue
+        };
+
+        let value = if BOOLEAN_SETTINGS.contains(key) {
+            match value.as_str() {
+                "true" | "1" => Value::Boolean(true),
+                "false" | "0" => Value::Boolean(false),
+                _ => return Err(format!("{}: must be true, false, 1, or 0", variable))
+            }
+        }
+        else if NUMBER_SETTINGS.contains(key) {
+            Value::Integer(value.parse::<i64>().map_err(|_| format!("{}: must be a number greater than 0", variable))?)
+        }
+        else {
+            Value::String(value)
+        };
+
+        set(config, key, &value).map_err(|e| format!("{}: {}", variable, e))?;
+    }
+
+    Ok(())
+}
+
+// Reads the config file, if there is one, exiting if it can't be read or is invalid, so that a mistake in it isn't
+// silently ignored
+fn load_file() -> Config
+{
+    let path = match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(CONFIG_PATH),
+        None => return Config::default()
+    };
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Config::default(),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            std::process::exit(-1);
+        }
+    };
+
+    parse(&contents).unwrap_or_else(|e| {
+        eprintln!("Invalid config file {}: {}", path.display(), e);
+        std::process::exit(-1);
+    })
+}
+
+// Reads the config file and then the environment, exiting if either is invalid
+pub fn load() -> Config
+{
+    let mut config = load_file();
+
+    apply_environment(&mut config).unwrap_or_else(|e| {
+        eprintln!("Invalid environment variable {}", e);
+        std::process::exit(-1);
+    });
+
+    config
+}
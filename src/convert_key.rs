@@ -0,0 +1,157 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::io::Read;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use zeroize::Zeroizing;
+
+use crate::grind::decode_hex;
+use crate::{
+    fingerprint, key_file_bytes, keypair_json, one_of, redacted_base58_error, redacted_base64_error, write_secret_file
+};
+
+// The formats of secret keys that wallets and tools use
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeyFormat
+{
+    // A JSON array of the 64 bytes of a keypair, as the Solana CLI writes key files
+    Json,
+
+    // The 64 bytes of a keypair in base58, as wallets export private keys
+    Base58,
+
+    Base64,
+
+    // The 32 byte secret key, from which the public key follows, in hex
+    Seed
+}
+
+impl KeyFormat
+{
+    fn from_str(s : &str) -> Option<Self>
+    {
+        match s {
+            "json" => Some(KeyFormat::Json),
+            "base58" => Some(KeyFormat::Base58),
+            "base64" => Some(KeyFormat::Base64),
+            "seed" => Some(KeyFormat::Seed),
+            _ => None
+        }
+    }
+}
+
+// Reads a keypair in format, checking that its public half is that of its secret half, or for a seed, completing it
+// with its public key
+fn read_key(
+    contents : &str,
+    format : KeyFormat
+) -> Result<Zeroizing<[u8; 64]>, String>
+{
+    let contents = contents.trim();
+
+    let bytes = match format {
+        KeyFormat::Json if contents.starts_with('[') => key_file_bytes(contents)?,
+        KeyFormat::Json => return Err("not a JSON array of bytes".to_string()),
+        KeyFormat::Base58 => Zeroizing::new(bs58::decode(contents).into_vec().map_err(redacted_base58_error)?),
+        KeyFormat::Base64 => Zeroizing::new(base64::decode(contents).map_err(redacted_base64_error)?),
+        KeyFormat::Seed => Zeroizing::new(decode_hex(contents)?)
+    };
+
+    let expected_len = if format == KeyFormat::Seed { 32 } else { 64 };
+    if bytes.len() != expected_len {
+        return Err(format!("has {} bytes rather than {}", bytes.len(), expected_len));
+    }
+
+    let secret_key = ed25519_dalek::SecretKey::from_bytes(&bytes[..32]).map_err(|e| e.to_string())?;
+    let public_key = ed25519_dalek::PublicKey::from(&secret_key);
+
+    if (bytes.len() == 64) && (bytes[32..] != public_key.as_bytes()[..]) {
+        return Err(
+            "has a public key (its last 32 bytes) that is not that of its secret key (its first 32 bytes)".to_string()
+        );
+    }
+
+    let mut keypair = Zeroizing::new([0_u8; 64]);
+    keypair[..32].copy_from_slice(&bytes[..32]);
+    keypair[32..].copy_from_slice(public_key.as_bytes());
+    Ok(keypair)
+}
+
+fn write_key(
+    keypair : &[u8; 64],
+    format : KeyFormat
+) -> Zeroizing<String>
+{
+    match format {
+        KeyFormat::Json => keypair_json(keypair),
+        KeyFormat::Base58 => Zeroizing::new(bs58::encode(keypair).into_string()),
+        KeyFormat::Base64 => Zeroizing::new(base64::encode(keypair)),
+        KeyFormat::Seed => Zeroizing::new(keypair[..32].iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+pub fn convert_key_command() -> Command
+{
+    let format = |name : &'static str, help : &'static str| {
+        Arg::new(name)
+            .long(name)
+            .value_name("FORMAT")
+            .required(true)
+            .value_parser(one_of(&["json", "base58", "base64", "seed"], KeyFormat::from_str))
+            .help(help)
+    };
+
+    Command::new("convert-key")
+        .about("Converts a secret key between the formats that wallets and tools use")
+        .arg(format("from", "The format of the key"))
+        .arg(format("to", "The format to convert the key to"))
+        .arg(Arg::new("out").long("out").value_name("FILE").help("Write the key to FILE rather than stdout"))
+        .arg(Arg::new("force").long("force").action(ArgAction::SetTrue).help("Overwrite FILE if it exists"))
+        .arg(Arg::new("key").value_name("KEY").required(true).help("The file holding the key, or - for stdin"))
+}
+
+// Converts a secret key from one format to another
+pub fn convert_key(matches : &ArgMatches)
+{
+    let from = *matches.get_one::<KeyFormat>("from").unwrap();
+    let to = *matches.get_one::<KeyFormat>("to").unwrap();
+    let out = matches.get_one::<String>("out").cloned();
+    let force = matches.get_flag("force");
+    let key = matches.get_one::<String>("key").unwrap();
+
+    if let Some(out) = &out {
+        if !force && std::path::Path::new(out).exists() {
+            eprintln!("{} already exists, use --force to overwrite it", out);
+            std::process::exit(-1);
+        }
+    }
+
+    // Room for any key, so that reading it doesn't leave secret bytes behind in a buffer that is outgrown
+    let mut contents = Zeroizing::new(String::with_capacity(4096));
+    let read = if key == "-" {
+        std::io::stdin().read_to_string(&mut contents).map(|_| ())
+    }
+    else {
+        std::fs::File::open(key).and_then(|mut file| file.read_to_string(&mut contents)).map(|_| ())
+    };
+    read.unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", key, e);
+        std::process::exit(-1);
+    });
+
+    let keypair = read_key(&contents, from).unwrap_or_else(|e| {
+        eprintln!("Invalid key {}: {} ({})", key, e, fingerprint(&contents));
+        std::process::exit(-1);
+    });
+
+    let converted = write_key(&keypair, to);
+
+    match out {
+        Some(out) => write_secret_file(&out, &converted, force).unwrap_or_else(|e| {
+            eprintln!("Failed to write {}: {}", out, e);
+            std::process::exit(-1);
+        }),
+        None => println!("{}", *converted)
+    }
+}
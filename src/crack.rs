@@ -0,0 +1,247 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::grind::{parse_charset, report_progress, VaryType};
+use crate::{
+    check_seed_limits, config, make_seed, parse_positive, parse_range, read_program_id, read_pubkey, split_seeds,
+    SeedHash
+};
+
+enum BumpMode
+{
+    // The canonical bump seed, as found by find_program_address
+    Canonical,
+
+    // A specific bump seed
+    Fixed(u8),
+
+    // No bump seed at all
+    None
+}
+
+pub fn crack_command() -> Command
+{
+    Command::new("crack")
+        .about("Searches for the value of an unknown seed that gives a PDA")
+        .arg(Arg::new("pda").value_name("PDA").required(true).help("The PDA whose seed is unknown"))
+        .arg(Arg::new("program_id").value_name("PROGRAM_ID").required(true).help("The program id of the PDA"))
+        .arg(
+            Arg::new("template")
+                .long("template")
+                .value_name("TEMPLATE")
+                .required(true)
+                .help("The seeds of the PDA, separated by spaces, one of which is the unknown TYPE[?]")
+        )
+        .arg(
+            Arg::new("range")
+                .long("range")
+                .value_name("RANGE")
+                .value_parser(parse_range)
+                .help("The values of a numeric unknown to try, as START..END or START..=END")
+        )
+        .arg(
+            Arg::new("charset")
+                .long("charset")
+                .value_name("CHARSET")
+                .value_parser(parse_charset)
+                .help("The characters of a String[?] unknown (default a-z0-9)")
+        )
+        .arg(
+            Arg::new("len")
+                .long("len")
+                .value_name("LEN")
+                .value_parser(parse_positive)
+                .help("The length of a String[?] unknown")
+        )
+        .arg(
+            Arg::new("threads")
+                .long("threads")
+                .value_name("N")
+                .value_parser(parse_positive)
+                .help("The number of threads to search with (default all cores)")
+        )
+        .arg(
+            Arg::new("bump")
+                .long("bump")
+                .value_name("BUMP")
+                .value_parser(clap::value_parser!(u8))
+                .help("Derive with this bump seed rather than the canonical one")
+        )
+        .arg(
+            Arg::new("no-bump-seed")
+                .long("no-bump-seed")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("bump")
+                .help("Derive without a bump seed")
+        )
+        .arg(Arg::new("no-progress").long("no-progress").action(ArgAction::SetTrue).help("Don't report progress"))
+        .arg(
+            Arg::new("no-limit-check")
+                .long("no-limit-check")
+                .action(ArgAction::SetTrue)
+                .help("Don't check seeds against Solana's limits")
+        )
+}
+
+pub fn crack(matches : &ArgMatches)
+{
+    let range = matches.get_one::<(u64, u64)>("range").copied();
+    let charset = matches.get_one::<Vec<u8>>("charset").cloned();
+    let len = matches.get_one::<usize>("len").copied();
+    let threads = matches.get_one::<usize>("threads").copied().or(config::load().threads);
+    let bump_mode = match matches.get_one::<u8>("bump") {
+        Some(bump) => BumpMode::Fixed(*bump),
+        None if matches.get_flag("no-bump-seed") => BumpMode::None,
+        None => BumpMode::Canonical
+    };
+    let progress = !matches.get_flag("no-progress");
+    let limit_check = !matches.get_flag("no-limit-check");
+
+    let target = read_pubkey(matches.get_one::<String>("pda").unwrap()).unwrap_or_else(|e| {
+        eprintln!("Invalid PDA: {}", e);
+        std::process::exit(-1);
+    });
+
+    let program_id = read_program_id(matches.get_one::<String>("program_id").unwrap()).unwrap_or_else(|e| {
+        eprintln!("Invalid program id: {}", e);
+        std::process::exit(-1);
+    });
+
+    let template = split_seeds(matches.get_one::<String>("template").unwrap());
+
+    // Exactly one seed in the template must be the unknown, of the form TYPE[?]
+    let unknowns = template.iter().enumerate().filter(|(_, seed)| seed.ends_with("[?]")).collect::<Vec<_>>();
+    if unknowns.len() != 1 {
+        eprintln!("The template must contain exactly one unknown seed of the form TYPE[?]");
+        std::process::exit(-1);
+    }
+    let unknown = unknowns[0].0;
+
+    let vary = match &template[unknown][..(template[unknown].len() - 3)] {
+        "String" => VaryType::String {
+            charset : charset.unwrap_or_else(|| parse_charset("a-z0-9").unwrap()),
+            len : len.unwrap_or_else(|| {
+                eprintln!("A String[?] unknown requires --len");
+                std::process::exit(-1);
+            })
+        },
+        seed_type => {
+            VaryType::from_str(seed_type).filter(|vary| !matches!(vary, VaryType::String { .. })).unwrap_or_else(|| {
+                eprintln!("Invalid unknown seed type {} (expected u8, u16, u32, u64, or String)", seed_type);
+                std::process::exit(-1);
+            })
+        },
+    };
+
+    let (first, last) = range.unwrap_or((0, vary.max()));
+    if last > vary.max() {
+        eprintln!("--range extends past the largest value ({}) of the unknown seed", vary.max());
+        std::process::exit(-1);
+    }
+
+    let prefix =
+        template[..unknown].iter().enumerate().map(|(i, seed)| make_seed(i + 1, seed)).collect::<Vec<Vec<u8>>>();
+    let suffix =
+        template.iter().enumerate().skip(unknown + 1).map(|(i, seed)| make_seed(i + 1, seed)).collect::<Vec<Vec<u8>>>();
+
+    if limit_check {
+        let mut unknown_seed = vec![];
+        vary.encode(first, &mut unknown_seed);
+        check_seed_limits(
+            &[prefix.as_slice(), &[unknown_seed], suffix.as_slice()].concat(),
+            matches!(bump_mode, BumpMode::None)
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+    }
+
+    let (prefix, suffix) = (prefix.concat(), suffix.concat());
+
+    let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+
+    let found = AtomicBool::new(false);
+    let result = Mutex::new(None);
+    let attempts = AtomicU64::new(0);
+    let done = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        if progress {
+            let (attempts, done) = (&attempts, &done);
+            let probability = 1.0 / (((last - first) as f64) + 1.0);
+            scope.spawn(move || report_progress(attempts, done, Some(probability)));
+        }
+
+        let workers = (0..(threads as u64))
+            .map(|t| {
+                let (vary, prefix, suffix, bump_mode) = (&vary, &prefix, &suffix, &bump_mode);
+                let (found, result, attempts) = (&found, &result, &attempts);
+                scope.spawn(move || {
+                    let prefix = SeedHash::new(prefix);
+                    let mut unknown_seed = vec![];
+                    let mut value = first.checked_add(t);
+                    let mut unreported_attempts = 0;
+
+                    while let Some(current) = value.filter(|value| *value <= last) {
+                        unreported_attempts += 1;
+                        if unreported_attempts == 1024 {
+                            attempts.fetch_add(unreported_attempts, Ordering::Relaxed);
+                            unreported_attempts = 0;
+                            if found.load(Ordering::Relaxed) {
+                                break;
+                            }
+                        }
+
+                        unknown_seed.clear();
+                        vary.encode(current, &mut unknown_seed);
+                        let mut seed = prefix.clone();
+                        seed.update(&unknown_seed);
+                        seed.update(suffix);
+
+                        // The target is a valid PDA, so when the bump seed is known, a matching hash is necessarily
+                        // off the curve and there's no need to check
+                        let is_match = match bump_mode {
+                            BumpMode::Canonical => {
+                                seed.find_pda(&program_id).map(|(pda, _)| pda == target).unwrap_or(false)
+                            },
+                            BumpMode::Fixed(bump_seed) => seed.pda_hash(&program_id, Some(*bump_seed)) == target.0,
+                            BumpMode::None => seed.pda_hash(&program_id, None) == target.0
+                        };
+
+                        if is_match {
+                            *result.lock().unwrap() = Some(current);
+                            found.store(true, Ordering::Relaxed);
+                            break;
+                        }
+
+                        value = current.checked_add(threads as u64);
+                    }
+
+                    attempts.fetch_add(unreported_attempts, Ordering::Relaxed);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers.into_iter().for_each(|worker| worker.join().unwrap());
+
+        done.store(true, Ordering::Relaxed);
+    });
+
+    match result.into_inner().unwrap() {
+        Some(value) => {
+            let mut seeds = template.clone();
+            seeds[unknown] = vary.seed_string(value);
+            println!("{}", seeds.join(" "));
+        },
+        None => {
+            eprintln!("No value in the range {}..={} reproduces {}", first, last, target);
+            std::process::exit(1);
+        }
+    }
+}
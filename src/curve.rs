@@ -0,0 +1,412 @@
+/**
+ * LICENSE: Public Domain
+ **/
+// Field elements modulo p = 2^255 - 19, as four little-endian 64 bit limbs.  Values are kept below 2^256 and are only
+// fully reduced when necessary.
+type Element = [u64; 4];
+
+const P : Element = [0xFFFFFFFFFFFFFFED, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF, 0x7FFFFFFFFFFFFFFF];
+
+// The curve constant d = -121665 / 121666
+const D : Element = [0x75EB4DCA135978A3, 0x00700A4D4141D8AB, 0x8CC740797779E898, 0x52036CEE2B6FFE73];
+
+const ONE : Element = [1, 0, 0, 0];
+
+// A fast test of whether bytes are a compressed ed25519 point, which is what determines whether a hash can be used
+// as a PDA.  Decompressing a point takes a modular square root, which costs a field exponentiation; but only whether the
+// square root exists matters here, and that can be found from the Jacobi symbol, which is computed with a binary GCD
+// style algorithm that is much cheaper.
+//
+// The result is exactly that of curve25519_dalek's CompressedEdwardsY::decompress().is_some(), which Solana uses: the
+// high bit (the sign of x) is ignored, y is taken modulo p without rejecting non-canonical encodings, and the point is
+// valid if (y^2 - 1) / (d y^2 + 1) is a square (including zero).
+pub fn is_curve_point(bytes : &[u8; 32]) -> bool
+{
+    let mut y = [0_u64; 4];
+    for (i, limb) in y.iter_mut().enumerate() {
+        *limb = u64::from_le_bytes(bytes[(i * 8)..((i + 1) * 8)].try_into().unwrap());
+    }
+    y[3] &= 0x7FFFFFFFFFFFFFFF;
+
+    let yy = mul(&y, &y);
+    let u = sub(&yy, &ONE);
+    let v = add(&mul(&yy, &D), &ONE);
+
+    // v is never zero (because -1/d is not a square), so u/v is a square exactly when u * v is
+    let uv = reduce(&mul(&u, &v));
+
+    (uv == [0; 4]) || (jacobi(uv, P) == 1)
+}
+
+// a + b (mod p), with the result below 2^256
+fn add(
+    a : &Element,
+    b : &Element
+) -> Element
+{
+    let mut r = [0_u64; 4];
+    let mut carry = 0_u64;
+    for i in 0..4 {
+        let sum = (a[i] as u128) + (b[i] as u128) + (carry as u128);
+        r[i] = sum as u64;
+        carry = (sum >> 64) as u64;
+    }
+    // 2^256 = 38 (mod p)
+    fold(&mut r, carry * 38);
+    r
+}
+
+// a - b (mod p), with the result below 2^256
+fn sub(
+    a : &Element,
+    b : &Element
+) -> Element
+{
+    let mut r = [0_u64; 4];
+    let mut borrow = 0_u64;
+    for i in 0..4 {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow);
+        r[i] = d2;
+        borrow = (b1 | b2) as u64;
+    }
+    // Each wrap around adds 2^256, which is 38 too much (mod p).  Subtracting 38 can only wrap around again if the result
+    // was tiny, and then the next subtraction can't.
+    while borrow != 0 {
+        let mut carry = 38_u64;
+        for limb in r.iter_mut() {
+            let (d, b) = limb.overflowing_sub(carry);
+            *limb = d;
+            carry = b as u64;
+        }
+        borrow = carry;
+    }
+    r
+}
+
+// a * b (mod p), with the result below 2^256
+fn mul(
+    a : &Element,
+    b : &Element
+) -> Element
+{
+    let mut wide = [0_u64; 8];
+    for i in 0..4 {
+        let mut carry = 0_u128;
+        for j in 0..4 {
+            let product = (a[i] as u128) * (b[j] as u128) + (wide[i + j] as u128) + carry;
+            wide[i + j] = product as u64;
+            carry = product >> 64;
+        }
+        wide[i + 4] = carry as u64;
+    }
+
+    // Fold the high half into the low half, since 2^256 = 38 (mod p)
+    let mut r = [0_u64; 4];
+    let mut carry = 0_u128;
+    for i in 0..4 {
+        let sum = (wide[i] as u128) + (wide[i + 4] as u128) * 38 + carry;
+        r[i] = sum as u64;
+        carry = sum >> 64;
+    }
+    fold(&mut r, (carry as u64) * 38);
+    r
+}
+
+// Adds a small value that is a multiple of 2^256 (mod p) to r, folding any carry back in once more
+fn fold(
+    r : &mut Element,
+    value : u64
+)
+{
+    let mut carry = value as u128;
+    for limb in r.iter_mut() {
+        let sum = (*limb as u128) + carry;
+        *limb = sum as u64;
+        carry = sum >> 64;
+    }
+    // A second carry is only possible if r was within 2^64 of 2^256, in which case the result is now tiny
+    r[0] += (carry as u64) * 38;
+}
+
+// Returns the unique representative of a below p
+fn reduce(a : &Element) -> Element
+{
+    let mut r = *a;
+    // a < 2^256 < 3p, so at most two subtractions are needed
+    while !less_than(&r, &P) {
+        r = subtract(&r, &P);
+    }
+    r
+}
+
+fn less_than(
+    a : &Element,
+    b : &Element
+) -> bool
+{
+    (0..4).rev().find(|i| a[*i] != b[*i]).map(|i| a[i] < b[i]).unwrap_or(false)
+}
+
+// a - b as plain integers, where a >= b
+fn subtract(
+    a : &Element,
+    b : &Element
+) -> Element
+{
+    let mut r = [0_u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (d1, b1) = a[i].overflowing_sub(b[i]);
+        let (d2, b2) = d1.overflowing_sub(borrow as u64);
+        r[i] = d2;
+        borrow = b1 | b2;
+    }
+    r
+}
+
+// The Jacobi symbol (a / n) for odd n and a < n, using the binary algorithm, which needs only subtractions and shifts.
+// Both numbers shrink as it goes, so once they fit in 128 bits it continues with native arithmetic.
+fn jacobi(
+    mut a : Element,
+    mut n : Element
+) -> i32
+{
+    let mut result = 1;
+
+    while (a[2] | a[3] | n[2] | n[3]) != 0 {
+        if a == [0; 4] {
+            // n is too large to be 1, so shares a factor with a
+            return 0;
+        }
+
+        if a[0] == 0 {
+            // Shifting by 64 bits flips the sign an even number of times
+            a = [a[1], a[2], a[3], 0];
+            continue;
+        }
+
+        let zeros = a[0].trailing_zeros();
+        if zeros > 0 {
+            a = [
+                (a[0] >> zeros) | (a[1] << (64 - zeros)),
+                (a[1] >> zeros) | (a[2] << (64 - zeros)),
+                (a[2] >> zeros) | (a[3] << (64 - zeros)),
+                a[3] >> zeros
+            ];
+            // (2 / n) is -1 exactly when n is 3 or 5 (mod 8)
+            if ((zeros % 2) == 1) && matches!(n[0] % 8, 3 | 5) {
+                result = -result;
+            }
+        }
+
+        // Both are now odd; by quadratic reciprocity, swapping them flips the sign when both are 3 (mod 4)
+        if less_than(&a, &n) {
+            std::mem::swap(&mut a, &mut n);
+            if ((a[0] % 4) == 3) && ((n[0] % 4) == 3) {
+                result = -result;
+            }
+        }
+
+        a = subtract(&a, &n);
+    }
+
+    let mut a = (a[0] as u128) | ((a[1] as u128) << 64);
+    let mut n = (n[0] as u128) | ((n[1] as u128) << 64);
+
+    while a != 0 {
+        let zeros = a.trailing_zeros();
+        a >>= zeros;
+        if ((zeros % 2) == 1) && matches!(n % 8, 3 | 5) {
+            result = -result;
+        }
+
+        if a < n {
+            std::mem::swap(&mut a, &mut n);
+            if ((a % 4) == 3) && ((n % 4) == 3) {
+                result = -result;
+            }
+        }
+
+        a -= n;
+    }
+
+    if n == 1 {
+        result
+    }
+    else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use curve25519_dalek::edwards::CompressedEdwardsY;
+    use rand::Rng;
+
+    use super::*;
+
+    // What Solana itself decides
+    fn dalek_is_curve_point(bytes : &[u8; 32]) -> bool
+    {
+        CompressedEdwardsY(*bytes).decompress().is_some()
+    }
+
+    fn to_bytes(a : &Element) -> [u8; 32]
+    {
+        let mut bytes = [0_u8; 32];
+        for (i, limb) in a.iter().enumerate() {
+            bytes[(i * 8)..((i + 1) * 8)].copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn from_u128(n : u128) -> Element
+    {
+        [n as u64, (n >> 64) as u64, 0, 0]
+    }
+
+    // Checks bytes against dalek, both as given and with the sign bit of x set
+    fn check(bytes : [u8; 32])
+    {
+        let mut signed = bytes;
+        signed[31] |= 0x80;
+        for bytes in [bytes, signed] {
+            assert_eq!(is_curve_point(&bytes), dalek_is_curve_point(&bytes), "{:?}", bytes);
+        }
+    }
+
+    // a^e (mod p), by square and multiply
+    fn pow(
+        a : &Element,
+        e : &Element
+    ) -> Element
+    {
+        let mut r = ONE;
+        for i in (0..256).rev() {
+            r = mul(&r, &r);
+            if ((e[i / 64] >> (i % 64)) & 1) == 1 {
+                r = mul(&r, a);
+            }
+        }
+        reduce(&r)
+    }
+
+    // The Jacobi symbol by the textbook algorithm, for comparison with the binary one
+    fn slow_jacobi(
+        mut a : u128,
+        mut n : u128
+    ) -> i32
+    {
+        let mut result = 1;
+        a %= n;
+        while a != 0 {
+            while (a & 1) == 0 {
+                a /= 2;
+                if matches!(n % 8, 3 | 5) {
+                    result = -result;
+                }
+            }
+            std::mem::swap(&mut a, &mut n);
+            if ((a % 4) == 3) && ((n % 4) == 3) {
+                result = -result;
+            }
+            a %= n;
+        }
+        if n == 1 {
+            result
+        }
+        else {
+            0
+        }
+    }
+
+    #[test]
+    fn special_y()
+    {
+        // y = 0, 1, and -1 are all on the curve: y = 1 and y = -1 give x = 0, and y = 0 gives x^2 = -1, a square
+        let minus_one = subtract(&P, &ONE);
+        for y in [[0; 4], ONE, minus_one] {
+            assert!(is_curve_point(&to_bytes(&y)));
+            check(to_bytes(&y));
+        }
+
+        // Every small y, and every y just below p
+        for n in 0..256 {
+            check(to_bytes(&from_u128(n)));
+            check(to_bytes(&subtract(&P, &from_u128(n + 1))));
+        }
+    }
+
+    #[test]
+    fn non_canonical_y()
+    {
+        // y from p to 2^255 - 1 is taken modulo p rather than rejected, so is on the curve exactly when y - p is
+        for n in 0..19 {
+            let y = add(&P, &from_u128(n));
+            let bytes = to_bytes(&y);
+            assert!(!less_than(&y, &P));
+            assert_eq!(is_curve_point(&bytes), is_curve_point(&to_bytes(&from_u128(n))));
+            check(bytes);
+        }
+    }
+
+    #[test]
+    fn random_bytes()
+    {
+        let mut rng = rand::thread_rng();
+        let mut on_curve = 0;
+        for _ in 0..10000 {
+            let mut bytes = [0_u8; 32];
+            rng.fill(&mut bytes);
+            assert_eq!(is_curve_point(&bytes), dalek_is_curve_point(&bytes), "{:?}", bytes);
+            on_curve += is_curve_point(&bytes) as u32;
+        }
+        // About half of all bytes are points; far fewer or far more means that one side of the check is broken
+        assert!((4000..6000).contains(&on_curve), "{} of 10000 on the curve", on_curve);
+    }
+
+    #[test]
+    fn jacobi_small()
+    {
+        // Small numbers are handled by the 128 bit tail alone
+        for n in (1..300_u128).step_by(2) {
+            for a in 0..n {
+                assert_eq!(jacobi(from_u128(a), from_u128(n)), slow_jacobi(a, n), "({} / {})", a, n);
+            }
+        }
+    }
+
+    #[test]
+    fn jacobi_128_bit()
+    {
+        // Numbers near 2^128 go straight to the 128 bit tail, without the wide loop
+        let mut rng = rand::thread_rng();
+        for _ in 0..10000 {
+            let n = rng.gen::<u128>() | 1;
+            let a = rng.gen::<u128>() % n;
+            assert_eq!(jacobi(from_u128(a), from_u128(n)), slow_jacobi(a, n), "({} / {})", a, n);
+        }
+    }
+
+    #[test]
+    fn jacobi_modulo_p()
+    {
+        // Euler's criterion: a^((p - 1) / 2) is 1 for squares and -1 for non-squares
+        let half = [0xFFFFFFFFFFFFFFF6, 0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF, 0x3FFFFFFFFFFFFFFF];
+        let minus_one = subtract(&P, &ONE);
+        let mut rng = rand::thread_rng();
+        for i in 0..1000 {
+            // Small values and random ones, which start in the wide loop and finish in the 128 bit tail
+            let a = if i < 100 { from_u128(i as u128) } else { reduce(&[rng.gen(), rng.gen(), rng.gen(), rng.gen()]) };
+            let expected = match pow(&a, &half) {
+                r if r == [0; 4] => 0,
+                r if r == ONE => 1,
+                r if r == minus_one => -1,
+                r => panic!("{:?} is not 0, 1, or -1", r)
+            };
+            assert_eq!(jacobi(a, P), expected, "{:?}", a);
+        }
+    }
+}
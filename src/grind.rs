@@ -0,0 +1,1506 @@
+/**
+ * LICENSE: Public Domain
+ **/
+mod distributed;
+
+pub use distributed::{grind_worker, grind_worker_command};
+
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+
+use crate::{
+    check_seed_limits, config, make_seed, one_of, parse_positive, read_program_id, values_in_order, write_keypair_file,
+    Pubkey, SeedHash, BASE58_ALPHABET
+};
+
+// The type of the seed that is varied during a search, which maps each value in the search space to seed bytes
+#[derive(Clone)]
+pub enum VaryType
+{
+    U8,
+    U16,
+    U32,
+    U64,
+    // Strings of exactly len characters from charset
+    String
+    {
+        charset : Vec<u8>,
+        len : usize
+    }
+}
+
+impl VaryType
+{
+    pub fn from_str(s : &str) -> Option<Self>
+    {
+        match s {
+            "u8" => Some(VaryType::U8),
+            "u16" => Some(VaryType::U16),
+            "u32" => Some(VaryType::U32),
+            "u64" => Some(VaryType::U64),
+            // The charset and length are filled in once all options have been read
+            "string" => Some(VaryType::String { charset : vec![], len : 0 }),
+            _ => None
+        }
+    }
+
+    pub fn max(&self) -> u64
+    {
+        match self {
+            VaryType::U8 => u8::MAX as u64,
+            VaryType::U16 => u16::MAX as u64,
+            VaryType::U32 => u32::MAX as u64,
+            VaryType::U64 => u64::MAX,
+            // If there are more strings than values in a u64, only the first 2^64 can be searched
+            VaryType::String { charset, len } => {
+                (charset.len() as u64).checked_pow(*len as u32).map(|count| count - 1).unwrap_or(u64::MAX)
+            },
+        }
+    }
+
+    // Describes the type completely, so that checkpoints can verify that they are resumed with the same type
+    fn name(&self) -> String
+    {
+        match self {
+            VaryType::U8 => "u8".to_string(),
+            VaryType::U16 => "u16".to_string(),
+            VaryType::U32 => "u32".to_string(),
+            VaryType::U64 => "u64".to_string(),
+            VaryType::String { charset, len } => format!("string {} {}", String::from_utf8_lossy(charset), len)
+        }
+    }
+
+    // Appends the encoding of value, exactly as the corresponding seed type would encode it.  String values are
+    // treated as numbers in base charset.len(), most significant digit first, so that values are ordered in the same
+    // way as their strings.
+    pub fn encode(
+        &self,
+        value : u64,
+        into : &mut Vec<u8>
+    )
+    {
+        match self {
+            VaryType::U8 => into.push(value as u8),
+            VaryType::U16 => into.extend_from_slice(&(value as u16).to_le_bytes()),
+            VaryType::U32 => into.extend_from_slice(&(value as u32).to_le_bytes()),
+            VaryType::U64 => into.extend_from_slice(&value.to_le_bytes()),
+            VaryType::String { charset, len } => {
+                let start = into.len();
+                into.resize(start + len, charset[0]);
+                let base = charset.len() as u64;
+                let mut value = value;
+                for i in (start..(start + len)).rev() {
+                    into[i] = charset[(value % base) as usize];
+                    value /= base;
+                }
+            }
+        }
+    }
+
+    // Formats value as a seed specifier
+    pub fn seed_string(
+        &self,
+        value : u64
+    ) -> String
+    {
+        match self {
+            VaryType::String { .. } => {
+                let mut bytes = vec![];
+                self.encode(value, &mut bytes);
+                format!("String[{}]", String::from_utf8_lossy(&bytes))
+            },
+            _ => format!("{}[{}]", self.name(), value)
+        }
+    }
+}
+
+// Parses a charset specification such as a-z0-9_, which consists of single characters and ranges of characters.  A
+// '-' that is first or last is taken literally.  Only printable ASCII characters are allowed, so that every generated
+// string has the requested length in bytes.
+pub fn parse_charset(spec : &str) -> Result<Vec<u8>, String>
+{
+    let bytes = spec.as_bytes();
+
+    if let Some(c) = spec.chars().find(|c| !c.is_ascii_graphic()) {
+        return Err(format!("'{}' is not a printable ASCII character", c));
+    }
+
+    let mut charset = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if ((i + 2) < bytes.len()) && (bytes[i + 1] == b'-') {
+            if bytes[i] > bytes[i + 2] {
+                return Err(format!("invalid range {}", &spec[i..(i + 3)]));
+            }
+            charset.extend(bytes[i]..=bytes[i + 2]);
+            i += 3;
+        }
+        else {
+            charset.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    // Keep the order given, but drop duplicates, which would only cause the same strings to be tried repeatedly
+    let mut seen = [false; 256];
+    charset.retain(|c| !std::mem::replace(&mut seen[*c as usize], true));
+
+    if charset.len() < 2 {
+        return Err("a charset needs at least two characters".to_string());
+    }
+
+    Ok(charset)
+}
+
+enum Pattern
+{
+    StartsWith(String),
+    EndsWith(String),
+    Contains(String),
+    Matches(regex::Regex)
+}
+
+impl Pattern
+{
+    // Creates a pattern from its command line option and value.  Literal patterns are lower-cased if ignore_case is
+    // set, and must then be compared against lower-cased addresses.
+    fn new(
+        option : &str,
+        value : &str,
+        ignore_case : bool
+    ) -> Pattern
+    {
+        if option == "--matches" {
+            return Pattern::Matches(
+                regex::RegexBuilder::new(value).case_insensitive(ignore_case).build().unwrap_or_else(|e| {
+                    eprintln!("Invalid --matches regular expression: {}", e);
+                    std::process::exit(-1);
+                })
+            );
+        }
+
+        validate_base58_pattern(value, ignore_case);
+
+        let value = if ignore_case { value.to_lowercase() } else { value.to_string() };
+
+        match option {
+            "--starts-with" => Pattern::StartsWith(value),
+            "--ends-with" => Pattern::EndsWith(value),
+            _ => Pattern::Contains(value)
+        }
+    }
+
+    fn is_match(
+        &self,
+        address : &str,
+        lowercase_address : Option<&str>
+    ) -> bool
+    {
+        let literal_address = lowercase_address.unwrap_or(address);
+
+        match self {
+            Pattern::StartsWith(s) => literal_address.starts_with(s.as_str()),
+            Pattern::EndsWith(s) => literal_address.ends_with(s.as_str()),
+            Pattern::Contains(s) => literal_address.contains(s.as_str()),
+            Pattern::Matches(r) => r.is_match(address)
+        }
+    }
+
+    // Returns the approximate probability that a random address matches this pattern, or None if it cannot be
+    // computed (for regular expressions)
+    fn probability(
+        &self,
+        ignore_case : bool
+    ) -> Option<f64>
+    {
+        let (text, variants) = match self {
+            Pattern::StartsWith(s) | Pattern::EndsWith(s) | Pattern::Contains(s) => (s, case_variants(s, ignore_case)),
+            Pattern::Matches(_) => return None
+        };
+
+        let per_position = (variants.len() as f64) / 58_f64.powi(text.len() as i32);
+
+        Some(match self {
+            // Leading characters of Base58 addresses are far from uniformly distributed, so compute prefix
+            // probabilities exactly (when there aren't too many case variants to consider)
+            Pattern::StartsWith(_) => {
+                if variants.len() <= 4096 {
+                    variants.iter().map(|v| prefix_probability(v)).sum()
+                }
+                else {
+                    prefix_probability(text) * (variants.len() as f64)
+                }
+            },
+            Pattern::EndsWith(_) => per_position,
+            // Most addresses are 44 characters long
+            _ => per_position * ((45 - text.len().min(44)) as f64)
+        })
+    }
+}
+
+// Returns all strings that can be formed from s by changing the case of its letters (when ignore_case is set) such
+// that every character is still in the Base58 alphabet.
+fn case_variants(
+    s : &str,
+    ignore_case : bool
+) -> Vec<String>
+{
+    let mut variants = vec![String::new()];
+
+    for c in s.chars() {
+        let mut choices = vec![c];
+        if ignore_case {
+            choices = vec![c.to_ascii_lowercase(), c.to_ascii_uppercase()];
+            choices.dedup();
+            choices.retain(|c| BASE58_ALPHABET.contains(*c));
+        }
+        // Don't let the number of variants get out of hand; past this point only the count matters
+        if variants.len() > 4096 {
+            let count = variants.len() * choices.len();
+            variants.resize(count, s.to_string());
+            continue;
+        }
+        variants = variants
+            .iter()
+            .flat_map(|v| {
+                choices.iter().map(move |c| {
+                    let mut v = v.clone();
+                    v.push(*c);
+                    v
+                })
+            })
+            .collect();
+    }
+
+    variants
+}
+
+// Computes the fraction of 256 bit values whose Base58 encoding starts with prefix
+fn prefix_probability(prefix : &str) -> f64
+{
+    let total = 2_f64.powi(256);
+
+    let digits = prefix.chars().map(|c| BASE58_ALPHABET.find(c).unwrap_or(0) as f64);
+
+    // Leading '1' characters encode leading zero bytes, which are too rare to matter for any practical search
+    if prefix.starts_with('1') {
+        return 1.0 / 58_f64.powi(prefix.len() as i32);
+    }
+
+    let value = digits.fold(0_f64, |acc, d| (acc * 58.0) + d);
+
+    // Sum, over each possible address length, the count of values of that length that start with the prefix
+    (prefix.len()..=44)
+        .map(|len| {
+            let scale = 58_f64.powi((len - prefix.len()) as i32);
+            let low = value * scale;
+            let high = ((value + 1.0) * scale).min(total);
+            if low < high {
+                high - low
+            }
+            else {
+                0.0
+            }
+        })
+        .sum::<f64>() /
+        total
+}
+
+struct Matcher
+{
+    patterns : Vec<Pattern>,
+
+    // The command line form of each pattern, for output
+    descriptions : Vec<String>,
+
+    ignore_case : bool
+}
+
+impl Matcher
+{
+    // Creates a matcher from (option, value) pairs as given on the command line
+    fn new(
+        raw_patterns : &[(String, String)],
+        ignore_case : bool
+    ) -> Matcher
+    {
+        Matcher {
+            patterns : raw_patterns.iter().map(|(option, value)| Pattern::new(option, value, ignore_case)).collect(),
+            descriptions : raw_patterns.iter().map(|(option, value)| format!("{} {}", option, value)).collect(),
+            ignore_case
+        }
+    }
+
+    // Returns the indexes of all patterns that match address
+    fn matches(
+        &self,
+        address : &str
+    ) -> Vec<usize>
+    {
+        let lowercase_address = if self.ignore_case { Some(address.to_lowercase()) } else { None };
+
+        (0..self.patterns.len()).filter(|i| self.patterns[*i].is_match(address, lowercase_address.as_deref())).collect()
+    }
+
+    // Returns the approximate probability that a random address matches any pattern, if known
+    fn probability(&self) -> Option<f64>
+    {
+        self.patterns
+            .iter()
+            .map(|pattern| pattern.probability(self.ignore_case))
+            .sum::<Option<f64>>()
+            .map(|probability| probability.min(1.0))
+    }
+}
+
+// The options giving patterns for addresses to match, of which there must be at least one
+const PATTERN_OPTIONS : [&str; 4] = ["starts-with", "ends-with", "contains", "matches"];
+
+// Adds the options giving patterns for addresses to match, which grind and grind-key share
+fn with_pattern_args(command : Command) -> Command
+{
+    let pattern = |name : &'static str, value_name : &'static str, help : &'static str| {
+        Arg::new(name).long(name).value_name(value_name).action(ArgAction::Append).help(help)
+    };
+
+    command
+        .arg(pattern("starts-with", "TEXT", "Match addresses that start with TEXT"))
+        .arg(pattern("ends-with", "TEXT", "Match addresses that end with TEXT"))
+        .arg(pattern("contains", "TEXT", "Match addresses that contain TEXT"))
+        .arg(pattern("matches", "REGEX", "Match addresses that match the regular expression REGEX"))
+        .group(ArgGroup::new("patterns").args(PATTERN_OPTIONS).required(true).multiple(true))
+        .arg(
+            Arg::new("ignore-case")
+                .long("ignore-case")
+                .action(ArgAction::SetTrue)
+                .help("Match patterns without regard to case")
+        )
+}
+
+// The patterns given, as (option, value) pairs in the order given, as Matcher::new takes them
+fn raw_patterns(matches : &ArgMatches) -> Vec<(String, String)>
+{
+    values_in_order(matches, &PATTERN_OPTIONS).into_iter().map(|(id, value)| (format!("--{}", id), value)).collect()
+}
+
+// The options for how a search uses the machine, which grind, grind-key, and grind-worker share
+fn machine_args() -> [Arg; 3]
+{
+    [
+        Arg::new("threads")
+            .long("threads")
+            .value_name("N")
+            .value_parser(parse_positive)
+            .help("The number of threads to search with (default all cores)"),
+        Arg::new("nice").long("nice").action(ArgAction::SetTrue).help("Search at the lowest scheduling priority"),
+        Arg::new("pin")
+            .long("pin")
+            .value_name("CPUS")
+            .value_parser(parse_cpu_list)
+            .help("Search only on these CPUs, such as 0-3,6")
+    ]
+}
+
+fn no_progress_arg() -> Arg
+{
+    Arg::new("no-progress").long("no-progress").action(ArgAction::SetTrue).help("Don't report progress")
+}
+
+fn validate_base58_pattern(
+    pattern : &str,
+    ignore_case : bool
+)
+{
+    if pattern.is_empty() {
+        eprintln!("Empty grind pattern");
+        std::process::exit(-1);
+    }
+
+    let is_base58 = |c : char| {
+        BASE58_ALPHABET.contains(c) ||
+            (ignore_case &&
+                (BASE58_ALPHABET.contains(c.to_ascii_lowercase()) ||
+                    BASE58_ALPHABET.contains(c.to_ascii_uppercase())))
+    };
+
+    if let Some(c) = pattern.chars().find(|c| !is_base58(*c)) {
+        eprintln!("Grind pattern {} contains '{}', which can never appear in a Base58 address", pattern, c);
+        std::process::exit(-1);
+    }
+}
+
+pub fn grind_command() -> Command
+{
+    let option = |name : &'static str, value_name : &'static str, help : &'static str| {
+        Arg::new(name).long(name).value_name(value_name).help(help)
+    };
+
+    with_pattern_args(Command::new("grind").about("Searches for a PDA whose address matches patterns"))
+        .arg(
+            Arg::new("args")
+                .value_name("ARGS")
+                .num_args(1..)
+                .required_unless_present("estimate")
+                .help("The program id and the fixed seeds, which the varying seed follows")
+        )
+        .arg(
+            option("vary", "TYPE", "The type of the varying seed")
+                .value_parser(one_of(&["u8", "u16", "u32", "u64", "string"], VaryType::from_str))
+                .required_unless_present("estimate")
+        )
+        .arg(
+            option("charset", "CHARSET", "The characters of a string varying seed (default a-z0-9)")
+                .value_parser(parse_charset)
+        )
+        .arg(option("len", "LEN", "The length of a string varying seed").value_parser(parse_positive))
+        .arg(
+            option("require-bump", "BUMP", "Only match PDAs with this bump seed").value_parser(clap::value_parser!(u8))
+        )
+        .arg(
+            option("count", "N", "Find N addresses matching each pattern rather than one").value_parser(parse_positive)
+        )
+        .args(machine_args())
+        .arg(no_progress_arg())
+        .arg(option("checkpoint", "FILE", "Save the progress of the search to FILE"))
+        .arg(option("resume", "FILE", "Resume the search saved in FILE"))
+        .arg(option("max-attempts", "N", "Stop after trying N seeds").value_parser(clap::value_parser!(u64)))
+        .arg(
+            option("max-time", "TIME", "Stop after TIME, in seconds or with a unit of s, m, h, or d")
+                .value_parser(parse_duration)
+        )
+        .arg(
+            option("grind-seed", "N", "Try seeds in a random order determined by N")
+                .value_parser(clap::value_parser!(u64))
+        )
+        .arg(
+            Arg::new("estimate")
+                .long("estimate")
+                .action(ArgAction::SetTrue)
+                .help("Estimate how long the search would take rather than searching")
+        )
+        .arg(option("serve", "ADDRESS", "Coordinate grind-worker processes that connect to ADDRESS"))
+        .arg(
+            option("chunk-size", "N", "With --serve, hand out N seeds to workers at a time")
+                .value_parser(clap::value_parser!(u64).range(1..))
+        )
+        .arg(
+            Arg::new("no-limit-check")
+                .long("no-limit-check")
+                .action(ArgAction::SetTrue)
+                .help("Don't check seeds against Solana's limits")
+        )
+}
+
+pub fn grind(matches : &ArgMatches)
+{
+    let vary = matches.get_one::<VaryType>("vary").cloned();
+    let raw_patterns = raw_patterns(matches);
+    let ignore_case = matches.get_flag("ignore-case");
+    let threads = matches.get_one::<usize>("threads").copied().or(config::load().threads);
+    let progress = !matches.get_flag("no-progress");
+    let checkpoint_path = matches.get_one::<String>("checkpoint").cloned();
+    let resume_path = matches.get_one::<String>("resume").cloned();
+    let max_attempts = matches.get_one::<u64>("max-attempts").copied();
+    let max_time = matches.get_one::<Duration>("max-time").copied();
+    let count = matches.get_one::<usize>("count").copied();
+    let charset = matches.get_one::<Vec<u8>>("charset").cloned();
+    let len = matches.get_one::<usize>("len").copied();
+    let require_bump = matches.get_one::<u8>("require-bump").copied();
+    let serve = matches.get_one::<String>("serve").cloned();
+    let chunk_size = matches.get_one::<u64>("chunk-size").copied();
+    let estimate = matches.get_flag("estimate");
+    let grind_seed = matches.get_one::<u64>("grind-seed").copied();
+    let nice = matches.get_flag("nice");
+    let pin = matches.get_one::<Vec<usize>>("pin");
+    let limit_check = !matches.get_flag("no-limit-check");
+    let mut positional : Vec<String> =
+        matches.get_many::<String>("args").map(|args| args.cloned().collect()).unwrap_or_default();
+
+    run_in_background(nice, pin.map(|pin| pin.as_slice()));
+
+    // Estimates don't depend much on the seeds, so they can be made for the patterns alone
+    let vary = match vary.unwrap_or(VaryType::U64) {
+        VaryType::String { .. } => VaryType::String {
+            charset : charset.unwrap_or_else(|| parse_charset("a-z0-9").unwrap()),
+            len : len.unwrap_or_else(|| {
+                eprintln!("--vary string requires --len");
+                std::process::exit(-1);
+            })
+        },
+        vary => {
+            if charset.is_some() || len.is_some() {
+                eprintln!("--charset and --len can only be used with --vary string");
+                std::process::exit(-1);
+            }
+            vary
+        }
+    };
+
+    let matcher = Matcher::new(&raw_patterns, ignore_case);
+
+    let program_id = if positional.is_empty() {
+        // The system program
+        Pubkey([0; 32])
+    }
+    else {
+        read_program_id(&positional.remove(0)).unwrap_or_else(|e| {
+            eprintln!("Invalid program id: {}", e);
+            std::process::exit(-1);
+        })
+    };
+
+    let fixed_seeds = positional.iter().enumerate().map(|(i, seed)| make_seed(i + 1, seed)).collect::<Vec<Vec<u8>>>();
+
+    // The varying seed follows the fixed seeds, and is always followed by a bump seed
+    if limit_check {
+        let mut vary_seed = vec![];
+        vary.encode(0, &mut vary_seed);
+        check_seed_limits(&[fixed_seeds.as_slice(), &[vary_seed]].concat(), false).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+    }
+
+    let fixed_seed = fixed_seeds.concat();
+
+    let mut search = Search {
+        program_id,
+        fixed_seed,
+        last : vary.max(),
+        order : grind_seed.map(|grind_seed| Order::new(grind_seed, vary.max())),
+        vary,
+        matcher,
+        cursors : vec![],
+        attempts : 0,
+        progress,
+        checkpoint_path : None,
+        max_attempts,
+        max_time,
+        require_bump,
+        count
+    };
+
+    if estimate {
+        if serve.is_some() || checkpoint_path.is_some() || resume_path.is_some() {
+            eprintln!("--serve, --checkpoint, and --resume cannot be used with --estimate");
+            std::process::exit(-1);
+        }
+        search.estimate(threads.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)));
+        return;
+    }
+
+    if let Some(address) = serve {
+        // Workers decide how to split up their own ranges, and the coordinator's state isn't checkpointed
+        if threads.is_some() || checkpoint_path.is_some() || resume_path.is_some() {
+            eprintln!("--threads, --checkpoint, and --resume cannot be used with --serve");
+            std::process::exit(-1);
+        }
+        distributed::serve(search, &raw_patterns, &address, chunk_size.unwrap_or(distributed::DEFAULT_CHUNK_SIZE));
+        return;
+    }
+
+    if chunk_size.is_some() {
+        eprintln!("--chunk-size can only be used with --serve");
+        std::process::exit(-1);
+    }
+
+    match &resume_path {
+        Some(resume_path) => {
+            let checkpoint = Checkpoint::load(resume_path).unwrap_or_else(|e| {
+                eprintln!("Invalid checkpoint file {}: {}", resume_path, e);
+                std::process::exit(-1);
+            });
+            if (checkpoint.program_id != search.program_id) ||
+                (checkpoint.fixed_seed != search.fixed_seed) ||
+                (checkpoint.vary != search.vary.name()) ||
+                (checkpoint.grind_seed != grind_seed)
+            {
+                eprintln!(
+                    "Checkpoint file {} was written for a different program id, seeds, --vary type, or --grind-seed",
+                    resume_path
+                );
+                std::process::exit(-1);
+            }
+            // The thread count determines how the seed space is split up, so it must be the same as before
+            if threads.map(|threads| threads != checkpoint.cursors.len()).unwrap_or(false) {
+                eprintln!("Checkpoint file {} was written using --threads {}", resume_path, checkpoint.cursors.len());
+                std::process::exit(-1);
+            }
+            search.cursors = checkpoint.cursors;
+            search.attempts = checkpoint.attempts;
+        },
+        None => {
+            let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+            search.cursors = (0..(threads as u64)).collect();
+        }
+    }
+
+    // When resuming, keep updating the same checkpoint file unless told otherwise
+    search.checkpoint_path = checkpoint_path.or(resume_path);
+
+    let (results, outcome) = search.run();
+
+    search.output(&results, outcome);
+}
+
+// Parses a list of CPU numbers such as 0-3,6, which consists of single numbers and ranges of numbers
+fn parse_cpu_list(s : &str) -> Result<Vec<usize>, String>
+{
+    let mut cpus = vec![];
+
+    for part in s.split(',') {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let first = first.parse::<usize>().map_err(|e| format!("invalid CPU {}: {}", first, e))?;
+        let last = last.parse::<usize>().map_err(|e| format!("invalid CPU {}: {}", last, e))?;
+        if first > last {
+            return Err(format!("invalid CPU range {}", part));
+        }
+        cpus.extend(first..=last);
+    }
+
+    Ok(cpus)
+}
+
+// Makes a long search friendlier to other work on the machine: if nice is set, gives the process the lowest scheduling
+// priority, and if pin is given, restricts it to those CPUs.  Threads inherit both from the thread that creates them,
+// so this must be called before any are created; it also makes the default number of threads the number of CPUs given.
+fn run_in_background(
+    nice : bool,
+    pin : Option<&[usize]>
+)
+{
+    if nice {
+        #[cfg(unix)]
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } != 0 {
+            eprintln!("Failed to lower priority: {}", std::io::Error::last_os_error());
+        }
+
+        #[cfg(not(unix))]
+        eprintln!("--nice is not supported on this platform");
+    }
+
+    if let Some(cpus) = pin {
+        #[cfg(target_os = "linux")]
+        {
+            let mut set : libc::cpu_set_t = unsafe { std::mem::zeroed() };
+            for cpu in cpus {
+                if *cpu >= (libc::CPU_SETSIZE as usize) {
+                    eprintln!("Invalid CPU {}", cpu);
+                    std::process::exit(-1);
+                }
+                unsafe { libc::CPU_SET(*cpu, &mut set) };
+            }
+            if unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) } != 0 {
+                eprintln!("Failed to pin to CPUs {:?}: {}", cpus, std::io::Error::last_os_error());
+                std::process::exit(-1);
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            eprintln!("--pin is only supported on Linux");
+            std::process::exit(-1);
+        }
+    }
+}
+
+pub fn grind_key_command() -> Command
+{
+    with_pattern_args(Command::new("grind-key").about("Generates keypairs until one's public key matches patterns"))
+        .args(machine_args())
+        .arg(no_progress_arg())
+        .arg(Arg::new("out").long("out").value_name("FILE").help("The key file to write, rather than <PUBKEY>.json"))
+        .arg(Arg::new("force").long("force").action(ArgAction::SetTrue).help("Overwrite the key file if it exists"))
+}
+
+// Generates random keypairs until one has a public key matching the patterns, and writes it to a keypair file
+pub fn grind_key(matches : &ArgMatches)
+{
+    let raw_patterns = raw_patterns(matches);
+    let ignore_case = matches.get_flag("ignore-case");
+    let threads = matches.get_one::<usize>("threads").copied().or(config::load().threads);
+    let progress = !matches.get_flag("no-progress");
+    let out = matches.get_one::<String>("out").cloned();
+    let force = matches.get_flag("force");
+    let nice = matches.get_flag("nice");
+    let pin = matches.get_one::<Vec<usize>>("pin");
+
+    // Check for an existing output file before spending any time searching
+    if let Some(out) = &out {
+        if !force && std::path::Path::new(out).exists() {
+            eprintln!("{} already exists, use --force to overwrite it", out);
+            std::process::exit(-1);
+        }
+    }
+
+    let matcher = Matcher::new(&raw_patterns, ignore_case);
+
+    run_in_background(nice, pin.map(|pin| pin.as_slice()));
+
+    let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+
+    let result = Mutex::new(None);
+    let attempts = AtomicU64::new(0);
+    let done = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        if progress {
+            let (attempts, done) = (&attempts, &done);
+            let probability = matcher.probability();
+            scope.spawn(move || report_progress(attempts, done, probability));
+        }
+
+        let workers = (0..threads)
+            .map(|_| {
+                let (matcher, result, attempts, done) = (&matcher, &result, &attempts, &done);
+                scope.spawn(move || {
+                    use rand::{RngCore, SeedableRng};
+                    // A CSPRNG seeded from the operating system, which is much faster than asking the operating
+                    // system for every key
+                    let mut rng = rand::rngs::StdRng::from_entropy();
+                    let mut secret = [0_u8; 32];
+                    let mut unreported_attempts = 0;
+
+                    while !done.load(Ordering::Relaxed) {
+                        unreported_attempts += 1;
+                        if unreported_attempts == 64 {
+                            attempts.fetch_add(unreported_attempts, Ordering::Relaxed);
+                            unreported_attempts = 0;
+                        }
+
+                        rng.fill_bytes(&mut secret);
+                        let secret_key = ed25519_dalek::SecretKey::from_bytes(&secret).unwrap();
+                        let public_key = ed25519_dalek::PublicKey::from(&secret_key);
+
+                        if !matcher.matches(&bs58::encode(public_key.as_bytes()).into_string()).is_empty() {
+                            let mut keypair = [0_u8; 64];
+                            keypair[..32].copy_from_slice(&secret);
+                            keypair[32..].copy_from_slice(public_key.as_bytes());
+                            result.lock().unwrap().get_or_insert(keypair);
+                            done.store(true, Ordering::Relaxed);
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers.into_iter().for_each(|worker| worker.join().unwrap());
+    });
+
+    let keypair = result.into_inner().unwrap().unwrap();
+    let pubkey = Pubkey(keypair[32..].try_into().unwrap());
+
+    // By default, name the file after the public key, as solana-keygen grind does
+    let out = out.unwrap_or_else(|| format!("{}.json", pubkey));
+
+    write_keypair_file(&out, &keypair, force).unwrap_or_else(|e| {
+        eprintln!("Failed to write {}: {}", out, e);
+        std::process::exit(-1);
+    });
+
+    println!("{}", pubkey);
+}
+
+struct Search
+{
+    program_id : Pubkey,
+
+    fixed_seed : Vec<u8>,
+
+    vary : VaryType,
+
+    matcher : Matcher,
+
+    // The next value to be tried by each thread; the number of cursors is the number of threads
+    cursors : Vec<u64>,
+
+    // The last value to be tried
+    last : u64,
+
+    // If set, the order in which values are tried; cursors and results then refer to positions in this order
+    order : Option<Order>,
+
+    // Attempts made before this search started (i.e. those recorded in a checkpoint being resumed)
+    attempts : u64,
+
+    progress : bool,
+
+    checkpoint_path : Option<String>,
+
+    // Maximum number of attempts to make in this run
+    max_attempts : Option<u64>,
+
+    max_time : Option<Duration>,
+
+    // If set, only PDAs with this canonical bump seed are considered
+    require_bump : Option<u8>,
+
+    // If None, the search is for a single address matching any pattern; otherwise it is for this many addresses
+    // matching each pattern
+    count : Option<usize>
+}
+
+// A seed value (or its position, when there's an order) and the resulting PDA and bump seed
+type GrindResult = (u64, Pubkey, u8);
+
+enum Outcome
+{
+    // All requested matches were found
+    Complete,
+
+    // The entire seed space was searched without finding all requested matches
+    Exhausted,
+
+    // The search stopped due to --max-attempts or --max-time
+    LimitReached
+}
+
+impl Search
+{
+    // Searches the seed space using one thread per cursor, with each thread advancing its cursor by the number of
+    // threads, so that for N threads, thread t tries values t, t + N, t + 2N, etc.  The lowest matching values are
+    // returned regardless of thread count, because threads only stop once they pass the highest value that is still
+    // needed.  Returns the results for each target (each pattern if counting, else a single target for all patterns),
+    // sorted by value.
+    fn run(&self) -> (Vec<Vec<GrindResult>>, Outcome)
+    {
+        let (targets, count) = match self.count {
+            Some(count) => (self.matcher.patterns.len(), count),
+            None => (1, 1)
+        };
+
+        let threads = self.cursors.len() as u64;
+        let budget = AtomicU64::new(self.max_attempts.unwrap_or(u64::MAX));
+        // A time too far off to be represented is no deadline at all
+        let deadline = self.max_time.and_then(|max_time| Instant::now().checked_add(max_time));
+        let limit_reached = AtomicBool::new(false);
+        // Threads stop once their value exceeds this, which is the highest value needed by any target
+        let threshold = AtomicU64::new(u64::MAX);
+        let results = Mutex::new(vec![Vec::<GrindResult>::new(); targets]);
+        let attempts = AtomicU64::new(self.attempts);
+        let cursors = self.cursors.iter().map(|cursor| AtomicU64::new(*cursor)).collect::<Vec<AtomicU64>>();
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            if self.progress {
+                let (attempts, done) = (&attempts, &done);
+                scope.spawn(move || report_progress(attempts, done, self.probability()));
+            }
+
+            if let Some(checkpoint_path) = &self.checkpoint_path {
+                let (attempts, cursors, done) = (&attempts, &cursors, &done);
+                scope.spawn(move || self.write_checkpoints(checkpoint_path, attempts, cursors, done));
+            }
+
+            let workers = cursors
+                .iter()
+                .map(|cursor| {
+                    let (threshold, results, attempts) = (&threshold, &results, &attempts);
+                    let (budget, limit_reached) = (&budget, &limit_reached);
+                    scope.spawn(move || {
+                        let fixed_seed = SeedHash::new(&self.fixed_seed);
+                        let mut vary_seed = vec![];
+                        let mut value = cursor.load(Ordering::Relaxed);
+                        let mut unreported_attempts = 0;
+                        // Number of attempts this thread may make before checking in again
+                        let mut allowance = 0;
+
+                        while (value <= self.last) && (value <= threshold.load(Ordering::Relaxed)) {
+                            // Check in periodically rather than on every attempt to avoid contention between threads:
+                            // publish progress, check the time limit, and claim more attempts from the budget
+                            if allowance == 0 {
+                                attempts.fetch_add(unreported_attempts, Ordering::Relaxed);
+                                cursor.store(value, Ordering::Relaxed);
+                                unreported_attempts = 0;
+
+                                if deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false) {
+                                    limit_reached.store(true, Ordering::Relaxed);
+                                }
+                                if limit_reached.load(Ordering::Relaxed) {
+                                    break;
+                                }
+
+                                allowance = budget
+                                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |budget| {
+                                        if budget == 0 {
+                                            None
+                                        }
+                                        else {
+                                            Some(budget - budget.min(1024))
+                                        }
+                                    })
+                                    .map(|budget| budget.min(1024))
+                                    .unwrap_or(0);
+                                if allowance == 0 {
+                                    limit_reached.store(true, Ordering::Relaxed);
+                                    break;
+                                }
+                            }
+
+                            allowance -= 1;
+                            unreported_attempts += 1;
+
+                            vary_seed.clear();
+                            self.vary.encode(self.value(value), &mut vary_seed);
+                            let mut seed = fixed_seed.clone();
+                            seed.update(&vary_seed);
+
+                            if let Some((pda, bump_seed)) = self.find_pda(&seed) {
+                                let matched_targets = self.matched_targets(&pda);
+                                if !matched_targets.is_empty() {
+                                    let mut results = results.lock().unwrap();
+                                    if let Some(needed) =
+                                        self.record(&mut results, &matched_targets, (value, pda, bump_seed))
+                                    {
+                                        threshold.store(needed, Ordering::Relaxed);
+                                    }
+                                }
+                            }
+
+                            value = match value.checked_add(threads) {
+                                Some(value) => value,
+                                None => break
+                            };
+                        }
+
+                        attempts.fetch_add(unreported_attempts, Ordering::Relaxed);
+                        // Resuming from a checkpoint continues after the last match, to find the next one
+                        cursor.store(value, Ordering::Relaxed);
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers.into_iter().for_each(|worker| worker.join().unwrap());
+
+            done.store(true, Ordering::Relaxed);
+        });
+
+        let results = results.into_inner().unwrap();
+
+        let outcome = if results.iter().all(|results| results.len() == count) {
+            Outcome::Complete
+        }
+        else if limit_reached.into_inner() {
+            Outcome::LimitReached
+        }
+        else {
+            Outcome::Exhausted
+        };
+
+        (results, outcome)
+    }
+
+    // Returns the value tried at position in the search
+    fn value(
+        &self,
+        position : u64
+    ) -> u64
+    {
+        self.order.as_ref().map(|order| order.value(position)).unwrap_or(position)
+    }
+
+    // Returns the targets that pda counts towards
+    fn matched_targets(
+        &self,
+        pda : &Pubkey
+    ) -> Vec<usize>
+    {
+        let matches = self.matcher.matches(&pda.to_string());
+        if self.count.is_some() || matches.is_empty() {
+            matches
+        }
+        else {
+            vec![0]
+        }
+    }
+
+    // Adds result to the results of each of targets, keeping only the lowest values.  Once every target has enough
+    // results, nothing past the highest of them is needed anymore, and that value is returned.
+    fn record(
+        &self,
+        results : &mut [Vec<GrindResult>],
+        targets : &[usize],
+        result : GrindResult
+    ) -> Option<u64>
+    {
+        let count = self.count.unwrap_or(1);
+
+        for target in targets {
+            let results = &mut results[*target];
+            if results.iter().any(|(value, _, _)| *value == result.0) {
+                continue;
+            }
+            let position = results.partition_point(|(value, _, _)| *value < result.0);
+            results.insert(position, result);
+            results.truncate(count);
+        }
+
+        if results.iter().all(|results| results.len() == count) {
+            results.iter().map(|results| results[count - 1].0).max()
+        }
+        else {
+            None
+        }
+    }
+
+    // Outputs results and exits with the status corresponding to outcome
+    fn output(
+        &self,
+        results : &[Vec<GrindResult>],
+        outcome : Outcome
+    )
+    {
+        // When searching for each pattern separately, label results with the pattern that they matched
+        let labeled = self.count.is_some() && (self.matcher.patterns.len() > 1);
+
+        for (target, results) in results.iter().enumerate() {
+            for (value, pda, bump_seed) in results {
+                let value = self.value(*value);
+                if labeled {
+                    println!(
+                        "{} {}.{} {}",
+                        self.vary.seed_string(value),
+                        pda,
+                        bump_seed,
+                        self.matcher.descriptions[target]
+                    );
+                }
+                else {
+                    println!("{} {}.{}", self.vary.seed_string(value), pda, bump_seed);
+                }
+            }
+        }
+
+        match outcome {
+            Outcome::Complete => (),
+            Outcome::Exhausted => {
+                eprintln!("Not enough matches found in the entire seed space");
+                std::process::exit(1);
+            },
+            Outcome::LimitReached => {
+                eprintln!("Not enough matches found before reaching the --max-attempts or --max-time limit");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    fn find_pda(
+        &self,
+        seed : &SeedHash
+    ) -> Option<(Pubkey, u8)>
+    {
+        match self.require_bump {
+            // Only the bumps from 255 down to the required one need to be tried
+            Some(require_bump) => {
+                if (require_bump..=u8::MAX)
+                    .skip(1)
+                    .any(|bump| seed.try_find_pda(&self.program_id, Some(bump)).is_some())
+                {
+                    None
+                }
+                else {
+                    seed.try_find_pda(&self.program_id, Some(require_bump)).map(|pda| (pda, require_bump))
+                }
+            },
+            None => seed.find_pda(&self.program_id)
+        }
+    }
+
+    // Returns the approximate probability that a single attempt produces a match, if known
+    fn probability(&self) -> Option<f64>
+    {
+        self.matcher.probability().map(|probability| probability * self.bump_probability())
+    }
+
+    // Returns the probability that a single attempt has the required bump seed
+    fn bump_probability(&self) -> f64
+    {
+        // Each bump seed results in an off-curve address about half of the time, so the canonical bump seed is
+        // 255 - n with probability 1 / 2^(n + 1)
+        self.require_bump.map(|bump| 0.5_f64.powi(256 - (bump as i32))).unwrap_or(1.0)
+    }
+
+    // Returns the expected number of attempts needed to find all requested matches, if known
+    fn expected_attempts(&self) -> Option<f64>
+    {
+        match self.count {
+            None => self.probability().map(|probability| 1.0 / probability),
+            // Finding enough matches for the least likely pattern takes the longest
+            Some(count) => self
+                .matcher
+                .patterns
+                .iter()
+                .map(|pattern| pattern.probability(self.matcher.ignore_case))
+                .collect::<Option<Vec<f64>>>()
+                .map(|probabilities| {
+                    let probability = probabilities.into_iter().fold(1.0, f64::min) * self.bump_probability();
+                    (count as f64) / probability
+                })
+        }
+    }
+
+    // Measures how quickly this machine searches, and outputs the expected number of attempts and time needed to find
+    // the requested matches
+    fn estimate(
+        &self,
+        threads : usize
+    )
+    {
+        eprintln!("Measuring search speed ...");
+
+        let rate = self.measure_rate(threads, Duration::from_secs(3));
+
+        println!("Attempts per second: {:.0}", rate);
+
+        match self.expected_attempts() {
+            Some(expected_attempts) => {
+                println!("Expected attempts: {:.0}", expected_attempts);
+                println!("Expected time: {}", format_duration(expected_attempts / rate));
+                if expected_attempts > ((self.last as f64) + 1.0) {
+                    eprintln!(
+                        "The seed space of --vary {} has only {} values, so there may not be enough matches in it",
+                        self.vary.name(),
+                        (self.last as u128) + 1
+                    );
+                }
+            },
+            None => println!("Expected attempts: unknown (--matches patterns cannot be estimated)")
+        }
+    }
+
+    // Returns the number of attempts per second made by threads threads over duration, performing the same work as a
+    // search but without stopping at matches
+    fn measure_rate(
+        &self,
+        threads : usize,
+        duration : Duration
+    ) -> f64
+    {
+        let attempts = AtomicU64::new(0);
+        let start = Instant::now();
+
+        std::thread::scope(|scope| {
+            for t in 0..(threads as u64) {
+                let attempts = &attempts;
+                scope.spawn(move || {
+                    let fixed_seed = SeedHash::new(&self.fixed_seed);
+                    let mut vary_seed = vec![];
+                    let mut value = t.min(self.last);
+
+                    while start.elapsed() < duration {
+                        for _ in 0..256 {
+                            vary_seed.clear();
+                            self.vary.encode(self.value(value), &mut vary_seed);
+                            let mut seed = fixed_seed.clone();
+                            seed.update(&vary_seed);
+                            if let Some((pda, _)) = self.find_pda(&seed) {
+                                std::hint::black_box(self.matched_targets(&pda));
+                            }
+                            // Small seed spaces are simply tried repeatedly
+                            value = value.checked_add(threads as u64).filter(|value| *value <= self.last).unwrap_or(0);
+                        }
+                        attempts.fetch_add(256, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+
+        (attempts.into_inner() as f64) / start.elapsed().as_secs_f64()
+    }
+
+    // Writes a checkpoint every ten seconds until done is set, and then one final time
+    fn write_checkpoints(
+        &self,
+        path : &str,
+        attempts : &AtomicU64,
+        cursors : &[AtomicU64],
+        done : &AtomicBool
+    )
+    {
+        let mut next_write = Instant::now() + Duration::from_secs(10);
+
+        loop {
+            let is_done = done.load(Ordering::Relaxed);
+
+            if is_done || (Instant::now() >= next_write) {
+                let checkpoint = Checkpoint {
+                    program_id : self.program_id,
+                    fixed_seed : self.fixed_seed.clone(),
+                    vary : self.vary.name(),
+                    grind_seed : self.order.as_ref().map(|order| order.grind_seed),
+                    cursors : cursors.iter().map(|cursor| cursor.load(Ordering::Relaxed)).collect(),
+                    attempts : attempts.load(Ordering::Relaxed)
+                };
+                if let Err(e) = checkpoint.save(path) {
+                    eprintln!("Failed to write checkpoint file {}: {}", path, e);
+                }
+                next_write += Duration::from_secs(10);
+            }
+
+            if is_done {
+                break;
+            }
+
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+// The state of a grind, saved periodically so that it can be resumed later.  It's stored as simple text:
+//
+//   program_id <BASE58>
+//   fixed_seed <HEX>
+//   vary <TYPE>
+//   grind_seed <N>     (only if --grind-seed was given)
+//   attempts <N>
+//   cursor <VALUE>     (one line per thread)
+struct Checkpoint
+{
+    program_id : Pubkey,
+
+    fixed_seed : Vec<u8>,
+
+    vary : String,
+
+    grind_seed : Option<u64>,
+
+    cursors : Vec<u64>,
+
+    attempts : u64
+}
+
+impl Checkpoint
+{
+    fn load(path : &str) -> Result<Checkpoint, String>
+    {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        let mut program_id = None;
+        let mut fixed_seed = None;
+        let mut vary = None;
+        let mut grind_seed = None;
+        let mut attempts = 0;
+        let mut cursors = vec![];
+
+        for (i, line) in contents.lines().enumerate() {
+            let invalid = |e : String| format!("line {}: {}", i + 1, e);
+            match line.split_once(' ') {
+                Some(("program_id", value)) => program_id = Some(Pubkey::from_str(value).map_err(invalid)?),
+                Some(("fixed_seed", value)) => fixed_seed = Some(decode_hex(value).map_err(invalid)?),
+                Some(("vary", value)) => vary = Some(value.to_string()),
+                Some(("grind_seed", value)) => {
+                    grind_seed = Some(value.parse::<u64>().map_err(|e| invalid(e.to_string()))?)
+                },
+                Some(("attempts", value)) => attempts = value.parse::<u64>().map_err(|e| invalid(e.to_string()))?,
+                Some(("cursor", value)) => cursors.push(value.parse::<u64>().map_err(|e| invalid(e.to_string()))?),
+                _ => return Err(invalid(format!("unexpected contents: {}", line)))
+            }
+        }
+
+        if cursors.is_empty() {
+            return Err("no cursors".to_string());
+        }
+
+        Ok(Checkpoint {
+            program_id : program_id.ok_or("missing program_id")?,
+            fixed_seed : fixed_seed.ok_or("missing fixed_seed")?,
+            vary : vary.ok_or("missing vary")?,
+            grind_seed,
+            cursors,
+            attempts
+        })
+    }
+
+    // Writes to a temporary file first and renames it into place, so that an interrupted write can never destroy the
+    // previous checkpoint
+    fn save(
+        &self,
+        path : &str
+    ) -> Result<(), String>
+    {
+        let mut contents = format!(
+            "program_id {}\nfixed_seed {}\nvary {}\n",
+            self.program_id,
+            encode_hex(&self.fixed_seed),
+            self.vary
+        );
+        if let Some(grind_seed) = self.grind_seed {
+            contents.push_str(&format!("grind_seed {}\n", grind_seed));
+        }
+        contents.push_str(&format!("attempts {}\n", self.attempts));
+        self.cursors.iter().for_each(|cursor| contents.push_str(&format!("cursor {}\n", cursor)));
+
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, contents).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+    }
+}
+
+// A pseudo-random permutation of the values 0 to last, determined by a --grind-seed, so that a grind can try values
+// in an order that is shuffled but still reproducible.  It's built from a permutation of the smallest power of two range
+// containing all of the values (made of steps that are each invertible modulo that power of two), which is applied
+// repeatedly to any position until the result is in range ("cycle walking").  Since the range is at least half of the
+// power of two range, this takes two applications on average.
+struct Order
+{
+    grind_seed : u64,
+
+    last : u64,
+
+    // The number of bits in the power of two range
+    bits : u32,
+
+    keys : [u64; 4]
+}
+
+impl Order
+{
+    fn new(
+        grind_seed : u64,
+        last : u64
+    ) -> Order
+    {
+        // Derive round keys with splitmix64
+        let mut state = grind_seed;
+        let mut keys = [0_u64; 4];
+        for key in keys.iter_mut() {
+            state = state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            *key = z ^ (z >> 31);
+        }
+
+        Order { grind_seed, last, bits : 64 - last.leading_zeros(), keys }
+    }
+
+    fn value(
+        &self,
+        position : u64
+    ) -> u64
+    {
+        let mask = if self.bits == 64 { u64::MAX } else { (1_u64 << self.bits) - 1 };
+        let shift = (self.bits / 2).max(1);
+
+        let mut value = position;
+        loop {
+            for key in &self.keys {
+                value = (value ^ key) & mask;
+                value = value.wrapping_mul(0xD6E8FEB86659FD93) & mask;
+                value ^= value >> shift;
+            }
+            if value <= self.last {
+                return value;
+            }
+        }
+    }
+}
+
+fn encode_hex(bytes : &[u8]) -> String
+{
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Decodes a hex string.  The string is left out of errors, since it may be a secret, such as convert-key's seeds.
+pub fn decode_hex(s : &str) -> Result<Vec<u8>, String>
+{
+    if !s.len().is_multiple_of(2) {
+        return Err("odd length hex string".to_string());
+    }
+
+    // Checking every character first also keeps the slicing below on character boundaries
+    if !s.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err("invalid hex string".to_string());
+    }
+
+    Ok((0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..(i + 2)], 16).unwrap()).collect())
+}
+
+// Parses a duration given as a number of seconds, optionally followed by a unit of s, m, h, or d
+pub fn parse_duration(s : &str) -> Result<Duration, String>
+{
+    let (number, multiplier) = match s.chars().last() {
+        Some('s') => (&s[..(s.len() - 1)], 1),
+        Some('m') => (&s[..(s.len() - 1)], 60),
+        Some('h') => (&s[..(s.len() - 1)], 60 * 60),
+        Some('d') => (&s[..(s.len() - 1)], 24 * 60 * 60),
+        _ => (s, 1)
+    };
+
+    let n = number.parse::<u64>().map_err(|e| e.to_string())?;
+
+    Ok(Duration::from_secs(n.checked_mul(multiplier).ok_or("duration is too large")?))
+}
+
+fn format_duration(seconds : f64) -> String
+{
+    if !seconds.is_finite() {
+        return "forever".to_string();
+    }
+
+    let seconds = seconds.round() as u64;
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    let hms = format!("{}:{:02}:{:02}", seconds / 3600, (seconds / 60) % 60, seconds % 60);
+
+    if days > 0 {
+        format!("{}d {}", days, hms)
+    }
+    else {
+        hms
+    }
+}
+
+// Periodically writes grind statistics to stderr until done is set.  The reported rate counts only attempts made
+// since this function was called.  On a terminal the statistics line is updated in
+// place every second; otherwise a new line is written every ten seconds, which is friendlier to log files.
+pub fn report_progress(
+    attempts : &AtomicU64,
+    done : &AtomicBool,
+    probability : Option<f64>
+)
+{
+    let is_terminal = std::io::stderr().is_terminal();
+    let interval = Duration::from_secs(if is_terminal { 1 } else { 10 });
+    let start = Instant::now();
+    let mut next_report = start + interval;
+    let initial_attempts = attempts.load(Ordering::Relaxed);
+
+    while !done.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(50));
+
+        if Instant::now() < next_report {
+            continue;
+        }
+        next_report += interval;
+
+        let attempts = attempts.load(Ordering::Relaxed);
+        let elapsed = start.elapsed().as_secs_f64();
+        let rate = ((attempts - initial_attempts) as f64) / elapsed;
+
+        let mut line = format!("Attempts: {}, {:.0}/s, elapsed {}", attempts, rate, format_duration(elapsed));
+        if let Some(probability) = probability {
+            line.push_str(&format!(
+                ", expected attempts per match {:.0}, expected time per match {}",
+                1.0 / probability,
+                format_duration(1.0 / (probability * rate))
+            ));
+        }
+
+        if is_terminal {
+            eprint!("\r{}\x1b[K", line);
+        }
+        else {
+            eprintln!("{}", line);
+        }
+    }
+
+    if is_terminal && (start.elapsed() >= interval) {
+        eprintln!();
+    }
+}
@@ -0,0 +1,525 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::io::{BufRead, BufReader, Lines, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::{Arg, ArgMatches, Command};
+
+use super::{
+    decode_hex, encode_hex, machine_args, report_progress, run_in_background, GrindResult, Matcher, Order, Outcome,
+    Search, VaryType
+};
+use crate::{config, Pubkey, SeedHash};
+
+// Distributed grinding: a coordinator (grind --serve) splits the seed space into chunks of consecutive values and
+// hands them out to any number of workers (grind-worker), which search them and report back their matches.  The
+// coordinator verifies every reported match and combines them just as a single search would, so the output is the same
+// as that of running the same grind on one machine.
+//
+// The protocol is line based text over TCP.  On connecting, the coordinator sends the search to perform:
+//
+//   solpda-grind 1
+//   program_id <BASE58>
+//   fixed_seed <HEX>
+//   vary <TYPE>
+//   charset <HEX>            (only for --vary string)
+//   len <N>                  (only for --vary string)
+//   grind_seed <N>           (only if given)
+//   ignore_case <0|1>
+//   require_bump <BUMP>      (only if given)
+//   count <N>                (only if given)
+//   pattern <OPTION> <HEX>   (one line per pattern)
+//   end
+//
+// after which the worker repeatedly sends "next", to which the coordinator replies with "range <FIRST> <LAST>", "wait"
+// (nothing is available now, but a range abandoned by a disconnected worker may be later), or "done".  After searching
+// a range, the worker sends "result <FIRST> <LAST> <VALUE>...".
+//
+// A worker that doesn't report a match in its range can't be detected, so workers should only be run on trusted
+// machines.
+
+pub const DEFAULT_CHUNK_SIZE : u64 = 1 << 20;
+
+const PROTOCOL : &str = "solpda-grind 1";
+
+struct State
+{
+    // The lowest value not yet handed out, or None once every value has been
+    next : Option<u64>,
+
+    // The number of values that may still be handed out under --max-attempts
+    budget : u64,
+
+    // Ranges currently being searched by workers
+    outstanding : Vec<(u64, u64)>,
+
+    // Ranges whose workers disconnected before reporting on them, to be handed out again
+    abandoned : Vec<(u64, u64)>,
+
+    results : Vec<Vec<GrindResult>>,
+
+    // No value past this one is needed
+    threshold : u64
+}
+
+struct Coordinator
+{
+    search : Search,
+
+    // The search, as sent to each worker
+    spec : String,
+
+    chunk_size : u64,
+
+    state : Mutex<State>,
+
+    // Signaled whenever a worker reports on or abandons a range
+    changed : Condvar,
+
+    attempts : AtomicU64,
+
+    done : AtomicBool
+}
+
+impl Coordinator
+{
+    // Assigns the lowest range still needing to be searched, or returns None if there is none
+    fn claim(
+        &self,
+        state : &mut State
+    ) -> Option<(u64, u64)>
+    {
+        let threshold = state.threshold;
+        state.abandoned.retain(|(first, _)| *first <= threshold);
+
+        let range = match (0..state.abandoned.len()).min_by_key(|i| state.abandoned[*i].0) {
+            Some(i) => state.abandoned.swap_remove(i),
+            None => {
+                let first = state.next.filter(|first| (*first <= threshold) && (state.budget > 0))?;
+                let last =
+                    first.saturating_add(self.chunk_size.min(state.budget) - 1).min(self.search.last).min(threshold);
+                state.next = last.checked_add(1).filter(|next| *next <= self.search.last);
+                state.budget -= (last - first) + 1;
+                (first, last)
+            }
+        };
+
+        state.outstanding.push(range);
+        Some(range)
+    }
+
+    // Returns the outcome of the search once it is known
+    fn outcome(
+        &self,
+        state : &State
+    ) -> Option<Outcome>
+    {
+        // Results are final only once every range that could contain a lower match has been searched
+        if state.outstanding.iter().chain(state.abandoned.iter()).any(|(first, _)| *first <= state.threshold) {
+            None
+        }
+        else if state.results.iter().all(|results| results.len() == self.search.count.unwrap_or(1)) {
+            Some(Outcome::Complete)
+        }
+        else if state.next.is_none() {
+            Some(Outcome::Exhausted)
+        }
+        else if state.budget == 0 {
+            Some(Outcome::LimitReached)
+        }
+        else {
+            None
+        }
+    }
+
+    // Serves a single worker until it disconnects, and then makes any range it didn't report on available again
+    fn serve_worker(
+        &self,
+        stream : TcpStream
+    )
+    {
+        let peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_else(|_| "unknown".to_string());
+        let mut assigned = vec![];
+
+        if let Err(e) = self.talk(stream, &mut assigned) {
+            eprintln!("Worker {} disconnected: {}", peer, e);
+        }
+
+        if !assigned.is_empty() {
+            let mut state = self.state.lock().unwrap();
+            for range in assigned {
+                state.outstanding.retain(|outstanding| *outstanding != range);
+                state.abandoned.push(range);
+            }
+            self.changed.notify_all();
+        }
+    }
+
+    fn talk(
+        &self,
+        stream : TcpStream,
+        assigned : &mut Vec<(u64, u64)>
+    ) -> Result<(), String>
+    {
+        let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+        writer.write_all(self.spec.as_bytes()).map_err(|e| e.to_string())?;
+
+        for line in BufReader::new(stream).lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            let mut words = line.split(' ');
+
+            match words.next() {
+                Some("next") => {
+                    let reply = {
+                        let mut state = self.state.lock().unwrap();
+                        match self.claim(&mut state) {
+                            Some((first, last)) => {
+                                assigned.push((first, last));
+                                format!("range {} {}\n", first, last)
+                            },
+                            None if self.outcome(&state).is_some() => "done\n".to_string(),
+                            None => "wait\n".to_string()
+                        }
+                    };
+                    writer.write_all(reply.as_bytes()).map_err(|e| e.to_string())?;
+                },
+
+                Some("result") => {
+                    let numbers = words
+                        .map(|word| word.parse::<u64>().map_err(|e| format!("invalid result {}: {}", line, e)))
+                        .collect::<Result<Vec<u64>, String>>()?;
+                    if numbers.len() < 2 {
+                        return Err(format!("invalid result {}", line));
+                    }
+                    let (first, last) = (numbers[0], numbers[1]);
+                    if !assigned.contains(&(first, last)) {
+                        return Err(format!("result for range {}..={}, which it wasn't assigned", first, last));
+                    }
+
+                    // Check every match before accepting any of them, outside of the lock since this is slow
+                    let mut verified = vec![];
+                    for value in &numbers[2..] {
+                        if (*value < first) || (*value > last) {
+                            return Err(format!("result {} outside of range {}..={}", value, first, last));
+                        }
+                        let mut seed = self.search.fixed_seed.clone();
+                        self.search.vary.encode(self.search.value(*value), &mut seed);
+                        let (pda, bump_seed) =
+                            self.search.find_pda(&SeedHash::new(&seed)).ok_or(format!("bad result {}", value))?;
+                        let targets = self.search.matched_targets(&pda);
+                        if targets.is_empty() {
+                            return Err(format!("bad result {}", value));
+                        }
+                        verified.push((targets, (*value, pda, bump_seed)));
+                    }
+
+                    let mut state = self.state.lock().unwrap();
+                    for (targets, result) in verified {
+                        if let Some(needed) = self.search.record(&mut state.results, &targets, result) {
+                            state.threshold = needed;
+                        }
+                    }
+                    state.outstanding.retain(|outstanding| *outstanding != (first, last));
+                    assigned.retain(|assigned| *assigned != (first, last));
+                    self.attempts.fetch_add((last - first).saturating_add(1), Ordering::Relaxed);
+                    self.changed.notify_all();
+                },
+
+                _ => return Err(format!("unexpected message {}", line))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Describes search in the form sent to workers
+fn spec(
+    search : &Search,
+    raw_patterns : &[(String, String)]
+) -> String
+{
+    let mut spec =
+        format!("{}\nprogram_id {}\nfixed_seed {}\n", PROTOCOL, search.program_id, encode_hex(&search.fixed_seed));
+
+    match &search.vary {
+        VaryType::String { charset, len } => {
+            spec.push_str(&format!("vary string\ncharset {}\nlen {}\n", encode_hex(charset), len))
+        },
+        vary => spec.push_str(&format!("vary {}\n", vary.name()))
+    }
+
+    if let Some(order) = &search.order {
+        spec.push_str(&format!("grind_seed {}\n", order.grind_seed));
+    }
+    spec.push_str(&format!("ignore_case {}\n", search.matcher.ignore_case as u8));
+    if let Some(require_bump) = search.require_bump {
+        spec.push_str(&format!("require_bump {}\n", require_bump));
+    }
+    if let Some(count) = search.count {
+        spec.push_str(&format!("count {}\n", count));
+    }
+    for (option, value) in raw_patterns {
+        spec.push_str(&format!("pattern {} {}\n", option, encode_hex(value.as_bytes())));
+    }
+    spec.push_str("end\n");
+
+    spec
+}
+
+// Reads the search sent by the coordinator
+fn read_spec(lines : &mut Lines<BufReader<TcpStream>>) -> Result<Search, String>
+{
+    let mut next_line =
+        || lines.next().unwrap_or(Err(std::io::ErrorKind::UnexpectedEof.into())).map_err(|e| e.to_string());
+
+    let protocol = next_line()?;
+    if protocol != PROTOCOL {
+        return Err(format!("unsupported protocol {}", protocol));
+    }
+
+    let mut program_id = None;
+    let mut fixed_seed = None;
+    let mut vary = None;
+    let mut charset = None;
+    let mut len = None;
+    let mut grind_seed = None;
+    let mut ignore_case = false;
+    let mut require_bump = None;
+    let mut count = None;
+    let mut raw_patterns = vec![];
+
+    loop {
+        let line = next_line()?;
+        let invalid = |e : String| format!("invalid search {}: {}", line, e);
+        match line.split_once(' ') {
+            Some(("program_id", value)) => program_id = Some(Pubkey::from_str(value).map_err(invalid)?),
+            Some(("fixed_seed", value)) => fixed_seed = Some(decode_hex(value).map_err(invalid)?),
+            Some(("vary", value)) => vary = Some(VaryType::from_str(value).ok_or_else(|| invalid(value.to_string()))?),
+            Some(("charset", value)) => charset = Some(decode_hex(value).map_err(invalid)?),
+            Some(("len", value)) => len = Some(value.parse::<usize>().map_err(|e| invalid(e.to_string()))?),
+            Some(("grind_seed", value)) => grind_seed = Some(value.parse::<u64>().map_err(|e| invalid(e.to_string()))?),
+            Some(("ignore_case", value)) => ignore_case = value == "1",
+            Some(("require_bump", value)) => {
+                require_bump = Some(value.parse::<u8>().map_err(|e| invalid(e.to_string()))?)
+            },
+            Some(("count", value)) => count = Some(value.parse::<usize>().map_err(|e| invalid(e.to_string()))?),
+            Some(("pattern", value)) => {
+                let (option, value) = value.split_once(' ').ok_or_else(|| invalid("missing value".to_string()))?;
+                let value =
+                    String::from_utf8(decode_hex(value).map_err(invalid)?).map_err(|e| invalid(e.to_string()))?;
+                raw_patterns.push((option.to_string(), value));
+            },
+            None if line == "end" => break,
+            _ => return Err(invalid("unexpected contents".to_string()))
+        }
+    }
+
+    let vary = match vary.ok_or("missing vary")? {
+        VaryType::String { .. } => VaryType::String {
+            charset : charset.filter(|charset| !charset.is_empty()).ok_or("missing charset")?,
+            len : len.filter(|len| *len > 0).ok_or("missing len")?
+        },
+        vary => vary
+    };
+
+    if raw_patterns.is_empty() {
+        return Err("missing patterns".to_string());
+    }
+
+    Ok(Search {
+        program_id : program_id.ok_or("missing program_id")?,
+        fixed_seed : fixed_seed.ok_or("missing fixed_seed")?,
+        last : vary.max(),
+        order : grind_seed.map(|grind_seed| Order::new(grind_seed, vary.max())),
+        vary,
+        matcher : Matcher::new(&raw_patterns, ignore_case),
+        cursors : vec![],
+        attempts : 0,
+        progress : false,
+        checkpoint_path : None,
+        max_attempts : None,
+        max_time : None,
+        require_bump,
+        count
+    })
+}
+
+// Coordinates search over TCP, listening on address, and outputs the results once workers have found them
+pub(super) fn serve(
+    search : Search,
+    raw_patterns : &[(String, String)],
+    address : &str,
+    chunk_size : u64
+)
+{
+    let listener = TcpListener::bind(address).unwrap_or_else(|e| {
+        eprintln!("Failed to listen on {}: {}", address, e);
+        std::process::exit(-1);
+    });
+
+    let targets = if search.count.is_some() { search.matcher.patterns.len() } else { 1 };
+
+    let coordinator = Arc::new(Coordinator {
+        spec : spec(&search, raw_patterns),
+        chunk_size,
+        state : Mutex::new(State {
+            next : Some(0),
+            budget : search.max_attempts.unwrap_or(u64::MAX),
+            outstanding : vec![],
+            abandoned : vec![],
+            results : vec![vec![]; targets],
+            threshold : u64::MAX
+        }),
+        changed : Condvar::new(),
+        attempts : AtomicU64::new(0),
+        done : AtomicBool::new(false),
+        search
+    });
+
+    eprintln!("Waiting for workers on {}", address);
+
+    {
+        let coordinator = coordinator.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let coordinator = coordinator.clone();
+                        std::thread::spawn(move || coordinator.serve_worker(stream));
+                    },
+                    Err(e) => eprintln!("Failed to accept connection: {}", e)
+                }
+            }
+        });
+    }
+
+    let progress = coordinator.search.progress.then(|| {
+        let coordinator = coordinator.clone();
+        std::thread::spawn(move || {
+            report_progress(&coordinator.attempts, &coordinator.done, coordinator.search.probability())
+        })
+    });
+
+    // A time too far off to be represented is no deadline at all
+    let deadline = coordinator.search.max_time.and_then(|max_time| Instant::now().checked_add(max_time));
+
+    let (results, outcome) = {
+        let mut state = coordinator.state.lock().unwrap();
+        let outcome = loop {
+            if let Some(outcome) = coordinator.outcome(&state) {
+                break outcome;
+            }
+            if deadline.map(|deadline| Instant::now() >= deadline).unwrap_or(false) {
+                break Outcome::LimitReached;
+            }
+            state = coordinator.changed.wait_timeout(state, Duration::from_millis(100)).unwrap().0;
+        };
+        (std::mem::take(&mut state.results), outcome)
+    };
+
+    coordinator.done.store(true, Ordering::Relaxed);
+    if let Some(progress) = progress {
+        progress.join().unwrap();
+    }
+
+    coordinator.search.output(&results, outcome);
+}
+
+pub fn grind_worker_command() -> Command
+{
+    Command::new("grind-worker")
+        .about("Searches ranges of seeds that a grind --serve coordinator hands out")
+        .arg(Arg::new("address").value_name("ADDRESS").required(true).help("The address of the coordinator"))
+        .args(machine_args())
+}
+
+// Connects to a coordinator and searches the ranges that it hands out until it has no more
+pub fn grind_worker(matches : &ArgMatches)
+{
+    let threads = matches.get_one::<usize>("threads").copied().or(config::load().threads);
+    let nice = matches.get_flag("nice");
+    let pin = matches.get_one::<Vec<usize>>("pin");
+
+    run_in_background(nice, pin.map(|pin| pin.as_slice()));
+
+    let address = matches.get_one::<String>("address").unwrap();
+
+    let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+
+    let stream = TcpStream::connect(address).unwrap_or_else(|e| {
+        eprintln!("Failed to connect to {}: {}", address, e);
+        std::process::exit(-1);
+    });
+
+    match work(stream, threads) {
+        // The coordinator closes the connection when it has finished
+        Ok(()) => eprintln!("Coordinator {} has no more ranges to search", address),
+        Err(e) => {
+            eprintln!("Lost connection to coordinator {}: {}", address, e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+fn work(
+    stream : TcpStream,
+    threads : usize
+) -> Result<(), String>
+{
+    let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+    let mut lines = BufReader::new(stream).lines();
+
+    let mut search = read_spec(&mut lines)?;
+
+    loop {
+        if writer.write_all(b"next\n").is_err() {
+            return Ok(());
+        }
+
+        let reply = match lines.next() {
+            Some(reply) => reply.map_err(|e| e.to_string())?,
+            None => return Ok(())
+        };
+
+        let (first, last) = match reply.split(' ').collect::<Vec<&str>>()[..] {
+            ["range", first, last] => {
+                let first = first.parse::<u64>().map_err(|e| format!("invalid range {}: {}", reply, e))?;
+                let last = last.parse::<u64>().map_err(|e| format!("invalid range {}: {}", reply, e))?;
+                if (first > last) || (last > search.vary.max()) {
+                    return Err(format!("invalid range {}", reply));
+                }
+                (first, last)
+            },
+            ["wait"] => {
+                std::thread::sleep(Duration::from_secs(1));
+                continue;
+            },
+            ["done"] => return Ok(()),
+            _ => return Err(format!("unexpected message {}", reply))
+        };
+
+        search.cursors =
+            (0..(threads as u64)).filter_map(|t| first.checked_add(t)).filter(|value| *value <= last).collect();
+        search.last = last;
+
+        let (results, _) = search.run();
+
+        let mut values = results.iter().flatten().map(|(value, _, _)| *value).collect::<Vec<u64>>();
+        values.sort_unstable();
+        values.dedup();
+
+        let mut message = format!("result {} {}", first, last);
+        values.iter().for_each(|value| message.push_str(&format!(" {}", value)));
+        message.push('\n');
+
+        if writer.write_all(message.as_bytes()).is_err() {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,73 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::{mnemonic, write_keypair_file, Pubkey};
+
+pub fn keygen_command() -> Command
+{
+    Command::new("keygen")
+        .about("Generates a keypair and writes it to a key file")
+        .arg(Arg::new("out").long("out").value_name("FILE").help("The key file to write, rather than <PUBKEY>.json"))
+        .arg(Arg::new("force").long("force").action(ArgAction::SetTrue).help("Overwrite the key file if it exists"))
+        .arg(
+            Arg::new("seed-phrase")
+                .long("seed-phrase")
+                .action(ArgAction::SetTrue)
+                .help("Generate the keypair from a new seed phrase, which is output on stderr")
+        )
+}
+
+// Generates a keypair, from the operating system's random number generator or from a new seed phrase, and writes it to
+// a keypair file as the Solana CLI does
+pub fn keygen(matches : &ArgMatches)
+{
+    let out = matches.get_one::<String>("out").cloned();
+    let force = matches.get_flag("force");
+    let seed_phrase = matches.get_flag("seed-phrase");
+
+    if let Some(out) = &out {
+        if !force && std::path::Path::new(out).exists() {
+            eprintln!("{} already exists, use --force to overwrite it", out);
+            std::process::exit(-1);
+        }
+    }
+
+    let (mnemonic, secret) = if seed_phrase {
+        let (mnemonic, secret) = mnemonic::generate();
+        (Some(mnemonic), secret)
+    }
+    else {
+        let mut secret = Zeroizing::new([0_u8; 32]);
+        rand::rngs::OsRng.fill_bytes(&mut *secret);
+        (None, secret)
+    };
+
+    let secret_key = ed25519_dalek::SecretKey::from_bytes(&*secret).unwrap();
+    let public_key = ed25519_dalek::PublicKey::from(&secret_key);
+
+    let mut keypair = Zeroizing::new([0_u8; 64]);
+    keypair[..32].copy_from_slice(&*secret);
+    keypair[32..].copy_from_slice(public_key.as_bytes());
+
+    let pubkey = Pubkey(public_key.to_bytes());
+
+    // By default, name the file after the public key, as grind-key does
+    let out = out.unwrap_or_else(|| format!("{}.json", pubkey));
+
+    write_keypair_file(&out, &keypair, force).unwrap_or_else(|e| {
+        eprintln!("Failed to write {}: {}", out, e);
+        std::process::exit(-1);
+    });
+
+    // The seed phrase goes to stderr, so that it is seen even when the public key is captured
+    if let Some(mnemonic) = mnemonic {
+        eprintln!("Save this seed phrase to recover the keypair written to {}:", out);
+        eprintln!("{}", mnemonic.phrase());
+    }
+
+    println!("{}", pubkey);
+}
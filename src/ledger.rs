@@ -0,0 +1,258 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use crate::Pubkey;
+
+// The vendor id of Ledger's USB devices
+#[cfg(target_os = "linux")]
+const LEDGER_VENDOR_ID : u32 = 0x2c97;
+
+// The Solana app's instruction to get the public key at a derivation path, without confirmation on the device
+#[cfg(target_os = "linux")]
+const GET_PUBKEY : u8 = 0x05;
+
+const HARDENED : u32 = 1 << 31;
+
+// The derivation path that the Solana CLI uses for a usb://ledger URL: m/44'/501', followed by the account and change
+// indexes of ?key=<ACCOUNT>[/<CHANGE>], all hardened
+fn derivation_path(key : Option<&str>) -> Result<Vec<u32>, String>
+{
+    let mut path = vec![44 | HARDENED, 501 | HARDENED];
+
+    if let Some(key) = key {
+        let indexes = key.split('/').collect::<Vec<&str>>();
+        if indexes.len() > 2 {
+            return Err(format!("key={} has more than an account and a change index", key));
+        }
+        for index in indexes {
+            path.push(
+                index
+                    .trim_end_matches('\'')
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|index| *index < HARDENED)
+                    .map(|index| index | HARDENED)
+                    .ok_or_else(|| format!("key={} has an invalid index: {}", key, index))?
+            );
+        }
+    }
+
+    Ok(path)
+}
+
+// Parses a URL such as usb://ledger?key=0/0 or usb://ledger/<WALLET_ID>?key=0 into the wallet id, if any, and the
+// derivation path
+fn parse_url(url : &str) -> Result<(Option<Pubkey>, Vec<u32>), String>
+{
+    let rest = url.strip_prefix("usb://ledger").ok_or_else(|| format!("{} is not a usb://ledger URL", url))?;
+
+    let (wallet_id, query) = match rest.split_once('?') {
+        Some((wallet_id, query)) => (wallet_id, Some(query)),
+        None => (rest, None)
+    };
+
+    let wallet_id = match wallet_id.trim_start_matches('/') {
+        "" => None,
+        wallet_id => Some(wallet_id.parse::<Pubkey>().map_err(|e| format!("invalid wallet id {}: {}", wallet_id, e))?)
+    };
+
+    let mut key = None;
+    for parameter in query.into_iter().flat_map(|query| query.split('&')) {
+        match parameter.split_once('=') {
+            Some(("key", value)) => key = Some(value),
+            _ => return Err(format!("{} has an unknown parameter: {}", url, parameter))
+        }
+    }
+
+    Ok((wallet_id, derivation_path(key)?))
+}
+
+// A Ledger device's HID interface, as a hidraw device
+#[cfg(target_os = "linux")]
+struct Device
+{
+    path : std::path::PathBuf,
+
+    file : std::fs::File
+}
+
+#[cfg(target_os = "linux")]
+impl Device
+{
+    // The size of HID packets, not counting the report id that is written before each
+    const PACKET_SIZE : usize = 64;
+
+    // The transport header of each packet: the channel, the APDU tag, and the packet's sequence number
+    const HEADER_SIZE : usize = 5;
+
+    // Finds the hidraw devices of the Ledger devices that are connected.  Each Ledger device has several HID
+    // interfaces, of which the first is the one that takes APDUs.  Without hidraw support, there are none.
+    fn find() -> Vec<std::path::PathBuf>
+    {
+        let entries = match std::fs::read_dir("/sys/class/hidraw") {
+            Ok(entries) => entries,
+            Err(_) => return vec![]
+        };
+
+        let mut paths = entries
+            .filter_map(|entry| {
+                let sys_path = entry.ok()?.path();
+                let uevent = std::fs::read_to_string(sys_path.join("device/uevent")).ok()?;
+                // HID_ID is <BUS>:<VENDOR>:<PRODUCT>, in hex
+                let vendor_id = uevent.lines().find_map(|line| line.strip_prefix("HID_ID="))?.split(':').nth(1)?;
+                if u32::from_str_radix(vendor_id, 16).ok()? != LEDGER_VENDOR_ID {
+                    return None;
+                }
+                let interface = std::fs::read_to_string(sys_path.join("device/../bInterfaceNumber")).ok()?;
+                if interface.trim() != "00" {
+                    return None;
+                }
+                Some(std::path::Path::new("/dev").join(sys_path.file_name()?))
+            })
+            .collect::<Vec<std::path::PathBuf>>();
+
+        paths.sort();
+        paths
+    }
+
+    fn open(path : std::path::PathBuf) -> Result<Self, String>
+    {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        Ok(Device { path, file })
+    }
+
+    // Sends an APDU to the Solana app and returns its response, without the status word, splitting both into packets
+    // as Ledger's HID transport does
+    fn exchange(
+        &mut self,
+        instruction : u8,
+        data : &[u8]
+    ) -> Result<Vec<u8>, String>
+    {
+        use std::io::{Read, Write};
+
+        let error = |e : std::io::Error| format!("{}: {}", self.path.display(), e);
+
+        // The APDU: class, instruction, P1, P2, and the length of the data, which is preceded by the length of the APDU
+        let mut apdu = vec![0xe0, instruction, 0, 0, data.len() as u8];
+        apdu.extend_from_slice(data);
+        let mut message = (apdu.len() as u16).to_be_bytes().to_vec();
+        message.extend(apdu);
+
+        for (sequence, chunk) in message.chunks(Self::PACKET_SIZE - Self::HEADER_SIZE).enumerate() {
+            let mut packet = [0_u8; Self::PACKET_SIZE + 1];
+            packet[1..6].copy_from_slice(&[0x01, 0x01, 0x05, (sequence >> 8) as u8, sequence as u8]);
+            packet[6..(6 + chunk.len())].copy_from_slice(chunk);
+            self.file.write_all(&packet).map_err(error)?;
+        }
+
+        let mut response = Vec::new();
+        let mut length = None;
+        for sequence in 0..=u16::MAX {
+            let mut packet = [0_u8; Self::PACKET_SIZE];
+            let size = self.file.read(&mut packet).map_err(error)?;
+            if (size < Self::HEADER_SIZE) ||
+                (packet[..3] != [0x01, 0x01, 0x05]) ||
+                (u16::from_be_bytes([packet[3], packet[4]]) != sequence)
+            {
+                return Err(format!("{}: unexpected response from the device", self.path.display()));
+            }
+
+            let mut payload = &packet[Self::HEADER_SIZE..size];
+            if sequence == 0 {
+                if payload.len() < 2 {
+                    return Err(format!("{}: unexpected response from the device", self.path.display()));
+                }
+                length = Some(u16::from_be_bytes([payload[0], payload[1]]) as usize);
+                payload = &payload[2..];
+            }
+            response.extend_from_slice(payload);
+
+            let length = length.unwrap_or(0);
+            if response.len() >= length {
+                response.truncate(length);
+                break;
+            }
+        }
+
+        if response.len() < 2 {
+            return Err(format!("{}: unexpected response from the device", self.path.display()));
+        }
+        let status = u16::from_be_bytes([response[response.len() - 2], response[response.len() - 1]]);
+        response.truncate(response.len() - 2);
+
+        match status {
+            0x9000 => Ok(response),
+            0x6d00 | 0x6e00 | 0x6e01 | 0x6511 => {
+                Err("the Solana app is not open on the Ledger device, or is too old to be supported".to_string())
+            },
+            0x5515 | 0x6b0c => Err("the Ledger device is locked".to_string()),
+            0x6985 => Err("the request was rejected on the Ledger device".to_string()),
+            status => Err(format!("the Ledger device returned the error status {:04x}", status))
+        }
+    }
+
+    fn pubkey(
+        &mut self,
+        path : &[u32]
+    ) -> Result<Pubkey, String>
+    {
+        let mut data = vec![path.len() as u8];
+        path.iter().for_each(|index| data.extend_from_slice(&index.to_be_bytes()));
+
+        let response = self.exchange(GET_PUBKEY, &data)?;
+        let pubkey = response
+            .try_into()
+            .map_err(|response : Vec<u8>| format!("the Ledger device returned a {} byte public key", response.len()))?;
+        Ok(Pubkey(pubkey))
+    }
+}
+
+// Reads the public key that a usb://ledger URL names from a Ledger device running the Solana app.  If the URL has a
+// wallet id, the device is the one whose m/44'/501' key it is; otherwise there must be only one device.
+#[cfg(target_os = "linux")]
+pub fn pubkey(url : &str) -> Result<Pubkey, String>
+{
+    let (wallet_id, path) = parse_url(url)?;
+
+    let paths = Device::find();
+    if paths.is_empty() {
+        return Err("no Ledger device is connected".to_string());
+    }
+
+    let mut device = match wallet_id {
+        Some(wallet_id) => {
+            let mut found = None;
+            for path in paths {
+                let mut device = Device::open(path)?;
+                if device.pubkey(&derivation_path(None)?)? == wallet_id {
+                    found = Some(device);
+                    break;
+                }
+            }
+            found.ok_or_else(|| format!("no connected Ledger device has the wallet id {}", wallet_id))?
+        },
+        None if paths.len() == 1 => Device::open(paths.into_iter().next().unwrap())?,
+        None => {
+            return Err(format!(
+                "{} Ledger devices are connected, so give the wallet id of one as usb://ledger/<WALLET_ID>",
+                paths.len()
+            ))
+        },
+    };
+
+    device.pubkey(&path)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pubkey(url : &str) -> Result<Pubkey, String>
+{
+    parse_url(url)?;
+
+    Err("Ledger devices are only supported on Linux".to_string())
+}
@@ -1,44 +1,254 @@
 /**
  * LICENSE: Public Domain
  **/
+mod alias;
+mod anchor;
+mod batch;
+mod bench;
+mod config;
+mod convert_key;
+mod crack;
+mod curve;
+mod grind;
+mod keygen;
+mod ledger;
+mod man;
+mod mnemonic;
+mod normalize;
+mod parallel;
+#[cfg(feature = "parity")]
+mod parity;
+mod repl;
+mod selftest;
+mod sign;
+mod solana_config;
+mod transaction;
+mod tui;
+mod watch;
+
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use config::Config;
+use normalize::Normalization;
 use sha2::{Digest, Sha256};
+use std::io::{BufRead, Read};
+use std::num::IntErrorKind;
 use std::str::FromStr;
+use transaction::Message;
+use zeroize::Zeroizing;
+
+// The types of seed, as a table, which usage_string() and the man page both give
+#[rustfmt::skip]
+macro_rules! seed_grammar {
+    () => {
+        "\x20   u8[values]     : values is a comma-separated list of numbers in the\n\
+         \x20                    range [0, 255]\n\
+         \x20   u16[values]    : values is a comma-separated list of numbers in the\n\
+         \x20                    range [0, 65535]\n\
+         \x20   u32[values]    : values is a comma-separated list of numbers in the\n\
+         \x20                    range [0, 4294967295]\n\
+         \x20   u64[values]    : values is a comma-separated list of numbers in the\n\
+         \x20                    range [0, 18446744073709551615]\n\
+         \x20   String[value]  : value is a string, which may contain [ and ] only in\n\
+         \x20                    balanced pairs\n\
+         \x20   Pubkey[value] : value is a Base58-encoded ed25519 public key\n\
+         \x20   Sha256[SEED]   : value is a SEED (i.e. u8(10))\n\
+         \x20   Pda[PROGRAM_ID, SEED, ...]\n\
+         \x20                  : the PDA of PROGRAM_ID and the SEEDs, with the\n\
+         \x20                    canonical bump seed, as for the PDA of a PDA\n"
+    };
+}
+
+const SEED_GRAMMAR : &str = seed_grammar!();
 
 #[rustfmt::skip]
 fn usage_string() -> String
 {
-    "\nUsage: solpda [--help]\n\
-    \x20      solpda [--no-bump-seed] [--bytes] <PROGRAM_ID> <SEED>...\n\
-    \x20      solpda -pubkey [--bytes] <PROGRAM_ID>\n\n\
+    concat!("\nUsage: solpda [--help]\n\
+    \x20      solpda --version\n\
+    \x20      solpda [--no-bump-seed] [--bytes] <PROGRAM_ID> <SEED>... [-- <SEED>...]...\n\
+    \x20      solpda [--no-bump-seed] [--bytes] [--jobs <N>] [--unordered] [-0]\n\
+    \x20             --stdin [<PROGRAM_ID>]\n\
+    \x20      solpda [--no-bump-seed] [--bytes] --matrix <PROGRAM_ID> <SEED>...\n\
+    \x20      solpda [--no-bump-seed] [--bytes] --count <N> <PROGRAM_ID> [<SEED>...]\n\
+    \x20      solpda [--no-bump-seed] [--bytes] (--program <PROGRAM_ID>)...\n\
+    \x20             [--programs-file <FILE>] <SEED>...\n\
+    \x20      solpda [--no-bump-seed] [--bytes] --watch <SPEC>\n\
+    \x20      solpda [--no-bump-seed] [--bytes] --anchor [<PROGRAM_NAME>] <SEED>...\n\
+    \x20      solpda --check [--no-bump-seed] <PROGRAM_ID> <SEED>... [-- <SEED>...]...\n\
+    \x20      solpda -pubkey [--bytes] <PROGRAM_ID>...\n\
+    \x20      solpda -pubkey [--bytes] --use-solana-config\n\
+    \x20      solpda -pubkey [--bytes] --mnemonic [--derivation-path [<PATH>]]\n\
+    \x20      solpda -pubkey [--bytes] --mnemonic --accounts <RANGE>\n\
+    \x20      solpda -pubkey [--bytes] --from-tx <BASE64_TX>\n\
+    \x20      solpda batch [--no-bump-seed] [--format <FORMAT>] [--jobs <N>] [--unordered]\n\
+    \x20             [--dedupe] [--shard <I>/<N>] [--sort <KEY>] [--fail-fast] [--stats]\n\
+    \x20             [-0] [--output <OUT>] [--compress zst] [--state <STATE>] <FILE>\n\
+    \x20      solpda tx-verify [--bump <BUMP> [--require-canonical]] <BASE64_TX>\n\
+    \x20             <PROGRAM_ID> <SEED>...\n\
+    \x20      solpda oncurve <ADDRESS>\n\
+    \x20      solpda grind <PROGRAM_ID> <SEED>... --vary <TYPE> <PATTERN>...\n\
+    \x20      solpda grind --estimate [<PROGRAM_ID> <SEED>... --vary <TYPE>] <PATTERN>...\n\
+    \x20      solpda grind-worker <ADDRESS> [--threads <N>]\n\
+    \x20      solpda crack <PDA> <PROGRAM_ID> --template <TEMPLATE> [--range <RANGE>]\n\
+    \x20      solpda grind-key <PATTERN>... [--out <FILE>] [--force]\n\
+    \x20      solpda keygen [--out <FILE>] [--force] [--seed-phrase]\n\
+    \x20      solpda convert-key --from <FORMAT> --to <FORMAT> [--out <FILE>] [--force]\n\
+    \x20             <KEY>\n\
+    \x20      solpda sign [-k <KEYPAIR>] <MESSAGE>\n\
+    \x20      solpda verify-sig <PUBKEY> <SIGNATURE> <MESSAGE>\n\
+    \x20      solpda bench [--time <TIME>]\n\
+    \x20      solpda selftest\n\
+    \x20      solpda repl [--no-bump-seed] [--bytes] [<PROGRAM_ID>]\n\
+    \x20      solpda tui [<PROGRAM_ID> [<SEED>...]]\n\
+    \x20      solpda alias add [--force] <NAME> <ADDRESS>\n\
+    \x20      solpda alias remove <NAME>\n\
+    \x20      solpda alias list\n\
+    \x20      solpda man [<COMMAND>]\n\n\
+    \x20 Each command also takes --help, which lists all of its options, for\n\
+    \x20 example solpda grind --help.  Options may be given anywhere on the\n\
+    \x20 command line, before, between, or after the program id and seeds, as\n\
+    \x20 in solpda <PROGRAM_ID> <SEED>... --bytes.\n\n\
+    \x20 --version outputs the version of solpda, along with the git commit it\n\
+    \x20 was built from, the date it was built, and the cargo features it was\n\
+    \x20 built with (such as parity), which tell builds of the same version\n\
+    \x20 apart.\n\n\
+    \x20 Defaults for some options may be set in ~/.config/solpda/config.toml,\n\
+    \x20 which options given on the command line take precedence over:\n\n\
+    \x20   bytes = true           : output addresses as with --bytes\n\
+    \x20   no_bump_seed = true    : derive PDAs as with --no-bump-seed, unless\n\
+    \x20                            --bump is given (also for batch)\n\
+    \x20   no_limit_check = true  : check seeds as with --no-limit-check, unless\n\
+    \x20                            --cross-check is given (also for batch, repl,\n\
+    \x20                            and tui)\n\
+    \x20   strict = true          : fail on a keypair program id as with --strict\n\
+    \x20   allow_keypair = true   : allow a keypair program id as with\n\
+    \x20                            --allow-keypair, unless --strict is given\n\
+    \x20   jobs = 4               : the --jobs to use if none is given (also for\n\
+    \x20                            batch)\n\
+    \x20   threads = 4            : the --threads of grind, grind-key,\n\
+    \x20                            grind-worker, and crack if none is given\n\
+    \x20   format = \"json\"        : the batch --format to use if none is given\n\
+    \x20   normalize = \"nfc\"      : the --normalize form to use if none is given\n\
+    \x20                            (also for batch, repl, and tui)\n\
+    \x20   program_id_format =    : the --program-id-format to use if none is\n\
+    \x20     \"base58\"               given (also for batch, repl, and tui)\n\
+    \x20   program_id = \"<ID>\"    : the <PROGRAM_ID> to use if none is given and\n\
+    \x20                            SOLPDA_PROGRAM_ID is not set\n\n\
+    \x20 Each of these settings but program_id may also be given by the\n\
+    \x20 environment variable SOLPDA_ followed by its name in upper case, such as\n\
+    \x20 SOLPDA_BYTES or SOLPDA_PROGRAM_ID_FORMAT, which takes precedence over the\n\
+    \x20 config file (true or false settings may be given as 1 or 0 too), as\n\
+    \x20 SOLPDA_PROGRAM_ID does over its program_id.  This suits containers and\n\
+    \x20 CI.  Options given on the command line still take precedence over them.\n\n\
+    \x20 --no-bytes and --bump-seed undo bytes = true and no_bump_seed = true for\n\
+    \x20 a single command (--bump-seed also for batch, and both for repl).\n\n\
     \x20 solpda computes the Solana Program Derived Address for a given program and\n\
     \x20 set of seeds.  It outputs the PDA as either an array of byte values if the\n\
     \x20 --bytes option is provided, or as a Base58-encoded address if not.  Unless\n\
     \x20 [--no-bump-seeed] is specified, it also appends a bump seed automatically\n\
     \x20 starting with 255 and reducing down to 0 until a valid PDA is found, and\n\
     \x20 also outputs the \"bump seed\" that was used to derive the PDA.\n\n\
+    \x20 If there is no PDA, solpda says why on stderr and exits with status 1,\n\
+    \x20 or with --no-bump-seed, status 3, since then the seeds alone give an\n\
+    \x20 address on the ed25519 curve, as about half of all seeds do.\n\n\
     \x20 <PROGRAM_ID> is either the Base58-encoded address of the program for\n\
-    \x20   which to compute the PDA, or a file containing a JSON array of the bytes\n\
-    \x20   of the same, or a array of u8 bytes.\n\n\
-    \x20 One or more <SEED> values are provided.  Each SEED is specified as:\n\n\
-    \x20   u8[values]     : values is a comma-separated list of numbers in the\n\
-    \x20                    range [0, 255]\n\
-    \x20   u16[values]    : values is a comma-separated list of numbers in the\n\
-    \x20                    range [0, 65535]\n\
-    \x20   u32[values]    : values is a comma-separated list of numbers in the\n\
-    \x20                    range [0, 4294967295]\n\
-    \x20   u64[values]    : values is a comma-separated list of numbers in the\n\
-    \x20                    range [0, 18446744073709551615]\n\
-    \x20   String[value]  : value is a string\n\
-    \x20   Pubkey[value] : value is a Base58-encoded ed25519 public key\n\
-    \x20   Sha256[SEED]   : value is a SEED (i.e. u8(10))\n\n\
+    \x20   which to compute the PDA, or a key file containing a JSON array of the\n\
+    \x20   64 bytes of its keypair (as the Solana CLI writes them) or of the 32\n\
+    \x20   bytes of its address, or of the 64 bytes of its keypair in base64 (as\n\
+    \x20   some wallets export them), or a array of u8 bytes.  If it is -, it is\n\
+    \x20   read from stdin, as either a base58 address or the contents of a key\n\
+    \x20   file:\n\n\
+    \x20     $ solana address -k program.json | solpda - 'String[config]'\n\n\
+    \x20   If the SOLPDA_PROGRAM_ID environment variable is set, <PROGRAM_ID> may\n\
+    \x20   be left out (so that the first argument is a seed, or for -pubkey and\n\
+    \x20   --stdin, there are no arguments), and SOLPDA_PROGRAM_ID is read as it\n\
+    \x20   would have been:\n\n\
+    \x20     $ export SOLPDA_PROGRAM_ID=$PROGRAM_ID\n\
+    \x20     $ solpda 'String[config]'\n\n\
+    \x20   Within an Anchor workspace, --anchor [<PROGRAM_NAME>] may be given\n\
+    \x20   instead of <PROGRAM_ID>, to use the id of the workspace's program named\n\
+    \x20   <PROGRAM_NAME>, which may be left out if there is only one.  The\n\
+    \x20   workspace is found by looking for Anchor.toml in the current directory\n\
+    \x20   and those above it, and the program id is the address that it declares\n\
+    \x20   for the provider's cluster, or else that of the program's keypair in\n\
+    \x20   target/deploy:\n\n\
+    \x20     $ solpda --anchor my_program 'String[config]'\n\n\
+    \x20 One or more <SEED> values are provided.  Each SEED is specified as:\n\n",
+    seed_grammar!(),
+    "\n\
+    \x20 As on Solana, each <SEED> may be at most 32 bytes long, and there may be\n\
+    \x20 at most 16 seeds, counting the bump seed if there is one; seeds outside\n\
+    \x20 of these limits are reported as errors, since Solana would reject the\n\
+    \x20 PDA derived from them.  --no-limit-check, which may be given to any\n\
+    \x20 command that derives PDAs other than tx-verify, derives them anyway.\n\
+    \x20 Even then, the main command and batch reject seeds totalling more than\n\
+    \x20 64 KiB, which are more likely a mistake, such as a whole file given as\n\
+    \x20 a String seed, than anything that could be used on Solana, unless\n\
+    \x20 --allow-large-seeds is also given.\n\n\
     \x20 If [--bytes] was specified, then the PDA is output as a byte array, else the\n\
     \x20 PDA is output as a Base58-encoded string.\n\n\
     \x20 Unless [--no-bump-seed] was specified, the PDA is first output and then the\n\
     \x20 seed is output as \".SEED\"\n\n\
+    \x20 --bump <BUMP> uses <BUMP> as the bump seed instead of searching for one,\n\
+    \x20 as Solana's create_program_address does, and fails if the result is on\n\
+    \x20 the curve and so cannot be a PDA.  It may also be given with the\n\
+    \x20 --stdin, --matrix, --count, --program, and --watch options below.\n\n\
+    \x20 --cross-check, which may be given wherever --bump may, also derives each\n\
+    \x20 PDA with the Solana SDK's own find_program_address (or\n\
+    \x20 create_program_address) and fails, reporting both results, if they\n\
+    \x20 differ.  It is only available if solpda was built with the parity\n\
+    \x20 feature (cargo build --features parity), and cannot be used with\n\
+    \x20 --no-limit-check, as the Solana SDK rejects seeds outside of its limits.\n\n\
+    \x20 Since a PDA is derived from the bytes of its seeds one after another,\n\
+    \x20 with nothing between them, different seeds may give the same PDA, e.g.\n\
+    \x20 String[ab] String[c] and String[a] String[bc].  --diagnose, which may be\n\
+    \x20 given wherever --bump may, warns on stderr about seeds for which this may\n\
+    \x20 be misleading: adjacent String seeds, whose boundary has no effect on the\n\
+    \x20 PDA; empty seeds, which have no effect on the PDA at all, but still count\n\
+    \x20 towards the limit on the number of seeds; and, with --no-limit-check,\n\
+    \x20 seeds longer than Solana allows.\n\n\
+    \x20 Text that looks the same may be encoded in different ways, e.g. an\n\
+    \x20 accented letter may be a single character or a letter followed by a\n\
+    \x20 combining accent, and each gives a different PDA.  --normalize <FORM>,\n\
+    \x20 which may be given wherever --bump may, normalizes the text of String\n\
+    \x20 seeds to Unicode normalization form <FORM>, which is nfc, nfkc, or none\n\
+    \x20 (the default).  solpda warns on stderr about String seeds containing\n\
+    \x20 invisible characters, or, if they aren't normalized, combining\n\
+    \x20 characters.\n\n\
+    \x20 Each <PROGRAM_ID> is read as whichever of a key file, a base58 address,\n\
+    \x20 or a byte array works first, so a base58 address that happens to be the\n\
+    \x20 name of a file is read from the file.  --program-id-format <FORMAT>,\n\
+    \x20 which may be given wherever --bump may, reads every program id\n\
+    \x20 (including those given to --program, listed by --programs-file, read by\n\
+    \x20 --stdin or from a --watch spec, and of Pda seeds) in one way only:\n\
+    \x20 base58; pubkey-bytes, a byte array; keypair-file, a key file; or\n\
+    \x20 pubkey-file, a file containing a base58 address or byte array.\n\n\
+    \x20 A key file within an Anchor workspace may be the wrong deploy keypair,\n\
+    \x20 so solpda warns on stderr if the workspace's Anchor.toml declares a\n\
+    \x20 different address for the program that the file is named for (as\n\
+    \x20 <NAME>-keypair.json), or, for other key files, doesn't declare its\n\
+    \x20 address for any program.\n\n\
+    \x20 Deriving PDAs needs only a program's address, so a <PROGRAM_ID> (or\n\
+    \x20 --program) that is a key file holding a keypair, and so a secret key,\n\
+    \x20 is most likely the wrong file, and solpda warns on stderr that its\n\
+    \x20 secret key may have been exposed.  With --strict, solpda fails instead.\n\
+    \x20 --allow-keypair allows such key files without a warning.  Key files\n\
+    \x20 found by --anchor, and those given to -pubkey, whose purpose is to read\n\
+    \x20 public keys from them, are always allowed.\n\n\
+    \x20 A key file holds either the 64 bytes of a keypair or the 32 bytes of a\n\
+    \x20 public key, and is read as whichever its length says it is.  --expect\n\
+    \x20 <KIND>, where <KIND> is pubkey or keypair, instead requires every\n\
+    \x20 <PROGRAM_ID> (and --program) to be that kind of key, failing if one is\n\
+    \x20 not: with pubkey, a key file holding a keypair is an error, and with\n\
+    \x20 keypair, anything but a key file holding a keypair is.  Since --expect\n\
+    \x20 keypair says that a keypair is meant, it also allows one as\n\
+    \x20 --allow-keypair does.\n\n\
     \x20 Example:\n\
     \x20   $ PROGRAM_ID=TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA\n\n\
     \x20   $ solpda --no-bump-seed $PROGRAM_ID u8[5,6] 'String[Hello, world!]'\n\
-    \x20     Cannot find PDA, consider allowing bump seed\n\n\
+    \x20     The seeds without a bump seed give an address on the ed25519 curve,\n\
+    \x20     which cannot be a PDA\n\n\
     \x20   $ solpda $PROGRAM_ID u8[5,6] 'String[Hello, world!]' u8[10]\n\
     \x20     A89GCYdsataUVrFDbrV416NEZnFZoa6X4CR5ZdSPJohC.255\n\n\
     \x20   $ solpda --bytes $PROGRAM_ID u8[5,6] 'String[Hello, world!]'\n\
@@ -47,25 +257,352 @@ fn usage_string() -> String
     \x20   $ solpda --no-bump-seed --bytes $PROGRAM_ID u8[5,6] 'String[Hello, world!]' u8[10]\n\
     \x20     [42,46,105,65,231,188,62,57,241,154,124,211,106,133,201,219,\\\n\
     \x20      254,69,136,17,107,6,180,194,222,36,56,108,166,70,47,226]\n\n\
-    \x20 As a convenience, solpda also supports the -pubkey argument which causes\n\
-    \x20 it to do nothing other than read the <PROGRAM_ID> argument, which is\n\
-    \x20 either a Base58-encoded public key, or a key file, or an array of u8\n\
-    \x20 bytes, and print out the public key that was read in, as either an array\n\
-    \x20 of bytes (if --bytes was specified), or as a Base58-encoded string (if\n\
-    \x20 --bytes was not specified).\n\n".to_string()
+    \x20 Several lists of <SEED> values may be given, separated by --, in which\n\
+    \x20 case the PDA for each list is output in turn.  This also applies to the\n\
+    \x20 --matrix, --count, and --program options below, with a blank line between\n\
+    \x20 the output for each list:\n\n\
+    \x20   $ solpda $PROGRAM_ID 'String[a]' u64[1] -- 'String[b]' u64[2]\n\n\
+    \x20 With --check, solpda only checks that the program id and each list of\n\
+    \x20 seeds are valid, as they would be checked before deriving, and outputs\n\
+    \x20 the length in bytes of each seed followed by the seed, with a blank line\n\
+    \x20 between lists, without deriving any PDA.  This is a cheap way to check\n\
+    \x20 generated seeds before deriving them.  Invalid seeds are reported on\n\
+    \x20 stderr, and solpda exits with a non-zero status at the end.\n\n\
+    \x20 With --stdin, solpda instead reads one derivation per line from stdin and\n\
+    \x20 outputs one PDA per line.  Each line is a list of <SEED> values separated\n\
+    \x20 by spaces (spaces within brackets are part of the seed), optionally\n\
+    \x20 preceded by the <PROGRAM_ID> to use for that line, which is required if\n\
+    \x20 none is given on the command line.  Blank lines are skipped.  Lines that\n\
+    \x20 fail are reported on stderr with their line number, and solpda exits\n\
+    \x20 with a non-zero status at the end:\n\n\
+    \x20   $ printf 'u8[1]\\nu8[2]\\n' | solpda --stdin $PROGRAM_ID\n\n\
+    \x20 Lines are derived in parallel on all available cores, or on --jobs <N>\n\
+    \x20 threads.  The output is in the same order as the input, unless\n\
+    \x20 --unordered is given, in which case each PDA is output as soon as it is\n\
+    \x20 found, which is faster when a few derivations take much longer than the\n\
+    \x20 rest, but leaves nothing to match PDAs with their lines by.\n\n\
+    \x20 With -0, --stdin reads lines terminated by NUL rather than newline, and\n\
+    \x20 terminates each PDA output with NUL, so that seeds may contain newlines\n\
+    \x20 and the output may be passed on to tools such as xargs -0.  Likewise,\n\
+    \x20 batch -0 terminates each record of text output with NUL, so that labels\n\
+    \x20 may contain newlines.\n\n\
+    \x20 With --matrix, any <SEED> may instead be written as TYPE{V1,V2,...} to\n\
+    \x20 stand for each of the seeds TYPE[V1], TYPE[V2], and so on, where for the\n\
+    \x20 u8, u16, u32, and u64 types a value may also be a range START..END or\n\
+    \x20 START..=END.  A PDA is derived for every combination of these seeds, and\n\
+    \x20 each is output after the seeds with alternatives that produced it:\n\n\
+    \x20   $ solpda --matrix $PROGRAM_ID 'Pubkey{'$WALLET1,$WALLET2'}' \\\n\
+    \x20       'Pubkey{'$MINT1,$MINT2'}'\n\
+    \x20   $ solpda --matrix $PROGRAM_ID 'String[vault]' 'u64{0..100}'\n\n\
+    \x20 For the Pubkey type, a value may also be @<FILE>, which stands for each\n\
+    \x20 address listed in <FILE>, one per line (blank lines and lines starting\n\
+    \x20 with # are ignored), to derive the PDAs of many wallets or mints at once,\n\
+    \x20 such as their associated token accounts:\n\n\
+    \x20   $ solpda --matrix ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL \\\n\
+    \x20       'Pubkey{@wallets.txt}' \\\n\
+    \x20       Pubkey[TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA] Pubkey[$MINT]\n\n\
+    \x20 --count <N> appends an index seed u64[i] to the seeds and outputs the PDAs\n\
+    \x20 for each i from 0 to N - 1, labeled as for --matrix (with which it may be\n\
+    \x20 combined):\n\n\
+    \x20   $ solpda --count 3 $PROGRAM_ID 'String[vault]'\n\n\
+    \x20 Instead of giving <PROGRAM_ID> as the first argument, --program\n\
+    \x20 <PROGRAM_ID> may be given any number of times, and --programs-file <FILE>\n\
+    \x20 names a file listing one <PROGRAM_ID> per line (blank lines and lines\n\
+    \x20 starting with # are ignored).  The PDA for the seeds is derived for each\n\
+    \x20 program id, and when there is more than one, each is output after the\n\
+    \x20 program id it was derived for, as for --matrix (with which it may be\n\
+    \x20 combined):\n\n\
+    \x20   $ solpda --program $STAGING_ID --program $PROD_ID 'String[vault]'\n\n\
+    \x20 --watch <SPEC> derives the PDAs listed in the TOML file <SPEC>, and again\n\
+    \x20 whenever it changes, until interrupted.  <SPEC> has a [[pda]] table for\n\
+    \x20 each PDA, with seeds (an array of seeds, or a string of seeds separated\n\
+    \x20 by spaces) and optionally label and program_id; a program_id at the top\n\
+    \x20 level applies to each PDA that doesn't give its own.  PDAs are output as\n\
+    \x20 for --matrix, preceded by their labels:\n\n\
+    \x20   program_id = \"TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA\"\n\n\
+    \x20   [[pda]]\n\
+    \x20   label = \"vault\"\n\
+    \x20   seeds = [\"String[vault]\", \"u8[1]\"]\n\n\
+    \x20 As a convenience, solpda also supports the -pubkey argument (which may\n\
+    \x20 also be given as --pubkey) which causes it to do nothing other than read\n\
+    \x20 the <PROGRAM_ID> argument, which is either a Base58-encoded public key,\n\
+    \x20 or a key file, or an array of u8 bytes, and print out the public key\n\
+    \x20 that was read in, as either an array of bytes (if --bytes was\n\
+    \x20 specified), or as a Base58-encoded string (if --bytes was not\n\
+    \x20 specified).  Any number of <PROGRAM_ID> arguments may be\n\
+    \x20 given, along with --program and --programs-file as for deriving PDAs,\n\
+    \x20 and the public key of each is output in turn, one per line:\n\n\
+    \x20   $ solpda -pubkey keys/*.json\n\n\
+    \x20 With --use-solana-config instead of <PROGRAM_ID>, it reads the Solana\n\
+    \x20 CLI's default keypair, as set by solana config set --keypair in\n\
+    \x20 ~/.config/solana/cli/config.yml.\n\n\
+    \x20 <PROGRAM_ID> may also be a usb://ledger URL, as the Solana CLI accepts,\n\
+    \x20 such as usb://ledger?key=0/0, in which case the public key is read from\n\
+    \x20 the connected Ledger device, which must be running the Solana app.  The\n\
+    \x20 key is that at m/44'/501' followed by the indexes of key=, all hardened.\n\
+    \x20 If more than one Ledger device is connected, the URL must name one by its\n\
+    \x20 wallet id, as usb://ledger/<WALLET_ID>.  This is only supported on Linux,\n\
+    \x20 and requires read and write access to the device's /dev/hidraw* file.\n\n\
+    \x20 With --mnemonic instead of <PROGRAM_ID>, -pubkey prompts for a BIP39\n\
+    \x20 seed phrase and its passphrase (if any), and prints the public key of\n\
+    \x20 the keypair that solana-keygen recovers from them, so that no key file\n\
+    \x20 need be written.  As with solana-keygen, the secret key is the first 32\n\
+    \x20 bytes of the seed unless --derivation-path is given, in which case it is\n\
+    \x20 derived along <PATH> (m/44'/501'/0'/0' if <PATH> is not given), with\n\
+    \x20 every index taken to be hardened.\n\n\
+    \x20 --accounts <RANGE>, which is START..END or START..=END, instead derives\n\
+    \x20 the public key of each account in <RANGE>, with the path\n\
+    \x20 m/44'/501'/<ACCOUNT>'/0' that solana-keygen and most wallets use, and\n\
+    \x20 outputs each after its path, to find which account an address is:\n\n\
+    \x20   $ solpda -pubkey --mnemonic --accounts 0..10\n\n\
+    \x20 With --from-tx instead of <PROGRAM_ID>, -pubkey outputs the static\n\
+    \x20 account keys of <BASE64_TX>, which is a Base64-encoded serialized\n\
+    \x20 transaction or message (legacy or v0), in the order that the message\n\
+    \x20 lists them, to get the addresses involved in a transaction.  Addresses\n\
+    \x20 loaded from lookup tables cannot be read offline, so are only counted.\n\n\
+    \x20 The tx-verify command derives the PDA for <PROGRAM_ID> and <SEED>...\n\
+    \x20 (using the bump seed search, unless --bump is given) and then looks for\n\
+    \x20 it among the account keys of <BASE64_TX>, which is a Base64-encoded\n\
+    \x20 serialized transaction or message (legacy or v0).  It reports whether\n\
+    \x20 the PDA is writable or a signer, and which instructions reference it.\n\
+    \x20 It exits with status 1 if the PDA is not among the message's static\n\
+    \x20 account keys.\n\n\
+    \x20 With --bump <BUMP>, tx-verify derives the PDA with bump seed <BUMP>\n\
+    \x20 instead of searching for it, as create_program_address does, failing if\n\
+    \x20 the result is on the curve.  If <BUMP> is not the canonical bump seed\n\
+    \x20 (the one that the search finds), a warning is output, since programs\n\
+    \x20 that accept non-canonical bump seeds let callers choose between several\n\
+    \x20 addresses for the same seeds; with --require-canonical, tx-verify\n\
+    \x20 instead fails with exit status 2.\n\n\
+    \x20 The oncurve command reports whether <ADDRESS> (Base58-encoded, or an\n\
+    \x20 array of u8 bytes) is a point on the ed25519 curve, in which case it may\n\
+    \x20 be the public key of a keypair but cannot be a PDA, or off the curve, in\n\
+    \x20 which case it may be a PDA but nobody can hold its private key.  It\n\
+    \x20 exits with status 0 if <ADDRESS> is on the curve, or 1 if it is off.\n\n\
+    \x20 The grind command searches for a PDA whose Base58-encoded address matches\n\
+    \x20 any of the given patterns.  It appends a seed of type <TYPE> after the\n\
+    \x20 fixed <SEED> values, trying each possible value in turn, and outputs the\n\
+    \x20 first matching seed, PDA, and bump seed.  <TYPE> is one of:\n\n\
+    \x20   u8, u16, u32, u64 : numbers counting up from 0\n\
+    \x20   string            : strings of exactly --len <LEN> characters taken from\n\
+    \x20                       --charset <CHARSET> (default a-z0-9), which lists\n\
+    \x20                       characters and ranges of characters\n\n\
+    \x20 Each <PATTERN> is one of the following, and may be given more than once:\n\n\
+    \x20   --starts-with <TEXT> : the address starts with TEXT\n\
+    \x20   --ends-with <TEXT>   : the address ends with TEXT\n\
+    \x20   --contains <TEXT>    : the address contains TEXT anywhere\n\
+    \x20   --matches <REGEX>    : the address matches the regular expression REGEX\n\
+    \x20                          (e.g. '^Va.t' or '^[^0-9]+$')\n\n\
+    \x20 If --ignore-case is given, all patterns are matched without regard to\n\
+    \x20 upper or lower case.\n\n\
+    \x20 If --require-bump <BUMP> is given, only PDAs whose bump seed (as found by\n\
+    \x20 the usual search from 255 downwards) is exactly BUMP are considered.\n\n\
+    \x20 By default, grind stops once it finds an address matching any pattern.  If\n\
+    \x20 --count <N> is given, it instead searches until it has found N addresses\n\
+    \x20 matching each pattern, in a single pass over the seed space, and outputs\n\
+    \x20 all of them, grouped by pattern and labeled with it when there is more\n\
+    \x20 than one.\n\n\
+    \x20 The search is spread across all available cores unless --threads <N> is\n\
+    \x20 given.  The result is the same regardless of the number of threads: the\n\
+    \x20 lowest matching seed values are always output.\n\n\
+    \x20 Values are normally tried in increasing order.  If --grind-seed <N> is\n\
+    \x20 given, they are instead tried in a shuffled order determined by N (a\n\
+    \x20 number up to 18446744073709551615), so that different searches can cover\n\
+    \x20 different parts of the seed space while each remains reproducible: the\n\
+    \x20 first matches in that order are output, regardless of the number of\n\
+    \x20 threads or machines used.\n\n\
+    \x20 While searching, statistics (attempts, attempts per second, elapsed time,\n\
+    \x20 and expected time to find a match) are periodically written to stderr,\n\
+    \x20 unless --no-progress is given.\n\n\
+    \x20 For long searches in the background, --nice runs the search at the lowest\n\
+    \x20 scheduling priority, so that it only uses otherwise idle CPU time, and\n\
+    \x20 --pin <CPUS> (Linux only) restricts it to the CPUs listed in CPUS, such\n\
+    \x20 as 0-3,6, using one thread per CPU unless --threads is given.\n\n\
+    \x20 If --checkpoint <FILE> is given, the state of the search is saved to FILE\n\
+    \x20 every ten seconds and when the search ends.  An interrupted search can be\n\
+    \x20 continued by repeating the same command with --resume <FILE>; resuming a\n\
+    \x20 search that found a match continues on to find the next match.\n\n\
+    \x20 The search can be bounded with --max-attempts <N>, which limits the number\n\
+    \x20 of seed values tried, and --max-time <TIME>, where TIME is a number of\n\
+    \x20 seconds optionally followed by one of the units s, m, h, or d (e.g. 90m).\n\
+    \x20 grind exits with status 1 if the entire seed space was searched without\n\
+    \x20 finding enough matches, and 2 if a limit was reached first; any matches\n\
+    \x20 that were found are still output.\n\n\
+    \x20 For example:\n\n\
+    \x20   $ solpda grind $PROGRAM_ID 'String[vault]' --vary u64 --starts-with Va\n\
+    \x20     u64[10462] Vam1zYeFCavWQAie6pP9s3XBkyuDABGNie8jJXeiv6s.255\n\n\
+    \x20 With --estimate, grind instead measures how many attempts per second this\n\
+    \x20 machine makes over a few seconds, and outputs the expected number of\n\
+    \x20 attempts and the expected time needed to find the requested matches.\n\
+    \x20 <PROGRAM_ID>, <SEED>, and --vary may then be omitted:\n\n\
+    \x20   $ solpda grind --estimate --starts-with Vau\n\n\
+    \x20 A search can be spread across several machines by giving grind --serve\n\
+    \x20 <ADDRESS> (e.g. 0.0.0.0:7878), which makes it listen for workers instead\n\
+    \x20 of searching itself, and running grind-worker <ADDRESS> on each machine\n\
+    \x20 (with the --threads, --nice, and --pin options as for grind).  The seed\n\
+    \x20 space is handed out to workers in chunks of --chunk-size <N> values\n\
+    \x20 (default 1048576), chunks of workers that disconnect are handed out\n\
+    \x20 again, and the output is the same as that of a search on a single machine.\n\
+    \x20 --max-attempts and --max-time apply to the search as a whole.  Connections\n\
+    \x20 are neither authenticated nor encrypted, and workers are trusted to search\n\
+    \x20 their chunks completely, so only use this on a trusted network.\n\n\
+    \x20 The batch command derives a PDA for each record of <FILE> (or stdin if\n\
+    \x20 <FILE> is -), which is either a JSON array of objects with program_id,\n\
+    \x20 seeds, and optionally label fields, or CSV with a header row naming\n\
+    \x20 program_id, seeds, and optionally label columns.  seeds is a list of\n\
+    \x20 seeds as given on the command line, as a JSON array or separated by\n\
+    \x20 spaces.  <FORMAT> is text (the default), which outputs each PDA and bump\n\
+    \x20 seed followed by the label, or json or csv, which output a record for\n\
+    \x20 each input record with its PDA and bump seed or an error (in text output,\n\
+    \x20 errors are written to stderr).  Records that fail are reported and the\n\
+    \x20 rest are still derived, unless --fail-fast is given, which stops at the\n\
+    \x20 first failure (--keep-going restores the default).  At the end, the\n\
+    \x20 numbers of records processed, succeeded, and failed are written to\n\
+    \x20 stderr, and the exit status is -1 if any record was invalid, or 1 if\n\
+    \x20 any PDA could not be found (3 with --no-bump-seed, as for solpda).\n\
+    \x20 Records are derived in parallel as for --stdin, including the --jobs\n\
+    \x20 and --unordered options (the label, or the seeds in json and csv output,\n\
+    \x20 identify records output out of order):\n\n\
+    \x20   $ printf 'label,program_id,seeds\\nvault,%s,String[vault] u8[1]\\n' \\\n\
+    \x20       $PROGRAM_ID | solpda batch --format json -\n\n\
+    \x20 <FILE> may be compressed with gzip or zstd, which is detected from its\n\
+    \x20 contents (so the .json or .csv extension may be followed by .gz or\n\
+    \x20 .zst).  The output is compressed with zstd if --compress zst is given.\n\n\
+    \x20 A record may also give the PDA it is expected to derive, in an\n\
+    \x20 expected_pda field or column, and optionally its bump seed, in\n\
+    \x20 expected_bump.  Such a record is only output if it fails, which\n\
+    \x20 includes deriving a different PDA or bump seed, so that batch can check\n\
+    \x20 a list of known addresses and report only those that don't match (with\n\
+    \x20 exit status 1 if there are any).\n\n\
+    \x20 With --dedupe, batch remembers the result of each distinct derivation (by\n\
+    \x20 program id and seed bytes) and reuses it for repeats, which saves time\n\
+    \x20 when the input has many duplicates, at the cost of memory for every\n\
+    \x20 distinct derivation.\n\n\
+    \x20 --sort <KEY> outputs the results sorted by pda, label, or bump, with\n\
+    \x20 failed records last and ties kept in input order, so that the output of\n\
+    \x20 different runs can be compared line by line.  Sorting needs all of the\n\
+    \x20 results to be held in memory until the end.\n\n\
+    \x20 A large batch can be split across machines by running it on each with a\n\
+    \x20 different --shard <I>/<N>, where <I> is from 1 to <N>, which derives only\n\
+    \x20 records I, I + N, I + 2N, and so on (counting records from 1).\n\n\
+    \x20 The output of batch is written to <OUT> if --output is given.  Long runs\n\
+    \x20 can be made resumable by also giving --state <STATE>, to which progress\n\
+    \x20 is saved every ten seconds.  Repeating the same command after an\n\
+    \x20 interruption picks up where the saved state left off, discarding any\n\
+    \x20 output written since, so that <OUT> ends up the same as if the run had\n\
+    \x20 not been interrupted; <STATE> is removed once the run completes.\n\
+    \x20 --state cannot be used with --unordered.\n\n\
+    \x20 With --stats, batch also writes to stderr at the end the number of\n\
+    \x20 derivations made, the time taken, the number of derivations per second,\n\
+    \x20 and a histogram of the bump seeds of the PDAs found, which help to plan\n\
+    \x20 for large runs such as indexer backfills.\n\n\
+    \x20 Both batch and --stdin read their input as they go and output each PDA as\n\
+    \x20 soon as they can, so inputs of any size are processed in bounded memory.\n\n\
+    \x20 The crack command searches for the value of an unknown seed that, together\n\
+    \x20 with the known seeds, reproduces <PDA>.  <TEMPLATE> is the list of seeds,\n\
+    \x20 separated by spaces, with exactly one of them written as TYPE[?], where\n\
+    \x20 TYPE is u8, u16, u32, u64, or String (in which case --len <LEN> and\n\
+    \x20 optionally --charset <CHARSET> are required, as for grind).  <RANGE> is\n\
+    \x20 START..END or START..=END and limits the values tried; by default all\n\
+    \x20 values of TYPE are tried.  The canonical bump seed is assumed, unless\n\
+    \x20 --bump <BUMP> gives a specific one or --no-bump-seed is given.  The\n\
+    \x20 --threads and --no-progress options are as for grind.  The seeds that\n\
+    \x20 reproduce <PDA> are output, or crack exits with status 1 if none do:\n\n\
+    \x20   $ solpda crack Vam1zYeFCavWQAie6pP9s3XBkyuDABGNie8jJXeiv6s $PROGRAM_ID \\\n\
+    \x20       --template 'String[vault] u64[?]' --range 0..100000\n\
+    \x20     String[vault] u64[10462]\n\n\
+    \x20 The grind-key command generates random keypairs until it finds one whose\n\
+    \x20 Base58-encoded public key matches any of the given patterns (which are as\n\
+    \x20 for grind, as are the --ignore-case, --threads, --no-progress, --nice, and\n\
+    \x20 --pin options).  The keypair is written to <FILE> in the JSON format used\n\
+    \x20 by the Solana CLI, or to <PUBKEY>.json if --out is not given, and the\n\
+    \x20 public key is output.  An existing file is only overwritten if --force\n\
+    \x20 is given.\n\n\
+    \x20 The keygen command generates a random keypair and writes it as\n\
+    \x20 grind-key does, outputting its public key.  With --seed-phrase, the\n\
+    \x20 keypair is instead made from a new 12 word BIP39 seed phrase (without a\n\
+    \x20 passphrase), as solana-keygen new makes it, and the seed phrase is\n\
+    \x20 output on stderr so that the keypair may later be recovered with\n\
+    \x20 solana-keygen recover, or its public key found with -pubkey --mnemonic.\n\n\
+    \x20 The convert-key command converts the secret key in the file <KEY> (or\n\
+    \x20 stdin if <KEY> is -) from one format to another, as wallets and tools\n\
+    \x20 each expect their own.  Each <FORMAT> is one of:\n\n\
+    \x20   json   : a JSON array of the 64 bytes of the keypair, as the Solana CLI\n\
+    \x20            writes key files\n\
+    \x20   base58 : the 64 bytes of the keypair in base58, as wallets such as\n\
+    \x20            Phantom export private keys\n\
+    \x20   base64 : the 64 bytes of the keypair in base64\n\
+    \x20   seed   : the 32 byte secret key (the first half of the keypair) in hex,\n\
+    \x20            as other ed25519 tools use\n\n\
+    \x20 The converted key is output, or written to <FILE>, readable only by its\n\
+    \x20 owner, if --out is given; an existing file is only overwritten if --force\n\
+    \x20 is given.  A keypair whose public half is not that of its secret half\n\
+    \x20 is rejected:\n\n\
+    \x20   $ solpda convert-key --from base58 --to json --out id.json phantom.txt\n\n\
+    \x20 The sign command signs the bytes of <MESSAGE> (as given, not in Solana's\n\
+    \x20 off-chain message format) with the keypair in the key file <KEYPAIR>,\n\
+    \x20 or the Solana CLI's default keypair if -k is not given, and outputs the\n\
+    \x20 signature in base58.  The verify-sig command checks such a signature of\n\
+    \x20 <MESSAGE> by <PUBKEY> (given in any of the ways <PROGRAM_ID> may be),\n\
+    \x20 and exits with status 1 if it is not valid.  Together they can show\n\
+    \x20 that someone controls a key, such as a program's upgrade authority,\n\
+    \x20 without a transaction:\n\n\
+    \x20   $ solpda sign -k authority.json \"I control $PROGRAM_ID, 2024-01-01\"\n\
+    \x20   $ solpda verify-sig $AUTHORITY $SIGNATURE \\\n\
+    \x20       \"I control $PROGRAM_ID, 2024-01-01\"\n\n\
+    \x20 The bench command measures how many operations per second this machine\n\
+    \x20 performs on a single core for each of: deriving a PDA (including the bump\n\
+    \x20 seed search), checking whether a hash is a curve point (both as solpda\n\
+    \x20 does it and by decompressing the point, as Solana does), and parsing a\n\
+    \x20 seed.  Each is measured for --time <TIME> (as for grind --max-time,\n\
+    \x20 default 2s).\n\n\
+    \x20 The selftest command derives a set of PDAs whose results are known from\n\
+    \x20 the Solana SDK, including edge cases such as empty and 32 byte seeds,\n\
+    \x20 --no-bump-seed, specific bump seeds (both off and on the curve), and\n\
+    \x20 every bump seed from 0 to 255, and reports whether each matched.  It\n\
+    \x20 exits with status 0 if all did, or 1 if any did not.\n\n\
+    \x20 The repl command derives PDAs interactively: each line entered is a\n\
+    \x20 list of seeds, optionally preceded by a program id, as for --stdin, and\n\
+    \x20 its PDA is output in turn.  The program id is given once, as\n\
+    \x20 <PROGRAM_ID> or by program <PROGRAM_ID> at the prompt, for any number of\n\
+    \x20 lines of seeds.  Tab completes seed types, and lines entered are kept in\n\
+    \x20 a history, which is saved in ~/.config/solpda/repl_history.  help lists\n\
+    \x20 the commands, and quit or Ctrl-D leaves.\n\n\
+    \x20 The tui command is a terminal UI for exploring derivations, such as\n\
+    \x20 when working out the seeds that a program uses.  It shows the program\n\
+    \x20 id and a slot for each seed, with the length of each seed or why it is\n\
+    \x20 invalid, and recomputes the PDA, its bump seed, and the address given by\n\
+    \x20 every bump seed from 255 to 0 as they are edited.  On leaving, it\n\
+    \x20 outputs the solpda command line for the seeds it was left with.  It is\n\
+    \x20 only available if solpda was built with the tui feature (cargo build\n\
+    \x20 --features tui).\n\n\
+    \x20 The alias command keeps a book of names for addresses, in\n\
+    \x20 ~/.config/solpda/aliases.toml, which may be shared with others.  alias\n\
+    \x20 add names <ADDRESS>, which may be given in any way that <PROGRAM_ID> may,\n\
+    \x20 as <NAME>, which may contain only letters, digits, _, and -.  Wherever\n\
+    \x20 an address is accepted, including program ids and Pubkey seeds, @<NAME>\n\
+    \x20 then stands for it.  With --matrix, Pubkey{@<NAME>} is the alias if\n\
+    \x20 there is one by that name, and otherwise a file as described above:\n\n\
+    \x20   $ solpda alias add treasury $TREASURY\n\
+    \x20   $ solpda $PROGRAM_ID 'String[vault]' 'Pubkey[@treasury]'\n\n\
+    \x20 The man command outputs a man page in roff, generated from solpda's\n\
+    \x20 options, for solpda itself (including the seed types above) or, if\n\
+    \x20 <COMMAND> is given, for that command, whose page is named solpda-<COMMAND>:\n\n\
+    \x20   $ solpda man > solpda.1\n\
+    \x20   $ solpda man grind > solpda-grind.1\n\n").to_string()
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 struct Pubkey(pub [u8; 32]);
 
+// A list of bytes, which may have been pasted from a key file, so whose elements aren't shown in errors
 fn u8_list_to_vec(bytes : &str) -> Result<Vec<u8>, String>
 {
-    bytes
-        .replace(" ", "")
-        .split(",")
-        .map(|s| s.parse::<u8>().map_err(|e| e.to_string()))
-        .collect::<Result<Vec<u8>, String>>()
+    number_list_to_vec(bytes, u8::to_le_bytes, false).map_err(|e| e.message)
 }
 
+const BASE58_ALPHABET : &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
 const U8_PREFIX : &str = "u8[";
 const U16_PREFIX : &str = "u16[";
 const U32_PREFIX : &str = "u32[";
@@ -73,274 +610,2519 @@ const U64_PREFIX : &str = "u64[";
 const STRING_PREFIX : &str = "String[";
 const PUBKEY_PREFIX : &str = "Pubkey[";
 const SHA256_PREFIX : &str = "Sha256[";
+const PDA_PREFIX : &str = "Pda[";
+
+const SEED_PREFIXES : [&str; 8] =
+    [U8_PREFIX, U16_PREFIX, U32_PREFIX, U64_PREFIX, STRING_PREFIX, PUBKEY_PREFIX, SHA256_PREFIX, PDA_PREFIX];
+
+// Solana's limits on the length of each seed of a PDA, and on the number of seeds
+const MAX_SEED_LEN : usize = 32;
+const MAX_SEEDS : usize = 16;
 
-fn make_seed(s : &str) -> Vec<u8>
+// The limit on the total length of seeds when Solana's limits aren't checked, which is far beyond anything Solana
+// allows, to catch mistakes such as a whole file given as a seed
+const MAX_TOTAL_SEED_LEN : usize = 64 * 1024;
+
+// Which limits seeds are checked against
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SeedLimits
 {
-    if s.ends_with("]") {
-        let s = &s[0..(s.len() - 1)];
-        if s.starts_with(U8_PREFIX) {
-            return u8_list_to_vec(&s[U8_PREFIX.len()..]).unwrap();
-        }
-        else if s.starts_with(U16_PREFIX) {
-            return s[U16_PREFIX.len()..]
-                .replace(" ", "")
-                .split(",")
-                .map(|s| s.parse::<u16>().unwrap().to_le_bytes())
-                .flatten()
-                .collect();
-        }
-        else if s.starts_with(U32_PREFIX) {
-            return s[U32_PREFIX.len()..]
-                .replace(" ", "")
-                .split(",")
-                .map(|s| s.parse::<u32>().unwrap().to_le_bytes())
-                .flatten()
-                .collect();
-        }
-        else if s.starts_with(U64_PREFIX) {
-            return s[U64_PREFIX.len()..]
-                .replace(" ", "")
-                .split(",")
-                .map(|s| s.parse::<u64>().unwrap().to_le_bytes())
-                .flatten()
-                .collect();
-        }
-        else if s.starts_with(STRING_PREFIX) {
-            return s[STRING_PREFIX.len()..].as_bytes().to_vec();
-        }
-        else if s.starts_with(PUBKEY_PREFIX) {
-            return Pubkey::from_str(&s[PUBKEY_PREFIX.len()..]).unwrap().0.to_vec();
-        }
-        else if s.starts_with(SHA256_PREFIX) {
-            let mut hasher = Sha256::new();
-            hasher.update(&make_seed(&s[SHA256_PREFIX.len()..]));
-            return hasher.finalize().to_vec();
+    // Solana's limits on the length and number of seeds
+    Solana,
+
+    // Only MAX_TOTAL_SEED_LEN, as with --no-limit-check
+    Total,
+
+    // No limits at all, as with --no-limit-check and --allow-large-seeds
+    None
+}
+
+impl SeedLimits
+{
+    fn new(
+        limit_check : bool,
+        allow_large_seeds : bool
+    ) -> Self
+    {
+        match (limit_check, allow_large_seeds) {
+            (true, _) => SeedLimits::Solana,
+            (false, false) => SeedLimits::Total,
+            (false, true) => SeedLimits::None
         }
     }
-
-    eprintln!("Invalid seed: {}", s);
-    std::process::exit(-1);
 }
 
-fn private_key_bytes_array_to_pubkey(bytes : &str) -> Result<Pubkey, String>
+// Describes element i (counting from 0) of a list of count numbers of N bytes each, which failed to parse.  The
+// element itself is only shown if show_element, since it may be part of a secret key.
+fn invalid_element<const N: usize>(
+    element : &str,
+    show_element : bool,
+    i : usize,
+    count : usize,
+    e : &std::num::ParseIntError
+) -> String
 {
-    if bytes.starts_with("[") && bytes.ends_with("]") {
-        let bytes = &bytes[1..(bytes.len() - 1)];
-        Ok(Pubkey(
-            ed25519_dalek::Keypair::from_bytes(u8_list_to_vec(&bytes)?.as_slice())
-                .map_err(|e| e.to_string())?
-                .public
-                .to_bytes()
-        ))
+    let max = u128::MAX >> (128 - (8 * N));
+
+    let shown = if show_element { format!(" ({})", element) } else { "".to_string() };
+
+    match e.kind() {
+        IntErrorKind::Empty if count == 1 => "no values given".to_string(),
+        IntErrorKind::Empty if i == (count - 1) => format!("trailing comma after element {}", i),
+        IntErrorKind::Empty if i == 0 => "leading comma before the first element".to_string(),
+        IntErrorKind::Empty => format!("element {} is empty, between two commas", i + 1),
+        IntErrorKind::PosOverflow => {
+            format!("element {}{} is larger than {}, the largest u{} value", i + 1, shown, max, 8 * N)
+        },
+        _ if element.starts_with('-') => {
+            format!("element {}{} is negative, but u{} values may not be", i + 1, shown, 8 * N)
+        },
+        _ => format!("element {}{} is not a decimal number", i + 1, shown)
     }
-    else {
-        Err("Invalid key file contents".to_string())
+}
+
+// A list of numbers separated by commas, with optional spaces around each, where errors are at the offset into
+// numbers of the element that is invalid, and show the element if show_elements
+fn number_list_to_vec<T : FromStr<Err = std::num::ParseIntError>, const N: usize>(
+    numbers : &str,
+    to_le_bytes : fn(T) -> [u8; N],
+    show_elements : bool
+) -> Result<Vec<u8>, SeedError>
+{
+    let count = numbers.split(',').count();
+    let mut offset = 0;
+    let mut bytes = vec![];
+
+    for (i, element) in numbers.split(',').enumerate() {
+        let trimmed = element.trim_start_matches(' ');
+        let element_offset = offset + (element.len() - trimmed.len());
+        let trimmed = trimmed.trim_end_matches(' ');
+        match trimmed.parse::<T>() {
+            Ok(number) => bytes.extend(to_le_bytes(number)),
+            Err(e) => {
+                return Err(SeedError::new(element_offset, invalid_element::<N>(trimmed, show_elements, i, count, &e)))
+            },
+        }
+        offset += element.len() + 1;
     }
+
+    Ok(bytes)
 }
 
-fn public_key_bytes_array_to_pubkey(bytes : &str) -> Result<Pubkey, String>
+// An error in a seed, at a byte offset into it
+struct SeedError
 {
-    if bytes.starts_with("[") && bytes.ends_with("]") {
-        let bytes = &bytes[1..(bytes.len() - 1)];
-        Ok(Pubkey(
-            u8_list_to_vec(&bytes)?.try_into().map_err(|_| "Incorrect number of bytes in public key".to_string())?
-        ))
+    offset : usize,
+
+    message : String
+}
+
+impl SeedError
+{
+    fn new(
+        offset : usize,
+        message : String
+    ) -> Self
+    {
+        SeedError { offset, message }
     }
-    else {
-        Err("Invalid key file contents".to_string())
+
+    // Moves the error along by offset, for an error in a part of a seed that starts offset bytes into it
+    fn within(
+        self,
+        offset : usize
+    ) -> Self
+    {
+        SeedError { offset : self.offset + offset, message : self.message }
+    }
+
+    // Describes the error, followed by the seed with a caret under where in it the error is
+    fn describe(
+        &self,
+        seed : &str
+    ) -> String
+    {
+        format!("{}\n  {}\n  {}^", self.message, seed, " ".repeat(seed[..self.offset].chars().count()))
     }
 }
 
-fn bytes_are_curve_point(bytes : &[u8; 32]) -> bool
+fn parse_seed(seed : &str) -> Result<Vec<u8>, String>
 {
-    curve25519_dalek::edwards::CompressedEdwardsY::from_slice(bytes.as_ref()).decompress().is_some()
+    parse_seed_value(seed, Normalization::None, None).map_err(|e| format!("Invalid seed: {}", e.describe(seed)))
 }
 
-fn try_find_pda(
-    pubkey : &Pubkey,
-    seed : &[u8],
-    bump_seed : Option<u8>
-) -> Option<Pubkey>
+// A recursive descent parser of seeds, in which Sha256 and Pda seeds contain other seeds, and String seeds may contain
+// brackets in balanced pairs
+struct SeedParser<'a>
 {
-    let mut hasher = Sha256::new();
+    seed : &'a str,
+
+    // The offset into seed of the next character to parse
+    offset : usize,
+
+    // How the text of String seeds is normalized
+    normalization : Normalization,
+
+    // How the program ids of Pda seeds are read
+    program_id_format : Option<ProgramIdFormat>
+}
 
-    hasher.update(&seed);
-    if let Some(bump_seed) = bump_seed {
-        hasher.update(&[bump_seed]);
+impl<'a> SeedParser<'a>
+{
+    fn rest(&self) -> &'a str
+    {
+        &self.seed[self.offset..]
     }
-    hasher.update(&pubkey.0);
-    hasher.update(b"ProgramDerivedAddress");
 
-    let hash = <[u8; 32]>::try_from(hasher.finalize().as_slice()).unwrap();
+    fn skip_spaces(&mut self)
+    {
+        self.offset += self.rest().len() - self.rest().trim_start_matches(' ').len();
+    }
 
-    if bytes_are_curve_point(&hash) {
-        None
+    fn missing_close(&self) -> SeedError
+    {
+        SeedError::new(self.seed.len(), "missing closing ]".to_string())
     }
-    else {
-        Some(Pubkey(hash))
+
+    // Parses a seed, leaving the offset just after it
+    fn seed(&mut self) -> Result<Vec<u8>, SeedError>
+    {
+        let prefix = SEED_PREFIXES.into_iter().find(|prefix| self.rest().starts_with(prefix)).ok_or_else(|| {
+            SeedError::new(
+                self.offset,
+                "unknown seed type, expected one of u8[], u16[], u32[], u64[], String[], Pubkey[], Sha256[], or Pda[]"
+                    .to_string()
+            )
+        })?;
+        self.offset += prefix.len();
+
+        let bytes = match prefix {
+            U8_PREFIX => self.numbers(u8::to_le_bytes)?,
+            U16_PREFIX => self.numbers(u16::to_le_bytes)?,
+            U32_PREFIX => self.numbers(u32::to_le_bytes)?,
+            U64_PREFIX => self.numbers(u64::to_le_bytes)?,
+            STRING_PREFIX => {
+                let text = self.balanced(false)?;
+                self.normalization.apply(text).into_bytes()
+            },
+            PUBKEY_PREFIX => {
+                let start = self.offset;
+                read_address(self.until_close()?)
+                    .map_err(|e| SeedError::new(start, format!("invalid public key: {}", e)))?
+                    .0
+                    .to_vec()
+            },
+            SHA256_PREFIX => Sha256::digest(self.seed()?).to_vec(),
+            _ => self.pda()?
+        };
+
+        if self.rest().starts_with(']') {
+            self.offset += 1;
+            Ok(bytes)
+        }
+        else if self.rest().is_empty() {
+            Err(self.missing_close())
+        }
+        else {
+            Err(SeedError::new(self.offset, "expected ]".to_string()))
+        }
     }
-}
 
-fn find_pda(
-    program_id : &Pubkey,
-    seed : &[u8],
-    no_bump_seed : bool
-) -> Option<(Pubkey, u8)>
-{
-    if no_bump_seed {
-        return try_find_pda(&program_id, seed, None).map(|pk| (pk, 0));
+    fn numbers<T : FromStr<Err = std::num::ParseIntError>, const N: usize>(
+        &mut self,
+        to_le_bytes : fn(T) -> [u8; N]
+    ) -> Result<Vec<u8>, SeedError>
+    {
+        let start = self.offset;
+        number_list_to_vec(self.until_close()?, to_le_bytes, true).map_err(|e| e.within(start))
     }
-    else {
-        // Use the same algorithm as Solana's seed finding algorithm: start the bump seed at 255 and work backwards
-        let mut bump_seed = (std::u8::MAX) as i16;
 
-        while bump_seed >= 0 {
-            if let Some(pubkey) = try_find_pda(&program_id, seed, Some(bump_seed as u8)) {
-                return Some((pubkey, bump_seed as u8));
+    // Takes the text up to the next ], leaving the offset at it
+    fn until_close(&mut self) -> Result<&'a str, SeedError>
+    {
+        let rest = self.rest();
+        let len = rest.find(']').ok_or_else(|| self.missing_close())?;
+        self.offset += len;
+        Ok(&rest[..len])
+    }
+
+    // Takes the text up to the ] that closes the seed, or if at_comma, up to a comma, passing over [ and ] in balanced
+    // pairs, and leaving the offset at the ] or comma
+    fn balanced(
+        &mut self,
+        at_comma : bool
+    ) -> Result<&'a str, SeedError>
+    {
+        let rest = self.rest();
+        let mut depth = 0_usize;
+
+        for (i, c) in rest.char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' | ',' if (depth == 0) && ((c == ']') || at_comma) => {
+                    self.offset += i;
+                    return Ok(&rest[..i]);
+                },
+                ']' => depth -= 1,
+                _ => ()
             }
-            bump_seed -= 1;
         }
+
+        Err(self.missing_close())
     }
 
-    None
-}
+    // Parses the program id and seeds of a Pda seed, separated by commas, and derives the PDA of them with the
+    // canonical bump seed
+    fn pda(&mut self) -> Result<Vec<u8>, SeedError>
+    {
+        self.skip_spaces();
+        let start = self.offset;
+        let program_id = read_program_id_as(self.balanced(true)?.trim_end_matches(' '), self.program_id_format)
+            .map_err(|e| SeedError::new(start, format!("invalid program id: {}", e)))?;
 
-fn print_pubkey_bytes(b : &[u8; 32])
-{
-    print!("[");
-    let mut need_comma = false;
-    b.iter().for_each(|b| {
-        if need_comma {
-            print!(",{}", b);
+        let mut seeds = vec![];
+        while self.rest().starts_with(',') {
+            self.offset += 1;
+            self.skip_spaces();
+            seeds.push(self.seed()?);
+            self.skip_spaces();
         }
-        else {
-            print!("{}", b);
-            need_comma = true;
+
+        if seeds.is_empty() {
+            return Err(SeedError::new(self.offset, "expected a comma and then the seeds of the PDA".to_string()));
         }
-    });
-    print!("]");
+
+        check_seed_limits(&seeds, false).map_err(|e| SeedError::new(start, e))?;
+
+        find_pda(&program_id, &seeds)
+            .map(|(pda, _)| pda.0.to_vec())
+            .ok_or_else(|| SeedError::new(start, "there is no PDA for these seeds".to_string()))
+    }
 }
 
-fn main()
+// Parses a seed, which must take up all of seed, returning an error that says what is wrong with it and where, but not
+// which seed it is, so that callers can name it in whatever way suits them
+fn parse_seed_value(
+    seed : &str,
+    normalization : Normalization,
+    program_id_format : Option<ProgramIdFormat>
+) -> Result<Vec<u8>, SeedError>
 {
-    let mut no_bump_seed = false;
-    let mut bytes = false;
-    let mut seeds = Vec::<String>::new();
-    let mut pubkey_only = false;
+    let mut parser = SeedParser { seed, offset : 0, normalization, program_id_format };
 
-    seeds.extend(std::env::args().skip(1));
+    let bytes = parser.seed()?;
 
-    while seeds.len() > 0 {
-        match seeds[0].as_str() {
-            "--help" => {
-                println!("{}", usage_string());
-                std::process::exit(0);
-            },
+    if parser.offset < seed.len() {
+        return Err(SeedError::new(parser.offset, "unexpected text after the end of the seed".to_string()));
+    }
 
-            "-pubkey" => {
-                pubkey_only = true;
-                seeds.remove(0);
-            },
+    Ok(bytes)
+}
 
-            "--no-bump-seed" => {
-                no_bump_seed = true;
-                seeds.remove(0);
-            },
+// Parses the seed at the given position (counting from 1) among the seeds given, with errors naming both
+fn parse_seed_at(
+    position : usize,
+    seed : &str,
+    normalization : Normalization,
+    program_id_format : Option<ProgramIdFormat>
+) -> Result<Vec<u8>, String>
+{
+    parse_seed_value(seed, normalization, program_id_format)
+        .map_err(|e| format!("Invalid seed {}: {}", position, e.describe(seed)))
+}
 
-            "--bytes" => {
-                bytes = true;
-                seeds.remove(0);
-            },
+// A seed given to --matrix, which stands for one or more alternative seeds
+struct MatrixSeed
+{
+    // The type prefix of the alternatives, e.g. "u64[", or the whole seed if there are no alternatives
+    prefix : String,
+
+    values : Vec<MatrixValue>
+}
+
+enum MatrixValue
+{
+    Single(String),
+
+    // An inclusive range of numbers
+    Range(u64, u64)
+}
+
+impl MatrixSeed
+{
+    // TYPE{V1,V2,...} stands for the seeds TYPE[V1], TYPE[V2], ..., where for the u8, u16, u32, and u64 types each
+    // value may also be a range START..END or START..=END, and for the Pubkey type @FILE, for each address listed in
+    // FILE; any other seed stands for just itself
+    fn new(seed : &str) -> Result<Self, String>
+    {
+        let (prefix, values) = match seed.strip_suffix('}').and_then(|s| s.split_once('{')) {
+            Some((prefix, values)) => (format!("{}[", prefix), values),
+            None => return Ok(MatrixSeed { prefix : seed.to_string(), values : vec![] })
+        };
 
-            _ => break
+        if !is_seed(&prefix) {
+            return Err(format!("Invalid seed: {}", seed));
         }
-    }
 
-    if seeds.len() < 1 {
-        eprintln!("{}", usage_string());
-        std::process::exit(-1);
+        let numeric = [U8_PREFIX, U16_PREFIX, U32_PREFIX, U64_PREFIX].contains(&prefix.as_str());
+
+        let mut matrix_values = vec![];
+
+        for value in values.split(',').map(|value| value.trim()) {
+            if numeric && value.contains("..") {
+                let (first, last) = parse_range(value).map_err(|e| format!("Invalid seed {}: {}", seed, e))?;
+                matrix_values.push(MatrixValue::Range(first, last));
+            }
+            // @NAME is an alias if there is one by that name, which the Pubkey seed reads itself
+            else if let Some(path) =
+                value.strip_prefix('@').filter(|name| (prefix == PUBKEY_PREFIX) && !alias::is_alias(name))
+            {
+                let addresses = read_address_file(path).map_err(|e| format!("Invalid seed {}: {}", seed, e))?;
+                matrix_values.extend(addresses.into_iter().map(MatrixValue::Single));
+            }
+            else {
+                matrix_values.push(MatrixValue::Single(value.to_string()));
+            }
+        }
+
+        Ok(MatrixSeed { prefix, values : matrix_values })
     }
 
-    let program_id = seeds.remove(0);
-    let program_id : Pubkey = std::fs::read_to_string(&program_id)
-        .map_err(|e| e.to_string())
-        .and_then(|pk_bytes| private_key_bytes_array_to_pubkey(&pk_bytes))
-        .or_else(|_| Pubkey::from_str(&program_id))
-        .or_else(|_| public_key_bytes_array_to_pubkey(&program_id))
-        .unwrap_or_else(|e| {
-            eprintln!("Invalid program id: {}", e);
-            std::process::exit(-1);
-        });
+    fn len(&self) -> u64
+    {
+        if self.values.is_empty() {
+            return 1;
+        }
+        self.values
+            .iter()
+            .map(|value| match value {
+                MatrixValue::Single(_) => 1,
+                MatrixValue::Range(first, last) => (last - first).saturating_add(1)
+            })
+            .fold(0_u64, |total, len| total.saturating_add(len))
+    }
 
-    if pubkey_only {
-        if bytes {
-            print_pubkey_bytes(&program_id.0);
-            println!("");
+    fn get(
+        &self,
+        mut index : u64
+    ) -> String
+    {
+        if self.values.is_empty() {
+            return self.prefix.clone();
         }
-        else {
-            println!("{}", program_id);
+        for value in &self.values {
+            match value {
+                MatrixValue::Single(value) => {
+                    if index == 0 {
+                        return format!("{}{}]", self.prefix, value);
+                    }
+                    index -= 1;
+                },
+                MatrixValue::Range(first, last) => {
+                    if index <= (last - first) {
+                        return format!("{}{}]", self.prefix, first + index);
+                    }
+                    index -= (last - first) + 1;
+                }
+            }
         }
-        return;
+        unreachable!()
     }
+}
 
-    if seeds.len() < 1 {
-        eprintln!("{}", usage_string());
-        std::process::exit(-1);
+// Parses START..END (END excluded) or START..=END (END included), returning the first and last values
+fn parse_range(s : &str) -> Result<(u64, u64), String>
+{
+    let (start, end, inclusive) = if let Some((start, end)) = s.split_once("..=") {
+        (start, end, true)
+    }
+    else if let Some((start, end)) = s.split_once("..") {
+        (start, end, false)
     }
+    else {
+        return Err("expected START..END or START..=END".to_string());
+    };
 
-    let seeds : Vec<u8> = seeds.iter().map(|seed| make_seed(seed)).flatten().collect();
+    let start = start.parse::<u64>().map_err(|e| format!("invalid start {}: {}", start, e))?;
+    let end = end.parse::<u64>().map_err(|e| format!("invalid end {}: {}", end, e))?;
 
-    if let Some((pda, bump_seed)) = find_pda(&program_id, seeds.as_slice(), no_bump_seed) {
-        if no_bump_seed {
-            if bytes {
-                print_pubkey_bytes(&pda.0);
-                println!("");
-            }
-            else {
-                println!("{}", pda);
+    let last = if inclusive { Some(end) } else { end.checked_sub(1) };
+
+    match last {
+        Some(last) if last >= start => Ok((start, last)),
+        _ => Err("empty range".to_string())
+    }
+}
+
+// Parses each of seeds and checks them against limits
+fn parse_seeds(
+    seeds : &[String],
+    no_bump_seed : bool,
+    limits : SeedLimits,
+    normalization : Normalization,
+    program_id_format : Option<ProgramIdFormat>
+) -> Result<Vec<Vec<u8>>, String>
+{
+    let seeds = seeds
+        .iter()
+        .enumerate()
+        .map(|(i, seed)| parse_seed_at(i + 1, seed, normalization, program_id_format))
+        .collect::<Result<Vec<Vec<u8>>, String>>()?;
+    match limits {
+        SeedLimits::Solana => check_seed_limits(&seeds, no_bump_seed)?,
+        SeedLimits::Total => {
+            let total = seeds.iter().map(|seed| seed.len()).sum::<usize>();
+            if total > MAX_TOTAL_SEED_LEN {
+                return Err(format!(
+                    "Seeds total {} bytes, more than the {} allowed even with --no-limit-check, since Solana allows \
+                     at most {}; give --allow-large-seeds to derive anyway",
+                    total,
+                    MAX_TOTAL_SEED_LEN,
+                    MAX_SEED_LEN * MAX_SEEDS
+                ));
             }
+        },
+        SeedLimits::None => ()
+    }
+    Ok(seeds)
+}
+
+// Checks seeds against the limits that Solana puts on the seeds of a PDA, where the bump seed, if there is one, counts
+// as one of them.  The runtime rejects PDAs derived from seeds outside of these limits with MaxSeedLengthExceeded.
+fn check_seed_limits(
+    seeds : &[Vec<u8>],
+    no_bump_seed : bool
+) -> Result<(), String>
+{
+    if let Some((i, seed)) = seeds.iter().enumerate().find(|(_, seed)| seed.len() > MAX_SEED_LEN) {
+        return Err(format!(
+            "Seed {} is {} bytes long, but seeds may be at most {} bytes",
+            i + 1,
+            seed.len(),
+            MAX_SEED_LEN
+        ));
+    }
+
+    if no_bump_seed && (seeds.len() > MAX_SEEDS) {
+        return Err(format!("{} seeds given, but there may be at most {}", seeds.len(), MAX_SEEDS));
+    }
+    if !no_bump_seed && (seeds.len() >= MAX_SEEDS) {
+        return Err(format!(
+            "{} seeds given, but there may be at most {} as the bump seed counts as one too",
+            seeds.len(),
+            MAX_SEEDS - 1
+        ));
+    }
+
+    Ok(())
+}
+
+// Describes how the PDA of seeds could be misleading, because it depends only on the seeds' bytes concatenated
+// together, and not on where one seed ends and the next begins.  parsed has the bytes of each of seeds.
+fn seed_warnings(
+    seeds : &[String],
+    parsed : &[Vec<u8>]
+) -> Vec<String>
+{
+    let mut warnings = vec![];
+
+    for (i, (seed, bytes)) in seeds.iter().zip(parsed).enumerate() {
+        if bytes.is_empty() {
+            warnings.push(format!(
+                "Seed {} ({}) is empty, so it has no effect on the PDA, but it still counts towards the limit of {} \
+                 seeds",
+                i + 1,
+                seed,
+                MAX_SEEDS
+            ));
+        }
+        else if bytes.len() > MAX_SEED_LEN {
+            warnings.push(format!(
+                "Seed {} ({}) is {} bytes long, so Solana would reject it, though the same bytes split into seeds of \
+                 at most {} bytes give the same PDA",
+                i + 1,
+                seed,
+                bytes.len(),
+                MAX_SEED_LEN
+            ));
+        }
+    }
+
+    // Adjacent non-empty String seeds, which could be split anywhere else with the same result
+    for i in 1..seeds.len() {
+        let strings = seeds[(i - 1)..=i]
+            .iter()
+            .map(|seed| seed.strip_prefix(STRING_PREFIX).and_then(|s| s.strip_suffix(']')))
+            .collect::<Option<Vec<&str>>>();
+        let (first, second) = match strings.as_deref() {
+            Some([first, second]) if !first.is_empty() && !second.is_empty() => (*first, *second),
+            _ => continue
+        };
+
+        // Another split of the same string, moving a character across the boundary, or joining the two seeds if each
+        // is a single character
+        let string = |s : &str| format!("{}{}]", STRING_PREFIX, s);
+        let other = match (first.char_indices().last(), second.char_indices().nth(1)) {
+            (Some((last, _)), _) if last > 0 => {
+                format!("{} {}", string(&first[..last]), string(&format!("{}{}", &first[last..], second)))
+            },
+            (_, Some((next, _))) => {
+                format!("{} {}", string(&format!("{}{}", first, &second[..next])), string(&second[next..]))
+            },
+            _ => string(&format!("{}{}", first, second))
+        };
+
+        warnings.push(format!(
+            "Seeds {} and {} are both strings, and only their concatenation \"{}{}\" affects the PDA, so other seeds \
+             such as {} give the same PDA",
+            i,
+            i + 1,
+            first,
+            second,
+            other
+        ));
+    }
+
+    warnings
+}
+
+fn make_seed(
+    position : usize,
+    seed : &str
+) -> Vec<u8>
+{
+    parse_seed_at(position, seed, Normalization::None, None).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    })
+}
+
+// Returns true if s looks like a seed specification rather than a program id
+fn is_seed(s : &str) -> bool
+{
+    SEED_PREFIXES.iter().any(|prefix| s.starts_with(prefix))
+}
+
+// Splits a list of seed specifications separated by whitespace that is not within brackets (so that String seeds may
+// contain spaces)
+fn split_seeds(seeds : &str) -> Vec<String>
+{
+    let mut split = vec![];
+    let mut current = String::new();
+    let mut depth = 0_usize;
+
+    for c in seeds.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            _ => ()
         }
-        else if bytes {
-            print_pubkey_bytes(&pda.0);
-            println!(".{}", bump_seed);
+        if c.is_whitespace() && (depth == 0) {
+            if !current.is_empty() {
+                split.push(std::mem::take(&mut current));
+            }
         }
         else {
-            println!("{}.{}", pda, bump_seed);
+            current.push(c);
         }
     }
-    else {
-        eprintln!("Cannot find PDA, consider allowing bump seed");
-        std::process::exit(1)
+
+    if !current.is_empty() {
+        split.push(current);
+    }
+
+    split
+}
+
+// Describes why input that may be a secret key failed to decode as base58, giving only the position of an invalid
+// character rather than the character itself
+fn redacted_base58_error(e : bs58::decode::Error) -> String
+{
+    match e {
+        bs58::decode::Error::InvalidCharacter { index, .. } | bs58::decode::Error::NonAsciiCharacter { index } => {
+            format!("the character at position {} is not base58", index + 1)
+        },
+        e => e.to_string()
     }
 }
 
-impl std::str::FromStr for Pubkey
+// Describes why input that may be a secret key failed to decode as base64, giving only the position of an invalid
+// character rather than the character itself
+fn redacted_base64_error(e : base64::DecodeError) -> String
 {
-    type Err = String;
+    match e {
+        base64::DecodeError::InvalidByte(offset, _) => {
+            format!("the character at position {} is not base64", offset + 1)
+        },
+        base64::DecodeError::InvalidLength => "its length is not that of base64".to_string(),
+        base64::DecodeError::InvalidLastSymbol(offset, _) => {
+            format!("the character at position {} cannot end base64", offset + 1)
+        }
+    }
+}
 
-    fn from_str(s : &str) -> Result<Self, Self::Err>
-    {
-        let mut address = [0_u8; 32];
+// Identifies input that failed to parse, without showing it since it may be a secret key: its length and SHA-256 hash,
+// which can be compared with those of the file that was meant, and shared without revealing anything
+fn fingerprint(contents : &str) -> String
+{
+    let hash = Sha256::digest(contents.as_bytes());
+
+    format!("{} bytes, SHA-256 {}", contents.len(), hash.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+// Reads the bytes of a key file, which are either a JSON array or, as some wallets export secret keys, base64.  The
+// bytes may be a secret key, so they are zeroized when dropped, and are never copied anywhere that isn't: the array is
+// parsed in place rather than into JSON values, and the bytes are collected without reallocating.
+fn key_file_bytes(contents : &str) -> Result<Zeroizing<Vec<u8>>, String>
+{
+    let contents = contents.trim();
+
+    if !contents.starts_with('[') {
+        // Only keypairs are accepted in base64, since a base58 address may also happen to be valid base64
+        let base64 = Zeroizing::new(contents.split_whitespace().collect::<String>());
+        return match base64::decode(&*base64).or_else(|_| base64::decode_config(&*base64, base64::URL_SAFE)) {
+            Ok(bytes) if bytes.len() == 64 => Ok(Zeroizing::new(bytes)),
+            Ok(bytes) => {
+                Err(format!("is base64 of {} bytes rather than the 64 of a keypair", Zeroizing::new(bytes).len()))
+            },
+            Err(e) => Err(format!("neither a JSON array of bytes nor base64 ({})", redacted_base64_error(e)))
+        };
+    }
 
-        let v = bs58::decode(s).into_vec().map_err(|e| format!("{}", e))?;
+    let elements =
+        contents.strip_prefix('[').and_then(|elements| elements.strip_suffix(']')).map(|elements| elements.trim());
+    let elements = match elements {
+        Some("") => return Ok(Zeroizing::new(vec![])),
+        Some(elements) => elements.split(','),
+        None => return Err("not a JSON array of bytes".to_string())
+    };
 
-        if v.len() == 32 {
-            address.copy_from_slice(v.as_slice());
-            Ok(Pubkey(address))
+    let mut bytes = Zeroizing::new(Vec::with_capacity(elements.clone().count()));
+    for (i, element) in elements.map(|element| element.trim()).enumerate() {
+        match element.parse::<u8>() {
+            Ok(b) if element.bytes().all(|c| c.is_ascii_digit()) => bytes.push(b),
+            _ => return Err(format!("element {} is not a number in the range [0, 255]", i + 1))
         }
-        else {
-            Err(format!("Invalid address {}", s))
+    }
+
+    Ok(bytes)
+}
+
+// Reads the public key in the contents of a key file, which are the 64 bytes of a keypair, as the Solana CLI writes
+// them, or the 32 bytes of a public key
+fn key_file_to_pubkey(contents : &str) -> Result<Pubkey, String>
+{
+    let bytes = key_file_bytes(contents)?;
+
+    match bytes.len() {
+        64 => {
+            // The second half of a keypair is the public key of the first, unless the file has been damaged
+            let secret = ed25519_dalek::SecretKey::from_bytes(&bytes[..32]).map_err(|e| e.to_string())?;
+            let public = ed25519_dalek::PublicKey::from(&secret).to_bytes();
+            if public[..] != bytes[32..] {
+                return Err("has a public key (its last 32 bytes) that is not that of its secret key (its first 32 \
+                            bytes)"
+                    .to_string());
+            }
+            Ok(Pubkey(public))
+        },
+        32 => Ok(Pubkey(bytes[..].try_into().unwrap())),
+        len => Err(format!("has {} bytes rather than the 64 of a keypair or the 32 of a public key", len))
+    }
+}
+
+fn public_key_bytes_array_to_pubkey(bytes : &str) -> Result<Pubkey, String>
+{
+    if bytes.starts_with("[") && bytes.ends_with("]") {
+        let bytes = u8_list_to_vec(&bytes[1..(bytes.len() - 1)])?;
+        match bytes.len() {
+            32 => Ok(Pubkey(bytes.try_into().unwrap())),
+            64 => Err("has 64 bytes, which is a keypair rather than a public key; give it as a key file".to_string()),
+            len => Err(format!("has {} bytes rather than the 32 of a public key", len))
         }
     }
+    else {
+        Err("not enclosed in [ and ]".to_string())
+    }
 }
 
-impl std::fmt::Display for Pubkey
+// The JSON byte array format of keypair files used by the Solana CLI
+fn keypair_json(keypair : &[u8; 64]) -> Zeroizing<String>
 {
-    fn fmt(
-        &self,
-        f : &mut std::fmt::Formatter
-    ) -> std::fmt::Result
-    {
-        write!(f, "{}", bs58::encode(self.0).into_string())
+    Zeroizing::new(format!("[{}]", keypair.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(",")))
+}
+
+// Writes a keypair in the JSON byte array format used by the Solana CLI
+fn write_keypair_file(
+    path : &str,
+    keypair : &[u8; 64],
+    force : bool
+) -> Result<(), String>
+{
+    write_secret_file(path, &keypair_json(keypair), force)
+}
+
+// Writes a file holding a secret key, which is only overwritten if force.  On Unix, the file is readable only by its
+// owner.
+fn write_secret_file(
+    path : &str,
+    contents : &str,
+    force : bool
+) -> Result<(), String>
+{
+    let mut options = std::fs::OpenOptions::new();
+
+    options.write(true);
+
+    if force {
+        options.create(true).truncate(true);
+    }
+    else {
+        options.create_new(true);
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    std::io::Write::write_all(&mut options.open(path).map_err(|e| e.to_string())?, contents.as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+// Reads an address given in base58, or as @NAME, the address of an alias
+fn read_address(s : &str) -> Result<Pubkey, String>
+{
+    match s.strip_prefix('@') {
+        Some(name) => alias::lookup(name),
+        None => Pubkey::from_str(s)
+    }
+}
+
+// Reads an address given in base58, as an alias, or as a byte array, with the error for whichever of them it looks like
+fn read_pubkey(s : &str) -> Result<Pubkey, String>
+{
+    if s.starts_with('[') {
+        public_key_bytes_array_to_pubkey(s)
+    }
+    else {
+        read_address(s)
+    }
+}
+
+// Reads the public key in contents, read from the key file at path, warning if it looks like the wrong one
+fn key_file_pubkey(
+    path : &str,
+    contents : &str
+) -> Result<Pubkey, String>
+{
+    let pubkey = key_file_to_pubkey(contents).map_err(|e| format!("{}: {} ({})", path, e, fingerprint(contents)))?;
+    anchor::key_file_warnings(path, &pubkey).iter().for_each(|warning| eprintln!("Warning: {}", warning));
+    Ok(pubkey)
+}
+
+// Reads a program id given as an alias, a usb://ledger URL, a key file, a base58 address, or a byte array, in that
+// order.  If it is none of them, the error says why each failed, since which was intended isn't known.
+fn read_program_id(program_id : &str) -> Result<Pubkey, String>
+{
+    if let Some(name) = program_id.strip_prefix('@') {
+        return alias::lookup(name);
+    }
+
+    if program_id.starts_with("usb://") {
+        return ledger::pubkey(program_id);
+    }
+
+    let file_error = match std::fs::read_to_string(program_id).map(Zeroizing::new) {
+        Ok(pk_bytes) => match key_file_pubkey(program_id, &pk_bytes) {
+            Ok(pubkey) => return Ok(pubkey),
+            Err(e) => e
+        },
+        Err(e) => e.to_string()
+    };
+
+    let base58_error = match Pubkey::from_str(program_id) {
+        Ok(pubkey) => return Ok(pubkey),
+        Err(e) => e
+    };
+
+    let bytes_error = match public_key_bytes_array_to_pubkey(program_id) {
+        Ok(pubkey) => return Ok(pubkey),
+        Err(e) => e
+    };
+
+    Err(format!(
+        "as a key file: {}; as a base58 address: {}; as a byte array: {}",
+        file_error, base58_error, bytes_error
+    ))
+}
+
+// The one way to read a program id that --program-id-format allows, instead of trying each in turn
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ProgramIdFormat
+{
+    Base58,
+
+    PubkeyBytes,
+
+    KeypairFile,
+
+    // A file containing a public key in base58 or as a byte array
+    PubkeyFile
+}
+
+impl ProgramIdFormat
+{
+    fn from_str(s : &str) -> Option<Self>
+    {
+        match s {
+            "base58" => Some(ProgramIdFormat::Base58),
+            "pubkey-bytes" => Some(ProgramIdFormat::PubkeyBytes),
+            "keypair-file" => Some(ProgramIdFormat::KeypairFile),
+            "pubkey-file" => Some(ProgramIdFormat::PubkeyFile),
+            _ => None
+        }
+    }
+}
+
+// Reads a program id in format, or as read_program_id does if it is None
+fn read_program_id_as(
+    program_id : &str,
+    format : Option<ProgramIdFormat>
+) -> Result<Pubkey, String>
+{
+    let read_file =
+        || std::fs::read_to_string(program_id).map(Zeroizing::new).map_err(|e| format!("{}: {}", program_id, e));
+
+    match format {
+        None => read_program_id(program_id),
+        Some(ProgramIdFormat::Base58) => read_address(program_id),
+        Some(ProgramIdFormat::PubkeyBytes) => public_key_bytes_array_to_pubkey(program_id),
+        Some(ProgramIdFormat::KeypairFile) => key_file_pubkey(program_id, &read_file()?),
+        Some(ProgramIdFormat::PubkeyFile) => {
+            read_pubkey(read_file()?.trim()).map_err(|e| format!("{}: {}", program_id, e))
+        },
+    }
+}
+
+// The environment variable giving the program id to use when none is given
+const PROGRAM_ID_VARIABLE : &str = "SOLPDA_PROGRAM_ID";
+
+// Reads the program id given by SOLPDA_PROGRAM_ID, or if it is not set, by the config file's program_id, in format,
+// exiting if it is invalid
+fn env_program_id(
+    format : Option<ProgramIdFormat>,
+    config : &Config
+) -> Option<Pubkey>
+{
+    let (program_id, source) = match std::env::var(PROGRAM_ID_VARIABLE).ok().filter(|program_id| !program_id.is_empty())
+    {
+        Some(program_id) => (program_id, PROGRAM_ID_VARIABLE),
+        None => (config.program_id.clone()?, "the config file")
+    };
+
+    Some(read_program_id_as(&program_id, format).unwrap_or_else(|e| {
+        eprintln!("Invalid program id in {}: {}", source, e);
+        std::process::exit(-1);
+    }))
+}
+
+// Reads all of stdin, for the program id "-", which may be the contents of a key file
+fn read_stdin() -> Result<Zeroizing<String>, String>
+{
+    // Room for any key file, so that reading it doesn't leave secret bytes behind in a buffer that is outgrown
+    let mut contents = Zeroizing::new(String::with_capacity(4096));
+    std::io::stdin().read_to_string(&mut contents).map_err(|e| format!("failed to read stdin: {}", e))?;
+    Ok(contents)
+}
+
+// Reads a program id from the contents of stdin, as the program id "-" asks, in format, or if it is None, as either a
+// base58 address or the contents of a key file
+fn read_program_id_stdin(
+    stdin : &str,
+    format : Option<ProgramIdFormat>
+) -> Result<Pubkey, String>
+{
+    let contents = stdin.trim();
+
+    if contents.is_empty() {
+        return Err("stdin is empty".to_string());
+    }
+
+    match format {
+        None => redacted_pubkey_from_str(contents).or_else(|base58_error| {
+            key_file_to_pubkey(contents).map_err(|key_file_error| {
+                format!("as a base58 address: {}; as the contents of a key file: {}", base58_error, key_file_error)
+            })
+        }),
+        Some(ProgramIdFormat::Base58) => redacted_pubkey_from_str(contents),
+        Some(ProgramIdFormat::PubkeyBytes) => public_key_bytes_array_to_pubkey(contents),
+        Some(ProgramIdFormat::KeypairFile) => key_file_to_pubkey(contents),
+        Some(ProgramIdFormat::PubkeyFile) => read_pubkey(contents)
+    }
+    .map_err(|e| format!("from stdin: {} ({})", e, fingerprint(stdin)))
+}
+
+// Reads a base58 address from input that may instead be a secret key, such as stdin, so without the hints of
+// Pubkey::from_str that show what the input decodes to
+fn redacted_pubkey_from_str(s : &str) -> Result<Pubkey, String>
+{
+    match bs58::decode(s).into_vec().map_err(redacted_base58_error)?.len() {
+        32 | 64 => Pubkey::from_str(s),
+        len => Err(format!("decodes to {} bytes rather than 32", len))
+    }
+}
+
+// Returns true if contents are those of a key file holding a keypair, and so a secret key, rather than a public key
+fn holds_secret_key(contents : &str) -> bool
+{
+    key_file_bytes(contents).map(|bytes| bytes.len() == 64).unwrap_or(false)
+}
+
+// Returns true if the program id would be read, in format, from a key file holding a secret key
+fn is_keypair_file(
+    program_id : &str,
+    format : Option<ProgramIdFormat>
+) -> bool
+{
+    matches!(format, None | Some(ProgramIdFormat::KeypairFile)) &&
+        std::fs::read_to_string(program_id)
+            .map(|contents| holds_secret_key(&Zeroizing::new(contents)))
+            .unwrap_or(false)
+}
+
+// The kind of key that --expect requires each program id to be
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExpectedKey
+{
+    Pubkey,
+
+    Keypair
+}
+
+impl ExpectedKey
+{
+    fn from_str(s : &str) -> Option<Self>
+    {
+        match s {
+            "pubkey" => Some(ExpectedKey::Pubkey),
+            "keypair" => Some(ExpectedKey::Keypair),
+            _ => None
+        }
+    }
+}
+
+// Fails if the program id from source isn't the kind of key that --expect requires, where is_keypair says whether it
+// was read from a key file holding a keypair
+fn check_expected_key(
+    source : &str,
+    is_keypair : bool,
+    expect : Option<ExpectedKey>
+)
+{
+    match (expect, is_keypair) {
+        (Some(ExpectedKey::Pubkey), true) => {
+            eprintln!("The program id {} is a keypair, but --expect pubkey was given", source);
+            std::process::exit(-1);
+        },
+        (Some(ExpectedKey::Keypair), false) => {
+            eprintln!("The program id {} is only a public key, but --expect keypair was given", source);
+            std::process::exit(-1);
+        },
+        _ => ()
+    }
+}
+
+// Deriving PDAs needs only the program's public key, so a secret key given as the program id is most likely the wrong
+// file, and one that may now be exposed in shell history or logs.  This warns about it, or with --strict, fails,
+// unless --allow-keypair is given.
+fn check_keypair_program_id(
+    source : &str,
+    strict : bool,
+    allow_keypair : bool
+)
+{
+    if allow_keypair {
+        return;
+    }
+
+    if strict {
+        eprintln!(
+            "The program id {} holds a secret key, but only the program's address is needed; give --allow-keypair to \
+             use it anyway",
+            source
+        );
+        std::process::exit(-1);
+    }
+
+    eprintln!(
+        "Warning: the program id {} holds a SECRET KEY, but only the program's address is needed.  If this is not the \
+         file you meant to give, check whether the secret key has been exposed.  Give the address instead, or \
+         --allow-keypair to silence this warning.",
+        source
+    );
+}
+
+// The SHA-256 state after absorbing the seeds of a PDA.  Every bump seed is tried by finishing a copy of this state,
+// so that the seeds are hashed only once no matter how many bump seeds are tried; likewise grind absorbs its fixed seeds
+// once and then only the varying seed for each attempt.
+#[derive(Clone)]
+struct SeedHash(Sha256);
+
+impl SeedHash
+{
+    fn new(seed : &[u8]) -> Self
+    {
+        SeedHash(Sha256::new_with_prefix(seed))
+    }
+
+    // Absorbs more seed bytes
+    fn update(
+        &mut self,
+        seed : &[u8]
+    )
+    {
+        self.0.update(seed);
+    }
+
+    // Computes the hash that is the PDA, if it is not on the ed25519 curve
+    fn pda_hash(
+        &self,
+        program_id : &Pubkey,
+        bump_seed : Option<u8>
+    ) -> [u8; 32]
+    {
+        let mut hasher = self.0.clone();
+
+        if let Some(bump_seed) = bump_seed {
+            hasher.update([bump_seed]);
+        }
+        hasher.update(program_id.0);
+        hasher.update(b"ProgramDerivedAddress");
+
+        hasher.finalize().into()
+    }
+
+    fn try_find_pda(
+        &self,
+        program_id : &Pubkey,
+        bump_seed : Option<u8>
+    ) -> Option<Pubkey>
+    {
+        let hash = self.pda_hash(program_id, bump_seed);
+
+        if curve::is_curve_point(&hash) {
+            None
+        }
+        else {
+            Some(Pubkey(hash))
+        }
+    }
+
+    fn find_pda(
+        &self,
+        program_id : &Pubkey
+    ) -> Option<(Pubkey, u8)>
+    {
+        // Use the same algorithm as Solana's seed finding algorithm: start the bump seed at 255 and work backwards
+        let mut bump_seed = u8::MAX as i16;
+
+        while bump_seed >= 0 {
+            if let Some(pubkey) = self.try_find_pda(program_id, Some(bump_seed as u8)) {
+                return Some((pubkey, bump_seed as u8));
+            }
+            bump_seed -= 1;
+        }
+
+        None
+    }
+}
+
+// Derives the PDA with the canonical bump seed, as find_program_address does
+fn find_pda(
+    program_id : &Pubkey,
+    seeds : &[Vec<u8>]
+) -> Option<(Pubkey, u8)>
+{
+    let mut hash = SeedHash::new(&[]);
+    seeds.iter().for_each(|seed| hash.update(seed));
+    hash.find_pda(program_id)
+}
+
+// Derives the PDA with a specific bump seed, as create_program_address does, returning None if it is on the curve
+fn create_pda(
+    program_id : &Pubkey,
+    seeds : &[Vec<u8>],
+    bump_seed : u8
+) -> Option<Pubkey>
+{
+    let mut hash = SeedHash::new(&[]);
+    seeds.iter().for_each(|seed| hash.update(seed));
+    hash.try_find_pda(program_id, Some(bump_seed))
+}
+
+// A derived PDA, and the bump seed that was appended to its seeds, unless it was derived without one
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Derived
+{
+    pda : Pubkey,
+
+    bump_seed : Option<u8>
+}
+
+// Derives the PDA with the canonical bump seed, or if no_bump_seed, of the seeds alone
+fn derive_pda(
+    program_id : &Pubkey,
+    seeds : &[Vec<u8>],
+    no_bump_seed : bool
+) -> Option<Derived>
+{
+    if no_bump_seed {
+        let mut hash = SeedHash::new(&[]);
+        seeds.iter().for_each(|seed| hash.update(seed));
+        hash.try_find_pda(program_id, None).map(|pda| Derived { pda, bump_seed : None })
+    }
+    else {
+        find_pda(program_id, seeds).map(|(pda, bump_seed)| Derived { pda, bump_seed : Some(bump_seed) })
+    }
+}
+
+// How the main command derives PDAs and outputs them
+#[derive(Clone, Copy)]
+struct DeriveOptions
+{
+    no_bump_seed : bool,
+
+    // A specific bump seed to derive with, as create_program_address does, instead of searching for one
+    bump_seed : Option<u8>,
+
+    // The limits that seeds are checked against
+    limits : SeedLimits,
+
+    // Whether each derivation is checked against the Solana SDK's
+    cross_check : bool,
+
+    // Whether to warn about seeds that other seeds could stand in for
+    diagnose : bool,
+
+    normalization : Normalization,
+
+    program_id_format : Option<ProgramIdFormat>,
+
+    bytes : bool
+}
+
+impl DeriveOptions
+{
+    // Parses seeds and derives their PDA, if there is one
+    fn derive(
+        &self,
+        program_id : &Pubkey,
+        seeds : &[String]
+    ) -> Result<Option<Derived>, String>
+    {
+        normalize::string_seed_warnings(seeds, self.normalization)
+            .iter()
+            .for_each(|warning| eprintln!("Warning: {}", warning));
+        let parsed = parse_seeds(seeds, self.no_bump_seed, self.limits, self.normalization, self.program_id_format)?;
+        if self.diagnose {
+            seed_warnings(seeds, &parsed).iter().for_each(|warning| eprintln!("Warning: {}", warning));
+        }
+        let seeds = parsed;
+        let result = match self.bump_seed {
+            Some(bump_seed) => {
+                create_pda(program_id, &seeds, bump_seed).map(|pda| Derived { pda, bump_seed : Some(bump_seed) })
+            },
+            None => derive_pda(program_id, &seeds, self.no_bump_seed)
+        };
+        if self.cross_check {
+            #[cfg(feature = "parity")]
+            parity::cross_check(program_id, &seeds, self.no_bump_seed, self.bump_seed, result);
+        }
+        Ok(result)
+    }
+
+    // Explains why derive found no PDA
+    fn no_pda_error(&self) -> String
+    {
+        no_pda_error(self.no_bump_seed, self.bump_seed)
+    }
+
+    // The exit status for when derive found no PDA
+    fn no_pda_status(&self) -> i32
+    {
+        if self.no_bump_seed {
+            NO_BUMP_SEED_STATUS
+        }
+        else {
+            1
+        }
+    }
+}
+
+// The exit status for when seeds without a bump seed give an address on the curve, which is distinct from the status
+// for failing to find a PDA with a bump seed since it is expected for about half of all seeds
+const NO_BUMP_SEED_STATUS : i32 = 3;
+
+// Explains why a PDA could not be derived: without a bump seed if no_bump_seed, else with bump_seed, or by searching for
+// a bump seed if it is None
+fn no_pda_error(
+    no_bump_seed : bool,
+    bump_seed : Option<u8>
+) -> String
+{
+    match bump_seed {
+        _ if no_bump_seed => {
+            "The seeds without a bump seed give an address on the ed25519 curve, which cannot be a PDA".to_string()
+        },
+        Some(bump_seed) => {
+            format!("Bump seed {} gives an address on the ed25519 curve, which cannot be a PDA", bump_seed)
+        },
+        None => "Every bump seed gives an address on the ed25519 curve, so there is no PDA".to_string()
+    }
+}
+
+// Reads the program ids listed one per line in path, in format, skipping blank lines and lines starting with #
+fn read_programs_file(
+    path : &str,
+    format : Option<ProgramIdFormat>
+) -> Vec<Pubkey>
+{
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(-1);
+    });
+
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            read_program_id_as(line, format).unwrap_or_else(|e| {
+                eprintln!("{} line {}: Invalid program id: {}", path, i + 1, e);
+                std::process::exit(-1);
+            })
+        })
+        .collect()
+}
+
+// Reads a file listing one address per line, ignoring blank lines and lines starting with #, as for --programs-file
+fn read_address_file(path : &str) -> Result<Vec<String>, String>
+{
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    let addresses = contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            read_address(line)
+                .map(|_| line.to_string())
+                .map_err(|e| format!("{} line {}: invalid address: {}", path, i + 1, e))
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+
+    if addresses.is_empty() {
+        return Err(format!("{} lists no addresses", path));
+    }
+
+    Ok(addresses)
+}
+
+fn print_pubkey_bytes(b : &[u8; 32])
+{
+    print!("[");
+    let mut need_comma = false;
+    b.iter().for_each(|b| {
+        if need_comma {
+            print!(",{}", b);
+        }
+        else {
+            print!("{}", b);
+            need_comma = true;
+        }
+    });
+    print!("]");
+}
+
+fn print_pda(
+    derived : &Derived,
+    bytes : bool
+)
+{
+    print_pda_terminated(derived, bytes, '\n');
+}
+
+// Prints a PDA as print_pda does, but followed by terminator instead of a newline
+fn print_pda_terminated(
+    derived : &Derived,
+    bytes : bool,
+    terminator : char
+)
+{
+    if bytes {
+        print_pubkey_bytes(&derived.pda.0);
+    }
+    else {
+        print!("{}", derived.pda);
+    }
+    if let Some(bump_seed) = derived.bump_seed {
+        print!(".{}", bump_seed);
+    }
+    print!("{}", terminator);
+}
+
+// Derives a PDA for each line of stdin, which is a list of seeds, optionally preceded by a program id (which is
+// required unless program_id is given), using jobs threads and outputting the PDAs in the order of the lines unless
+// unordered.  If nul, lines and the PDAs output are terminated by NUL rather than newline, so that seeds may contain
+// newlines.  Returns the exit status: -1 if any line was invalid, else that of DeriveOptions::no_pda_status if any PDA
+// could not be found, else 0.
+fn derive_stdin(
+    program_id : Option<Pubkey>,
+    options : DeriveOptions,
+    jobs : usize,
+    unordered : bool,
+    nul : bool
+) -> i32
+{
+    let mut status = 0;
+
+    let (terminator, line_name) = if nul { ('\0', "Record") } else { ('\n', "Line") };
+
+    let lines = std::io::BufReader::new(std::io::stdin()).split(terminator as u8).map(|line| {
+        line.map_err(|e| e.to_string())
+            .and_then(|line| String::from_utf8(line).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to read stdin: {}", e);
+                std::process::exit(-1);
+            })
+    });
+
+    parallel::map(
+        lines.enumerate(),
+        jobs,
+        !unordered,
+        |(i, line)| {
+            let mut seeds = split_seeds(&line);
+            if seeds.is_empty() {
+                return (i, None);
+            }
+
+            let line_program_id = if is_seed(&seeds[0]) {
+                program_id.ok_or_else(|| "Missing program id".to_string())
+            }
+            else {
+                read_program_id_as(&seeds.remove(0), options.program_id_format)
+                    .map_err(|e| format!("Invalid program id: {}", e))
+            };
+
+            let result = line_program_id.and_then(|program_id| options.derive(&program_id, &seeds));
+
+            (i, Some(result))
+        },
+        |(i, result)| {
+            match result {
+                None => (),
+                Some(Ok(Some(derived))) => print_pda_terminated(&derived, options.bytes, terminator),
+                Some(Ok(None)) => {
+                    eprintln!("{} {}: {}", line_name, i + 1, options.no_pda_error());
+                    if status == 0 {
+                        status = options.no_pda_status();
+                    }
+                },
+                Some(Err(e)) => {
+                    eprintln!("{} {}: {}", line_name, i + 1, e);
+                    status = -1;
+                }
+            }
+            true
+        }
+    );
+
+    status
+}
+
+// Derives a PDA for each program id and every combination of the alternatives of seeds, varying the last seed fastest,
+// and outputs each labeled with the program id if there is more than one and the seeds that have alternatives.
+// Updates status in the same way as derive_stdin determines its exit status.
+fn derive_matrix(
+    program_ids : &[Pubkey],
+    seeds : &[String],
+    options : DeriveOptions,
+    status : &mut i32
+)
+{
+    let seeds =
+        seeds.iter().map(|seed| MatrixSeed::new(seed)).collect::<Result<Vec<MatrixSeed>, String>>().unwrap_or_else(
+            |e| {
+                eprintln!("{}", e);
+                std::process::exit(-1);
+            }
+        );
+
+    for program_id in program_ids {
+        let program_label = if program_ids.len() > 1 { Some(program_id.to_string()) } else { None };
+        derive_matrix_program(program_id, program_label, &seeds, options, status);
+    }
+}
+
+fn derive_matrix_program(
+    program_id : &Pubkey,
+    program_label : Option<String>,
+    seeds : &[MatrixSeed],
+    options : DeriveOptions,
+    status : &mut i32
+)
+{
+    let mut indexes = vec![0_u64; seeds.len()];
+
+    loop {
+        let current = seeds.iter().zip(&indexes).map(|(seed, index)| seed.get(*index)).collect::<Vec<String>>();
+
+        let label = program_label
+            .iter()
+            .chain(seeds.iter().zip(&current).filter(|(seed, _)| !seed.values.is_empty()).map(|(_, current)| current))
+            .map(|s| s.as_str())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let error_prefix = if label.is_empty() { String::new() } else { format!("{}: ", label) };
+
+        match options.derive(program_id, &current) {
+            Ok(Some(derived)) => {
+                if !label.is_empty() {
+                    print!("{} ", label);
+                }
+                print_pda(&derived, options.bytes);
+            },
+            Ok(None) => {
+                eprintln!("{}{}", error_prefix, options.no_pda_error());
+                if *status == 0 {
+                    *status = options.no_pda_status();
+                }
+            },
+            Err(e) => {
+                eprintln!("{}{}", error_prefix, e);
+                *status = -1;
+            }
+        }
+
+        // Advance to the next combination, like an odometer
+        let mut i = seeds.len();
+        loop {
+            if i == 0 {
+                return;
+            }
+            i -= 1;
+            indexes[i] += 1;
+            if indexes[i] < seeds[i].len() {
+                break;
+            }
+            indexes[i] = 0;
+        }
+    }
+}
+
+fn oncurve_command() -> Command
+{
+    Command::new("oncurve")
+        .about("Reports whether an address is on the ed25519 curve, and so cannot be a PDA")
+        .arg(Arg::new("address").value_name("ADDRESS").required(true).help("A base58 address or array of u8 bytes"))
+}
+
+fn oncurve(matches : &ArgMatches)
+{
+    let address = read_pubkey(matches.get_one::<String>("address").unwrap()).unwrap_or_else(|e| {
+        eprintln!("Invalid address: {}", e);
+        std::process::exit(-1);
+    });
+
+    if curve::is_curve_point(&address.0) {
+        println!("{} is on the ed25519 curve, so it may be the public key of a keypair, but cannot be a PDA", address);
+    }
+    else {
+        println!("{} is off the ed25519 curve, so it may be a PDA, but has no private key", address);
+        std::process::exit(1);
+    }
+}
+
+fn tx_verify_command() -> Command
+{
+    Command::new("tx-verify")
+        .about("Checks how a transaction uses the PDA of a program id and seeds")
+        .arg(
+            Arg::new("bump")
+                .long("bump")
+                .value_name("BUMP")
+                .value_parser(clap::value_parser!(u8))
+                .help("Derive the PDA with this bump seed rather than searching for it")
+        )
+        .arg(
+            Arg::new("require-canonical")
+                .long("require-canonical")
+                .action(ArgAction::SetTrue)
+                .requires("bump")
+                .help("Fail with exit status 2 if the --bump seed is not the canonical one")
+        )
+        .arg(
+            Arg::new("tx")
+                .value_name("BASE64_TX")
+                .required(true)
+                .help("A Base64-encoded serialized transaction or message, legacy or v0")
+        )
+        .arg(Arg::new("program_id").value_name("PROGRAM_ID").required(true).help("The program id"))
+        .arg(Arg::new("seeds").value_name("SEED").required(true).num_args(1..).help("The seeds of the PDA"))
+}
+
+fn tx_verify(matches : &ArgMatches)
+{
+    let bump = matches.get_one::<u8>("bump").copied();
+    let require_canonical = matches.get_flag("require-canonical");
+    let args = matches.get_many::<String>("seeds").unwrap().cloned().collect::<Vec<String>>();
+
+    let tx = matches.get_one::<String>("tx").unwrap();
+    let tx = base64::decode(tx.trim()).unwrap_or_else(|e| {
+        eprintln!("Invalid Base64 transaction: {}", e);
+        std::process::exit(-1);
+    });
+    let message = Message::from_transaction_or_message(&tx).unwrap_or_else(|e| {
+        eprintln!("Invalid transaction: {}", e);
+        std::process::exit(-1);
+    });
+
+    let program_id = read_program_id(matches.get_one::<String>("program_id").unwrap()).unwrap_or_else(|e| {
+        eprintln!("Invalid program id: {}", e);
+        std::process::exit(-1);
+    });
+
+    // A PDA outside of Solana's limits can't be in a transaction, so they are always checked
+    let seeds = parse_seeds(&args, false, SeedLimits::Solana, Normalization::None, None).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    });
+
+    let (canonical_pda, canonical_bump_seed) = find_pda(&program_id, &seeds).unwrap_or_else(|| {
+        eprintln!("Cannot find PDA");
+        std::process::exit(-1);
+    });
+
+    // A given bump seed is used as create_program_address would use it, and is checked against the canonical one,
+    // since a program that accepts any bump seed lets callers choose between several PDAs for the same seeds
+    let (pda, bump_seed) = match bump {
+        Some(bump_seed) => {
+            let pda = create_pda(&program_id, &seeds, bump_seed).unwrap_or_else(|| {
+                eprintln!("{}", no_pda_error(false, Some(bump_seed)));
+                std::process::exit(-1);
+            });
+            if require_canonical && (bump_seed != canonical_bump_seed) {
+                eprintln!(
+                    "Bump seed {} is not the canonical bump seed {} (which gives {})",
+                    bump_seed, canonical_bump_seed, canonical_pda
+                );
+                std::process::exit(2);
+            }
+            (pda, bump_seed)
+        },
+        None => (canonical_pda, canonical_bump_seed)
+    };
+
+    println!("PDA: {}.{}", pda, bump_seed);
+    if bump_seed != canonical_bump_seed {
+        println!(
+            "  Warning: bump seed {} is not the canonical bump seed {} (which gives {})",
+            bump_seed, canonical_bump_seed, canonical_pda
+        );
+    }
+    println!(
+        "Message: {}, {} account keys, {} instructions",
+        message.version.map(|v| format!("v{}", v)).unwrap_or_else(|| "legacy".to_string()),
+        message.account_keys.len(),
+        message.instructions.len()
+    );
+
+    let index = match message.account_keys.iter().position(|key| *key == pda) {
+        Some(index) => index,
+        None => {
+            println!("PDA is not among the message's static account keys");
+            for lookup in &message.address_table_lookups {
+                println!(
+                    "  Note: {} writable and {} readonly addresses are loaded from lookup table {}, which cannot be \
+                     checked offline",
+                    lookup.writable_indexes.len(),
+                    lookup.readonly_indexes.len(),
+                    lookup.account_key
+                );
+            }
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Account index {}: {}, {}",
+        index,
+        if message.is_writable(index) { "writable" } else { "readonly" },
+        if message.is_signer(index) { "signer" } else { "not a signer" }
+    );
+
+    if message.is_signer(index) {
+        println!(
+            "  Warning: a PDA has no private key and can only sign via invoke_signed, not as a transaction signer"
+        );
+    }
+
+    // Indexes past the static keys refer to addresses loaded from lookup tables
+    let key_name = |i : u8| {
+        message
+            .account_keys
+            .get(i as usize)
+            .map(|key| key.to_string())
+            .unwrap_or_else(|| format!("<lookup table address #{}>", (i as usize) - message.account_keys.len()))
+    };
+
+    let mut referenced = false;
+
+    for (i, instruction) in message.instructions.iter().enumerate() {
+        if (instruction.program_id_index as usize) == index {
+            println!("  Instruction {}: invoked as the program id", i);
+            referenced = true;
+        }
+        for (position, account) in instruction.accounts.iter().enumerate() {
+            if (*account as usize) == index {
+                println!(
+                    "  Instruction {} (program {}): account position {}",
+                    i,
+                    key_name(instruction.program_id_index),
+                    position
+                );
+                referenced = true;
+            }
+        }
+    }
+
+    if !referenced {
+        println!("  Not referenced by any instruction");
+    }
+
+    if message.loaded_address_count() > 0 {
+        println!("  Note: {} additional addresses are loaded from lookup tables", message.loaded_address_count());
+    }
+}
+
+// Options of the main command that may not be given together
+const CONFLICTING_OPTIONS : [(&str, &str); 43] = [
+    ("--no-bump-seed", "--bump"),
+    ("--cross-check", "--no-limit-check"),
+    ("-pubkey", "--no-bump-seed"),
+    ("-pubkey", "--bump"),
+    ("-pubkey", "--no-limit-check"),
+    ("-pubkey", "--cross-check"),
+    ("-pubkey", "--diagnose"),
+    ("-pubkey", "--normalize"),
+    ("-pubkey", "--stdin"),
+    ("-pubkey", "--matrix"),
+    ("-pubkey", "--count"),
+    ("-pubkey", "--watch"),
+    ("--stdin", "--matrix"),
+    ("--stdin", "--count"),
+    ("--stdin", "--program"),
+    ("--stdin", "--programs-file"),
+    ("--stdin", "--watch"),
+    ("--watch", "--matrix"),
+    ("--watch", "--count"),
+    ("--watch", "--program"),
+    ("--watch", "--programs-file"),
+    ("--watch", "-0"),
+    ("--anchor", "--program"),
+    ("--anchor", "--programs-file"),
+    ("--anchor", "--watch"),
+    ("--anchor", "--use-solana-config"),
+    ("--anchor", "--mnemonic"),
+    ("--use-solana-config", "--mnemonic"),
+    ("--stdin", "--mnemonic"),
+    ("--accounts", "--derivation-path"),
+    ("--from-tx", "--mnemonic"),
+    ("--from-tx", "--use-solana-config"),
+    ("--from-tx", "--anchor"),
+    ("--expect", "--mnemonic"),
+    ("--expect", "--from-tx"),
+    ("--expect", "--anchor"),
+    ("--expect", "--watch"),
+    ("--check", "-pubkey"),
+    ("--check", "--stdin"),
+    ("--check", "--watch"),
+    ("--check", "--matrix"),
+    ("--check", "--count"),
+    ("--check", "--cross-check")
+];
+
+// Options of the main command that only apply along with another
+const DEPENDENT_OPTIONS : [(&str, &str); 8] = [
+    ("-0", "--stdin"),
+    ("--jobs", "--stdin"),
+    ("--unordered", "--stdin"),
+    ("--use-solana-config", "-pubkey"),
+    ("--mnemonic", "-pubkey"),
+    ("--derivation-path", "--mnemonic"),
+    ("--accounts", "--mnemonic"),
+    ("--from-tx", "-pubkey")
+];
+
+// A value parser for an option whose value is one of values, which from_str converts
+fn one_of<T : Clone + Send + Sync + 'static>(
+    values : &[&'static str],
+    from_str : fn(&str) -> Option<T>
+) -> impl TypedValueParser<Value = T>
+{
+    PossibleValuesParser::new(values.to_vec()).map(move |value| from_str(&value).unwrap())
+}
+
+// A value parser for a number of things, such as threads, of which there must be at least one
+fn parse_positive(s : &str) -> Result<usize, String>
+{
+    s.parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(|| "expected a number greater than 0".to_string())
+}
+
+// The values of options that may each be given any number of times, as (option, value) pairs in the order given
+fn values_in_order(
+    matches : &ArgMatches,
+    ids : &[&'static str]
+) -> Vec<(&'static str, String)>
+{
+    let mut values = ids
+        .iter()
+        .filter_map(|id| {
+            Some(matches.indices_of(id)?.zip(matches.get_many::<String>(id)?).map(move |(i, value)| (i, *id, value)))
+        })
+        .flatten()
+        .collect::<Vec<(usize, &str, &String)>>();
+
+    values.sort_by_key(|(i, _, _)| *i);
+
+    values.into_iter().map(|(_, id, value)| (id, value.clone())).collect()
+}
+
+// The command line of the main command, which derives PDAs, and of the subcommands.  The help of the main command is
+// the whole of usage_string(), which describes the subcommands too; the subcommands have their own.
+fn command() -> Command
+{
+    let flag =
+        |name : &'static str, help : &'static str| Arg::new(name).long(name).action(ArgAction::SetTrue).help(help);
+    let option = |name : &'static str, value_name : &'static str, help : &'static str| {
+        Arg::new(name).long(name).value_name(value_name).help(help)
+    };
+
+    let mut command = Command::new("solpda")
+        .about("Computes Solana Program Derived Addresses")
+        .version(concat!(
+            env!("CARGO_PKG_VERSION"),
+            " (commit ",
+            env!("SOLPDA_GIT_COMMIT"),
+            ", built ",
+            env!("SOLPDA_BUILD_DATE"),
+            ", features: ",
+            env!("SOLPDA_FEATURES"),
+            ")"
+        ))
+        .long_about(
+            "solpda computes the Solana Program Derived Address (PDA) of a program id and a list of seeds, and has \
+             commands for related tasks, such as searching for PDAs and keys whose addresses match patterns.  solpda \
+             --help describes every option in full, with examples."
+        )
+        .override_help(usage_string())
+        .disable_help_subcommand(true)
+        .args_conflicts_with_subcommands(true)
+        .arg(flag("pubkey", "Only output the public key of each program id (also -pubkey)"))
+        .arg(flag("use-solana-config", "With --pubkey, output the Solana CLI's default keypair's public key"))
+        .arg(flag("mnemonic", "With --pubkey, prompt for a seed phrase and output its public key"))
+        .arg(
+            option("derivation-path", "PATH", "Derive the --mnemonic key along PATH")
+                .num_args(0..=1)
+                .default_missing_value(mnemonic::DEFAULT_DERIVATION_PATH)
+        )
+        .arg(
+            option("accounts", "RANGE", "Output the --mnemonic key of each account in RANGE").value_parser(parse_range)
+        )
+        .arg(option("from-tx", "BASE64_TX", "With --pubkey, output the account keys of a transaction"))
+        .arg(option("anchor", "PROGRAM_NAME", "Find the program id in the current Anchor workspace").num_args(0..=1))
+        .arg(flag("no-bump-seed", "Derive the PDA without a bump seed").overrides_with("bump-seed"))
+        .arg(
+            flag("bump-seed", "Derive the PDA with a bump seed, whatever the config file says")
+                .overrides_with("no-bump-seed")
+        )
+        .arg(
+            option("bump", "BUMP", "Derive the PDA with this bump seed rather than searching for it")
+                .value_parser(clap::value_parser!(u8))
+        )
+        .arg(flag("no-limit-check", "Don't check seeds against Solana's limits"))
+        .arg(flag("allow-large-seeds", "With --no-limit-check, allow seeds of any total length"))
+        .arg(flag("strict", "Fail rather than warn if the program id is a keypair"))
+        .arg(flag("allow-keypair", "Allow a keypair as the program id without a warning"))
+        .arg(
+            option("expect", "KIND", "Require each program id to be this kind of key")
+                .value_parser(one_of(&["pubkey", "keypair"], ExpectedKey::from_str))
+        )
+        .arg(flag("cross-check", "Check each derivation against the Solana SDK's"))
+        .arg(flag("diagnose", "Warn about seeds whose boundaries, emptiness, or length could be misleading"))
+        .arg(flag("check", "Only check the program id and seeds, outputting the length of each seed"))
+        .arg(
+            option("normalize", "FORM", "Normalize the text of String seeds to this Unicode form")
+                .value_parser(one_of(&["nfc", "nfkc", "none"], Normalization::from_str))
+        )
+        .arg(option("program-id-format", "FORMAT", "Read program ids in this format only").value_parser(one_of(
+            &["base58", "pubkey-bytes", "keypair-file", "pubkey-file"],
+            ProgramIdFormat::from_str
+        )))
+        .arg(flag("bytes", "Output addresses as arrays of bytes").overrides_with("no-bytes"))
+        .arg(flag("no-bytes", "Output addresses in Base58, whatever the config file says").overrides_with("bytes"))
+        .arg(flag("stdin", "Read seeds from stdin, one derivation per line"))
+        .arg(Arg::new("0").short('0').action(ArgAction::SetTrue).help("With --stdin, separate lines with NUL"))
+        .arg(flag("matrix", "Derive every combination of seeds with alternatives"))
+        .arg(flag("unordered", "With --stdin, output PDAs as soon as they are found"))
+        .arg(option("program", "PROGRAM_ID", "Derive for this program id").action(ArgAction::Append))
+        .arg(option("programs-file", "FILE", "Derive for each program id listed in FILE").action(ArgAction::Append))
+        .arg(option("jobs", "N", "With --stdin, derive with N threads").value_parser(parse_positive))
+        .arg(option("watch", "SPEC", "Derive the PDAs listed in SPEC whenever it changes"))
+        .arg(
+            option("count", "N", "Derive for each index seed from 0 to N - 1")
+                .value_parser(clap::value_parser!(u64).range(1..))
+        )
+        .arg(
+            Arg::new("args")
+                .value_name("ARGS")
+                .num_args(0..)
+                .help("The program id and seeds, in groups separated by --")
+        )
+        .subcommand(tx_verify_command())
+        .subcommand(oncurve_command())
+        .subcommand(grind::grind_command())
+        .subcommand(grind::grind_worker_command())
+        .subcommand(crack::crack_command())
+        .subcommand(grind::grind_key_command())
+        .subcommand(keygen::keygen_command())
+        .subcommand(convert_key::convert_key_command())
+        .subcommand(sign::sign_command())
+        .subcommand(sign::verify_sig_command())
+        .subcommand(batch::batch_command())
+        .subcommand(bench::bench_command())
+        .subcommand(selftest::selftest_command())
+        .subcommand(repl::repl_command())
+        .subcommand(tui::tui_command())
+        .subcommand(alias::alias_command())
+        .subcommand(man::man_command());
+
+    // Arguments are named by their options, without the leading dashes
+    for (a, b) in CONFLICTING_OPTIONS {
+        command = command.mut_arg(a.trim_start_matches('-'), |arg| arg.conflicts_with(b.trim_start_matches('-')));
+    }
+    for (a, b) in DEPENDENT_OPTIONS {
+        command = command.mut_arg(a.trim_start_matches('-'), |arg| arg.requires(b.trim_start_matches('-')));
+    }
+
+    command
+}
+
+// What each -- separating groups of seeds is given to clap as, since clap would otherwise take the first -- to end the
+// options, and options may be given anywhere among the program id and seeds
+const GROUP_SEPARATOR : &str = "\u{1d}";
+
+// The --anchor option: None if it isn't given, else the program name given with it, if any.  The program name is
+// optional, and is only taken to be one if it couldn't be a seed; clap takes whatever follows --anchor as its value, so
+// anything else is a seed, which is put back among seeds where it was given, since options may come between seeds.
+fn anchor_option(
+    matches : &ArgMatches,
+    seeds : &mut Vec<String>
+) -> Option<Option<String>>
+{
+    let is_name = |arg : &str| {
+        !arg.starts_with('-') && arg.chars().all(|c| c.is_ascii_alphanumeric() || (c == '_') || (c == '-'))
+    };
+
+    matches.contains_id("anchor").then(|| match matches.get_one::<String>("anchor") {
+        Some(name) if is_name(name) => Some(name.clone()),
+        Some(seed) => {
+            let index = matches.index_of("anchor").unwrap_or(0);
+            let position =
+                matches.indices_of("args").map(|indices| indices.filter(|i| *i < index).count()).unwrap_or(0);
+            seeds.insert(position, seed.clone());
+            None
+        },
+        None => None
+    })
+}
+
+// Parses the command line, exiting with status 0 after outputting help, or with status -1, as solpda always has, if
+// the command line is invalid
+fn parse_command_line() -> ArgMatches
+{
+    let command = command();
+
+    // Subcommands don't have groups of seeds, so -- keeps its usual meaning for them
+    let is_subcommand = std::env::args().nth(1).is_some_and(|arg| command.find_subcommand(&arg).is_some());
+
+    // -pubkey predates the other options, so is accepted with a single dash too
+    let args = std::env::args().enumerate().map(|(i, arg)| {
+        if arg == "-pubkey" {
+            "--pubkey".to_string()
+        }
+        else if (arg == "--") && (i > 0) && !is_subcommand {
+            GROUP_SEPARATOR.to_string()
+        }
+        else {
+            arg
+        }
+    });
+
+    command.try_get_matches_from(args).unwrap_or_else(|e| {
+        e.print().ok();
+        std::process::exit(if e.use_stderr() { -1 } else { 0 });
+    })
+}
+
+fn main()
+{
+    let matches = parse_command_line();
+
+    match matches.subcommand() {
+        Some(("tx-verify", matches)) => {
+            tx_verify(matches);
+            return;
+        },
+
+        Some(("oncurve", matches)) => {
+            oncurve(matches);
+            return;
+        },
+
+        Some(("grind", matches)) => {
+            grind::grind(matches);
+            return;
+        },
+
+        Some(("grind-worker", matches)) => {
+            grind::grind_worker(matches);
+            return;
+        },
+
+        Some(("grind-key", matches)) => {
+            grind::grind_key(matches);
+            return;
+        },
+
+        Some(("keygen", matches)) => {
+            keygen::keygen(matches);
+            return;
+        },
+
+        Some(("convert-key", matches)) => {
+            convert_key::convert_key(matches);
+            return;
+        },
+
+        Some(("sign", matches)) => {
+            sign::sign(matches);
+            return;
+        },
+
+        Some(("verify-sig", matches)) => {
+            sign::verify_sig(matches);
+            return;
+        },
+
+        Some(("batch", matches)) => {
+            batch::batch(matches);
+            return;
+        },
+
+        Some(("bench", matches)) => {
+            bench::bench(matches);
+            return;
+        },
+
+        Some(("crack", matches)) => {
+            crack::crack(matches);
+            return;
+        },
+
+        Some(("selftest", matches)) => {
+            selftest::selftest(matches);
+            return;
+        },
+
+        Some(("tui", matches)) => {
+            tui::tui(matches);
+            return;
+        },
+
+        Some(("repl", matches)) => {
+            repl::repl(matches);
+            return;
+        },
+
+        Some(("alias", matches)) => {
+            alias::alias(matches);
+            return;
+        },
+
+        Some(("man", matches)) => {
+            man::man(matches);
+            return;
+        },
+
+        _ => ()
+    }
+
+    let mut seeds : Vec<String> =
+        matches.get_many::<String>("args").map(|args| args.cloned().collect()).unwrap_or_default();
+
+    let config = config::load();
+
+    let bump_seed = matches.get_one::<u8>("bump").copied();
+    // A bump seed given on the command line, or --bump-seed, overrides the config file's no_bump_seed
+    let no_bump_seed = matches.get_flag("no-bump-seed") ||
+        (config.no_bump_seed && bump_seed.is_none() && !matches.get_flag("bump-seed"));
+    let cross_check = matches.get_flag("cross-check");
+    // --cross-check needs the seeds to be within Solana's limits, so it overrides the config file's no_limit_check
+    let limit_check = !(matches.get_flag("no-limit-check") || (config.no_limit_check && !cross_check));
+    let allow_large_seeds = matches.get_flag("allow-large-seeds");
+    let diagnose = matches.get_flag("diagnose");
+    let normalization =
+        matches.get_one::<Normalization>("normalize").copied().or(config.normalize).unwrap_or(Normalization::None);
+    let program_id_format =
+        matches.get_one::<ProgramIdFormat>("program-id-format").copied().or(config.program_id_format);
+    let bytes = matches.get_flag("bytes") || (config.bytes && !matches.get_flag("no-bytes"));
+    let pubkey_only = matches.get_flag("pubkey");
+    let use_solana_config = matches.get_flag("use-solana-config");
+    let strict = matches.get_flag("strict") || config.strict;
+    // --strict overrides the config file's allow_keypair
+    let allow_keypair = matches.get_flag("allow-keypair") || (config.allow_keypair && !matches.get_flag("strict"));
+    let expect = matches.get_one::<ExpectedKey>("expect").copied();
+    let mnemonic = matches.get_flag("mnemonic");
+    let derivation_path = matches.get_one::<String>("derivation-path").cloned();
+    let accounts = matches.get_one::<(u64, u64)>("accounts").copied();
+    let stdin = matches.get_flag("stdin");
+    let nul = matches.get_flag("0");
+    let mut matrix = matches.get_flag("matrix");
+    let count = matches.get_one::<u64>("count").copied();
+    let jobs = matches.get_one::<usize>("jobs").copied().or(config.jobs);
+    let unordered = matches.get_flag("unordered");
+    let watch = matches.get_one::<String>("watch").cloned();
+    let check = matches.get_flag("check");
+
+    let from_tx = matches.get_one::<String>("from-tx").map(|tx| {
+        let tx = base64::decode(tx.trim()).unwrap_or_else(|e| {
+            eprintln!("Invalid Base64 transaction: {}", e);
+            std::process::exit(-1);
+        });
+        Message::from_transaction_or_message(&tx).unwrap_or_else(|e| {
+            eprintln!("Invalid transaction: {}", e);
+            std::process::exit(-1);
+        })
+    });
+
+    let anchor = anchor_option(&matches, &mut seeds);
+
+    // The --program and --programs-file options, which are read once --program-id-format is known
+    let program_options = values_in_order(&matches, &["program", "programs-file"]);
+
+    // Checked here rather than by clap, since the config file may give no_limit_check
+    if allow_large_seeds && limit_check {
+        eprintln!("--allow-large-seeds can only be used with --no-limit-check");
+        std::process::exit(-1);
+    }
+
+    if cross_check && !cfg!(feature = "parity") {
+        eprintln!("--cross-check requires solpda to be built with the parity feature");
+        std::process::exit(-1);
+    }
+
+    let options = DeriveOptions {
+        no_bump_seed,
+        bump_seed,
+        limits : SeedLimits::new(limit_check, allow_large_seeds),
+        cross_check,
+        diagnose,
+        normalization,
+        program_id_format,
+        bytes
+    };
+
+    // --expect keypair says that a keypair is meant, so it is no mistake
+    let allow_keypair = allow_keypair || (expect == Some(ExpectedKey::Keypair));
+
+    let mut programs = Vec::<Pubkey>::new();
+    // Labels for the public keys that -pubkey outputs, if they need them
+    let mut pubkey_labels = Vec::<String>::new();
+    for (option, value) in &program_options {
+        if *option == "program" {
+            programs.push(read_program_id_as(value, program_id_format).unwrap_or_else(|e| {
+                eprintln!("Invalid program id: {}", e);
+                std::process::exit(-1);
+            }));
+            let is_keypair = is_keypair_file(value, program_id_format);
+            check_expected_key(value, is_keypair, expect);
+            if is_keypair && !pubkey_only {
+                check_keypair_program_id(value, strict, allow_keypair);
+            }
+        }
+        else {
+            programs.extend(read_programs_file(value, program_id_format));
+        }
+    }
+
+    if let Some(path) = watch {
+        if !seeds.is_empty() {
+            eprintln!("{}", usage_string());
+            std::process::exit(-1);
+        }
+        watch::watch(&path, options);
+    }
+
+    // Without --program, the first argument is the program id, unless it is a seed (or with --matrix, a seed with
+    // alternatives), in which case the program id is found by --anchor, or else given by SOLPDA_PROGRAM_ID
+    let is_matrix_seed =
+        |seed : &str| matrix && MatrixSeed::new(seed).map(|seed| !seed.values.is_empty()).unwrap_or(false);
+    let program_id_given = seeds.first().map(|seed| !is_seed(seed) && !is_matrix_seed(seed)).unwrap_or(false);
+    let default_program_id = || match &anchor {
+        Some(name) => Some(anchor::workspace_program_id(name.as_deref()).unwrap_or_else(|e| {
+            eprintln!("Cannot find the program id with --anchor: {}", e);
+            std::process::exit(-1);
+        })),
+        None => env_program_id(program_id_format, &config)
+    };
+
+    if anchor.is_some() && program_id_given {
+        eprintln!("--anchor finds the program id, so it cannot also be given");
+        std::process::exit(-1);
+    }
+
+    if stdin {
+        if seeds.len() > 1 {
+            eprintln!("{}", usage_string());
+            std::process::exit(-1);
+        }
+        if seeds.first().map(|program_id| program_id == "-").unwrap_or(false) {
+            eprintln!("The program id cannot be read from stdin (-) with --stdin, which reads seeds from it");
+            std::process::exit(-1);
+        }
+        let program_id = match seeds.first() {
+            Some(program_id) => {
+                let pubkey = read_program_id_as(program_id, program_id_format).unwrap_or_else(|e| {
+                    eprintln!("Invalid program id: {}", e);
+                    std::process::exit(-1);
+                });
+                let is_keypair = is_keypair_file(program_id, program_id_format);
+                check_expected_key(program_id, is_keypair, expect);
+                if is_keypair {
+                    check_keypair_program_id(program_id, strict, allow_keypair);
+                }
+                Some(pubkey)
+            },
+            None => default_program_id()
+        };
+        std::process::exit(derive_stdin(
+            program_id,
+            options,
+            jobs.unwrap_or_else(parallel::default_jobs),
+            unordered,
+            nul
+        ));
+    }
+
+    if use_solana_config && !(programs.is_empty() && seeds.is_empty()) {
+        eprintln!("--use-solana-config reads the program id, so it cannot also be given");
+        std::process::exit(-1);
+    }
+
+    if mnemonic && !(programs.is_empty() && seeds.is_empty()) {
+        eprintln!("--mnemonic prompts for the program id's seed phrase, so the program id cannot also be given");
+        std::process::exit(-1);
+    }
+
+    if from_tx.is_some() && !(programs.is_empty() && seeds.is_empty()) {
+        eprintln!("--from-tx reads the public keys from the transaction, so program ids cannot also be given");
+        std::process::exit(-1);
+    }
+
+    if programs.is_empty() && !program_id_given {
+        if let Some(message) = from_tx {
+            if message.loaded_address_count() > 0 {
+                eprintln!(
+                    "Note: {} additional addresses are loaded from lookup tables, which cannot be read offline",
+                    message.loaded_address_count()
+                );
+            }
+            programs.extend(message.account_keys);
+        }
+        else if mnemonic {
+            // With --accounts, each public key is labeled with its derivation path, to show which account it is
+            let derivation_paths = match accounts {
+                Some((first, last)) => (first..=last).map(|i| Some(mnemonic::account_derivation_path(i))).collect(),
+                None => vec![derivation_path.clone()]
+            };
+            programs.extend(mnemonic::prompt_pubkeys(&derivation_paths).unwrap_or_else(|e| {
+                eprintln!("Cannot derive the public key: {}", e);
+                std::process::exit(-1);
+            }));
+            if accounts.is_some() {
+                pubkey_labels = derivation_paths.into_iter().flatten().collect();
+            }
+        }
+        // -pubkey --use-solana-config outputs the Solana CLI's default keypair, which is always a key file
+        else if use_solana_config {
+            let keypair_path = solana_config::keypair_path().unwrap_or_else(|e| {
+                eprintln!("Cannot use the Solana CLI config: {}", e);
+                std::process::exit(-1);
+            });
+            programs.push(read_program_id_as(&keypair_path, Some(ProgramIdFormat::KeypairFile)).unwrap_or_else(|e| {
+                eprintln!("Invalid default keypair: {}", e);
+                std::process::exit(-1);
+            }));
+        }
+        else {
+            programs.extend(default_program_id());
+        }
+    }
+
+    if seeds.is_empty() && programs.is_empty() {
+        eprintln!("{}", usage_string());
+        std::process::exit(-1);
+    }
+
+    // The first argument is the program id, unless there are others; -pubkey outputs the public key of every argument.
+    // Reading the public key of a keypair is what -pubkey is for, so only derivations check for them.
+    while !seeds.is_empty() && (programs.is_empty() || pubkey_only) {
+        let program_id = seeds.remove(0);
+        let (pubkey, is_keypair) = if program_id == "-" {
+            read_stdin().and_then(|contents| {
+                read_program_id_stdin(&contents, program_id_format).map(|pubkey| (pubkey, holds_secret_key(&contents)))
+            })
+        }
+        else {
+            read_program_id_as(&program_id, program_id_format)
+                .map(|pubkey| (pubkey, is_keypair_file(&program_id, program_id_format)))
+        }
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid program id: {}", e);
+            std::process::exit(-1);
+        });
+        let source = if program_id == "-" { "read from stdin" } else { &program_id };
+        check_expected_key(source, is_keypair, expect);
+        if is_keypair && !pubkey_only {
+            check_keypair_program_id(source, strict, allow_keypair);
+        }
+        programs.push(pubkey);
+    }
+
+    if pubkey_only {
+        for (i, program_id) in programs.iter().enumerate() {
+            if let Some(label) = pubkey_labels.get(i) {
+                print!("{} ", label);
+            }
+            if bytes {
+                print_pubkey_bytes(&program_id.0);
+                println!();
+            }
+            else {
+                println!("{}", program_id);
+            }
+        }
+        return;
+    }
+
+    // Each group of seeds separated by -- is a separate derivation
+    let mut groups =
+        seeds.split(|seed| seed == GROUP_SEPARATOR).map(|group| group.to_vec()).collect::<Vec<Vec<String>>>();
+
+    // --count is a matrix over an implicit index seed
+    if let Some(count) = count {
+        groups.iter_mut().for_each(|group| group.push(format!("u64{{0..{}}}", count)));
+        matrix = true;
+    }
+
+    if groups.iter().any(|group| group.is_empty()) {
+        eprintln!("{}", usage_string());
+        std::process::exit(-1);
+    }
+
+    if check {
+        std::process::exit(check_seeds(&groups, options));
+    }
+
+    let mut status = 0;
+
+    for (i, group) in groups.iter().enumerate() {
+        // Several program ids are labeled in the same way as a matrix, and each group's results are separated by a
+        // blank line
+        if matrix || (programs.len() > 1) {
+            if i > 0 {
+                println!();
+            }
+            derive_matrix(&programs, group, options, &mut status);
+            continue;
+        }
+
+        let error_prefix = if groups.len() > 1 { format!("Group {}: ", i + 1) } else { String::new() };
+
+        match options.derive(&programs[0], group) {
+            Ok(Some(derived)) => print_pda(&derived, bytes),
+            Ok(None) => {
+                eprintln!("{}{}", error_prefix, options.no_pda_error());
+                if status == 0 {
+                    status = options.no_pda_status();
+                }
+            },
+            Err(e) => {
+                eprintln!("{}{}", error_prefix, e);
+                status = -1;
+            }
+        }
+    }
+
+    std::process::exit(status);
+}
+
+// Checks each group of seeds as --check does, outputting the length of each seed of the groups that are valid.
+// Returns the exit status: -1 if any group is invalid, else 0.
+fn check_seeds(
+    groups : &[Vec<String>],
+    options : DeriveOptions
+) -> i32
+{
+    let mut status = 0;
+
+    for (i, group) in groups.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        match parse_seeds(group, options.no_bump_seed, options.limits, options.normalization, options.program_id_format)
+        {
+            Ok(parsed) => group.iter().zip(parsed).for_each(|(seed, parsed)| println!("{} {}", parsed.len(), seed)),
+            Err(e) => {
+                let error_prefix = if groups.len() > 1 { format!("Group {}: ", i + 1) } else { String::new() };
+                eprintln!("{}{}", error_prefix, e);
+                status = -1;
+            }
+        }
+    }
+
+    status
+}
+
+// If s is the base64 encoding of an address, says so and gives the address
+fn base64_hint(s : &str) -> Option<String>
+{
+    match base64::decode(s).or_else(|_| base64::decode_config(s, base64::URL_SAFE)) {
+        Ok(bytes) if bytes.len() == 32 => Some(format!(
+            "it appears to be base64 rather than base58; in base58 it is {}",
+            bs58::encode(bytes).into_string()
+        )),
+        _ => None
+    }
+}
+
+// Describes why s failed to decode as base58, with a hint as to what was likely meant when the character that isn't in
+// the alphabet is one that is often confused with one that is, or suggests that s is base64
+fn base58_error(
+    s : &str,
+    e : bs58::decode::Error
+) -> String
+{
+    let hint = match e {
+        bs58::decode::Error::InvalidCharacter { character, .. } => base64_hint(s).or(match character {
+            '0' | 'O' => Some(format!("{} is not used in base58; perhaps o was meant", character)),
+            'I' | 'l' => Some(format!("{} is not used in base58; perhaps 1, i, or L was meant", character)),
+            '+' | '/' | '=' | '-' | '_' => {
+                Some(format!("{} is not used in base58; the string may be base64 rather than base58", character))
+            },
+            _ => None
+        }),
+        _ => None
+    };
+
+    match hint {
+        Some(hint) => format!("{} ({})", e, hint),
+        None => e.to_string()
+    }
+}
+
+impl std::str::FromStr for Pubkey
+{
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Self, Self::Err>
+    {
+        let mut address = [0_u8; 32];
+
+        let v = bs58::decode(s).into_vec().map_err(|e| base58_error(s, e))?;
+
+        match v.len() {
+            32 => {
+                address.copy_from_slice(v.as_slice());
+                Ok(Pubkey(address))
+            },
+            // The base58 encoding of a keypair, as some wallets export them
+            64 => Err("decodes to 64 bytes, which is a keypair rather than an address".to_string()),
+            len => Err(format!(
+                "decodes to {} bytes rather than 32, so {}",
+                len,
+                base64_hint(s).unwrap_or_else(|| {
+                    if len < 32 { "it may be missing characters" } else { "it may have extra characters" }.to_string()
+                })
+            ))
+        }
+    }
+}
+
+impl std::fmt::Display for Pubkey
+{
+    fn fmt(
+        &self,
+        f : &mut std::fmt::Formatter
+    ) -> std::fmt::Result
+    {
+        write!(f, "{}", bs58::encode(self.0).into_string())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // The seeds and --anchor program name of a command line
+    fn anchor_args(args : &[&str]) -> (Vec<String>, Option<Option<String>>)
+    {
+        let matches = command().try_get_matches_from(std::iter::once("solpda").chain(args.iter().copied())).unwrap();
+        let mut seeds : Vec<String> = matches.get_many::<String>("args").into_iter().flatten().cloned().collect();
+        let anchor = anchor_option(&matches, &mut seeds);
+        (seeds, anchor)
+    }
+
+    #[test]
+    fn anchor_seed_keeps_its_position()
+    {
+        let strings = |seeds : &[&str]| seeds.iter().map(|seed| seed.to_string()).collect::<Vec<String>>();
+
+        assert_eq!(
+            anchor_args(&["--anchor", "String[a]", "String[b]"]),
+            (strings(&["String[a]", "String[b]"]), Some(None))
+        );
+        assert_eq!(
+            anchor_args(&["String[a]", "--anchor", "String[b]"]),
+            (strings(&["String[a]", "String[b]"]), Some(None))
+        );
+        assert_eq!(
+            anchor_args(&["String[a]", "--anchor", "String[b]", "String[c]"]),
+            (strings(&["String[a]", "String[b]", "String[c]"]), Some(None))
+        );
+        assert_eq!(
+            anchor_args(&["String[a]", "String[b]", "--anchor"]),
+            (strings(&["String[a]", "String[b]"]), Some(None))
+        );
+        assert_eq!(
+            anchor_args(&["--anchor", "foo", "String[a]", "String[b]"]),
+            (strings(&["String[a]", "String[b]"]), Some(Some("foo".to_string())))
+        );
+        assert_eq!(anchor_args(&["String[a]"]), (strings(&["String[a]"]), None));
     }
 }
@@ -0,0 +1,71 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use clap::{Arg, ArgMatches, Command};
+use clap_mangen::roff::{roman, Roff};
+use clap_mangen::Man;
+
+use crate::{command, SEED_GRAMMAR};
+
+pub fn man_command() -> Command
+{
+    Command::new("man").about("Outputs the man page of solpda, or of one of its commands, in roff").arg(
+        Arg::new("command")
+            .value_name("COMMAND")
+            .help("The command whose man page to output, such as grind, rather than solpda's")
+    )
+}
+
+// Adds the seed grammar, which the options don't describe, as a section of its own
+fn render_seeds_section(w : &mut dyn std::io::Write) -> std::io::Result<()>
+{
+    let mut roff = Roff::new();
+    roff.control("SH", ["SEEDS"]);
+    roff.text([roman("A PDA is derived from one or more seeds, each of which is one of:")]);
+    roff.control("PP", []);
+    roff.control("nf", []);
+    // The table is indented for usage_string(), but the man page indents it itself
+    for line in SEED_GRAMMAR.lines() {
+        roff.text([roman(line.strip_prefix("    ").unwrap_or(line))]);
+    }
+    roff.control("fi", []);
+    roff.to_writer(w)
+}
+
+// Renders the man page of the main command, which has the seeds section after its options, or that of a command, as
+// clap_mangen does by default
+pub fn man(matches : &ArgMatches)
+{
+    let mut root = command();
+    // Building the main command gives each command its full name, such as solpda-grind, for its man page's title
+    root.build();
+
+    let mut stdout = std::io::stdout().lock();
+
+    let source = format!("solpda {}", env!("CARGO_PKG_VERSION"));
+
+    let result = match matches.get_one::<String>("command") {
+        Some(name) => match root.find_subcommand(name) {
+            Some(subcommand) => Man::new(subcommand.clone()).source(source).render(&mut stdout),
+            None => {
+                eprintln!("Unknown command: {}", name);
+                std::process::exit(-1);
+            }
+        },
+        None => {
+            let man = Man::new(root).source(source);
+            man.render_title(&mut stdout)
+                .and_then(|_| man.render_name_section(&mut stdout))
+                .and_then(|_| man.render_synopsis_section(&mut stdout))
+                .and_then(|_| man.render_description_section(&mut stdout))
+                .and_then(|_| man.render_options_section(&mut stdout))
+                .and_then(|_| render_seeds_section(&mut stdout))
+                .and_then(|_| man.render_subcommands_section(&mut stdout))
+        }
+    };
+
+    result.unwrap_or_else(|e| {
+        eprintln!("Failed to write the man page: {}", e);
+        std::process::exit(-1);
+    });
+}
@@ -0,0 +1,189 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use bip39::{Language, Mnemonic, MnemonicType, Seed};
+use sha2::{Digest, Sha512};
+use std::io::{BufRead, Write};
+use zeroize::Zeroizing;
+
+use crate::Pubkey;
+
+// The derivation path that solana-keygen uses when --derivation-path is given without a value
+pub const DEFAULT_DERIVATION_PATH : &str = "m/44'/501'/0'/0'";
+
+// The first index of hardened children; ed25519 keys can only be derived as hardened children
+const HARDENED : u32 = 1 << 31;
+
+// HMAC-SHA512, whose keys and results here are all secret, so are zeroized when dropped
+fn hmac_sha512(
+    key : &[u8],
+    data : &[&[u8]]
+) -> Zeroizing<[u8; 64]>
+{
+    // SHA-512's block size
+    const BLOCK_SIZE : usize = 128;
+
+    let mut block = Zeroizing::new(vec![0_u8; BLOCK_SIZE]);
+    if key.len() > BLOCK_SIZE {
+        block[..64].copy_from_slice(&Zeroizing::new(<[u8; 64]>::from(Sha512::digest(key)))[..]);
+    }
+    else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |value : u8| {
+        let mut pad = Zeroizing::new(vec![0_u8; BLOCK_SIZE]);
+        pad.iter_mut().zip(block.iter()).for_each(|(pad, b)| *pad = b ^ value);
+        pad
+    };
+
+    let mut inner = Sha512::new();
+    inner.update(&*pad(0x36));
+    data.iter().for_each(|data| inner.update(data));
+    let inner = Zeroizing::new(<[u8; 64]>::from(inner.finalize()));
+
+    let mut outer = Sha512::new();
+    outer.update(&*pad(0x5c));
+    outer.update(&inner[..]);
+
+    Zeroizing::new(outer.finalize().into())
+}
+
+// Parses a derivation path such as m/44'/501'/0'/0'.  As with solana-keygen, every index is taken to be hardened
+// whether or not it is marked with ', since ed25519 keys can't be derived any other way.
+fn parse_derivation_path(path : &str) -> Result<Vec<u32>, String>
+{
+    let indexes = path
+        .strip_prefix('m')
+        .ok_or_else(|| format!("derivation path {} does not start with m", path))?
+        .trim_start_matches('/');
+
+    indexes
+        .split('/')
+        .filter(|index| !indexes.is_empty() || !index.is_empty())
+        .map(|index| {
+            index
+                .trim_end_matches('\'')
+                .parse::<u32>()
+                .ok()
+                .filter(|index| *index < HARDENED)
+                .map(|index| index | HARDENED)
+                .ok_or_else(|| format!("derivation path {} has an invalid index: {}", path, index))
+        })
+        .collect()
+}
+
+// Derives the ed25519 secret key at path from a BIP39 seed as SLIP-0010 specifies, as solana-keygen does
+fn derive_secret_key(
+    seed : &[u8],
+    path : &[u32]
+) -> Zeroizing<[u8; 32]>
+{
+    let mut key = hmac_sha512(b"ed25519 seed", &[seed]);
+
+    for index in path {
+        key = hmac_sha512(&key[32..], &[&[0], &key[..32], &index.to_be_bytes()]);
+    }
+
+    Zeroizing::new(key[..32].try_into().unwrap())
+}
+
+// Prompts on stderr and reads a line from stdin, without echoing it if stdin is a terminal.  The line is secret, so it is
+// zeroized when dropped, and read into a buffer large enough that it isn't copied as the buffer grows.
+fn prompt(prompt : &str) -> Result<Zeroizing<String>, String>
+{
+    eprint!("{}", prompt);
+    std::io::stderr().flush().ok();
+
+    #[cfg(unix)]
+    let saved = {
+        let mut termios : libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } == 0 {
+            let saved = termios;
+            termios.c_lflag &= !libc::ECHO;
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) };
+            Some(saved)
+        }
+        else {
+            None
+        }
+    };
+
+    let mut line = Zeroizing::new(String::with_capacity(1024));
+    let result = std::io::stdin().lock().read_line(&mut line);
+
+    #[cfg(unix)]
+    if let Some(saved) = saved {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &saved) };
+        // The newline that ended the line wasn't echoed either
+        eprintln!();
+    }
+
+    match result {
+        Ok(0) => Err("no input".to_string()),
+        Ok(_) => {
+            let len = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(len);
+            Ok(line)
+        },
+        Err(e) => Err(e.to_string())
+    }
+}
+
+// The derivation path of account index, as solana-keygen and most wallets number accounts
+pub fn account_derivation_path(index : u64) -> String
+{
+    format!("m/44'/501'/{}'/0'", index)
+}
+
+// Prompts for a seed phrase and its passphrase, and gives the public keys of the keypairs that solana-keygen recovers
+// from them for each of derivation_paths: the first 32 bytes of the BIP39 seed as the secret key for None, or else the
+// key derived at the path
+pub fn prompt_pubkeys(derivation_paths : &[Option<String>]) -> Result<Vec<Pubkey>, String>
+{
+    let paths = derivation_paths
+        .iter()
+        .map(|path| path.as_deref().map(parse_derivation_path).transpose())
+        .collect::<Result<Vec<Option<Vec<u32>>>, String>>()?;
+
+    let phrase = prompt("[pubkey] seed phrase: ")?;
+    let mnemonic =
+        Mnemonic::from_phrase(phrase.trim(), Language::English).map_err(|e| format!("invalid seed phrase: {}", e))?;
+    let passphrase = prompt(
+        "[pubkey] If this seed phrase has an associated passphrase, enter it now. Otherwise, press ENTER to continue: "
+    )?;
+
+    let seed = Seed::new(&mnemonic, &passphrase);
+
+    paths
+        .iter()
+        .map(|path| {
+            let secret_key = secret_key(&seed, path.as_deref());
+            let secret_key = ed25519_dalek::SecretKey::from_bytes(&*secret_key).map_err(|e| e.to_string())?;
+            Ok(Pubkey(ed25519_dalek::PublicKey::from(&secret_key).to_bytes()))
+        })
+        .collect()
+}
+
+// The secret key that solana-keygen recovers from the BIP39 seed of a seed phrase and passphrase: the first 32 bytes of
+// the seed, or the key derived at path if given
+fn secret_key(
+    seed : &Seed,
+    path : Option<&[u32]>
+) -> Zeroizing<[u8; 32]>
+{
+    match path {
+        Some(path) => derive_secret_key(seed.as_bytes(), path),
+        None => Zeroizing::new(seed.as_bytes()[..32].try_into().unwrap())
+    }
+}
+
+// Generates a 12 word seed phrase, without a passphrase, and the secret key of the keypair that solana-keygen new would
+// make from it, so that it can be recovered with solana-keygen recover
+pub fn generate() -> (Mnemonic, Zeroizing<[u8; 32]>)
+{
+    let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::English);
+    let secret_key = secret_key(&Seed::new(&mnemonic, ""), None);
+
+    (mnemonic, secret_key)
+}
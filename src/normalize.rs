@@ -0,0 +1,106 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+// How the text of String seeds is normalized before it is hashed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Normalization
+{
+    None,
+
+    Nfc,
+
+    Nfkc
+}
+
+impl Normalization
+{
+    pub fn from_str(s : &str) -> Option<Self>
+    {
+        match s {
+            "none" => Some(Normalization::None),
+            "nfc" => Some(Normalization::Nfc),
+            "nfkc" => Some(Normalization::Nfkc),
+            _ => None
+        }
+    }
+
+    // Normalizes the text of a String seed
+    pub fn apply(
+        &self,
+        text : &str
+    ) -> String
+    {
+        match self {
+            Normalization::None => text.to_string(),
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfkc => text.nfkc().collect()
+        }
+    }
+}
+
+// Characters that are not visible themselves, such as zero width spaces, joiners, direction marks, and variation
+// selectors
+fn is_invisible(c : char) -> bool
+{
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{034F}'
+            | '\u{061C}'
+            | '\u{115F}'
+            | '\u{1160}'
+            | '\u{17B4}'
+            | '\u{17B5}'
+            | '\u{180B}'..='\u{180F}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{206F}'
+            | '\u{3164}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FEFF}'
+            | '\u{FFA0}'
+            | '\u{E0000}'..='\u{E0FFF}'
+    )
+}
+
+// Warns about seeds containing characters that make them look the same as other seeds, which give different PDAs;
+// only String seeds may usefully contain such characters, and combining characters are only a concern if String seeds
+// aren't normalized
+pub fn string_seed_warnings(
+    seeds : &[String],
+    normalization : Normalization
+) -> Vec<String>
+{
+    seeds
+        .iter()
+        .enumerate()
+        .filter_map(|(i, seed)| {
+            if let Some(c) = seed.chars().find(|c| is_invisible(*c)) {
+                Some(format!(
+                    "Seed {} ({}) contains the invisible character U+{:04X}, so it looks the same as a seed without \
+                     it, which gives a different PDA",
+                    i + 1,
+                    seed,
+                    c as u32
+                ))
+            }
+            else if normalization == Normalization::None {
+                seed.chars().find(|c| is_combining_mark(*c)).map(|c| {
+                    format!(
+                        "Seed {} ({}) contains the combining character U+{:04X}, so it may look the same as a \
+                         differently encoded seed, which gives a different PDA; consider --normalize nfc",
+                        i + 1,
+                        seed,
+                        c as u32
+                    )
+                })
+            }
+            else {
+                None
+            }
+        })
+        .collect()
+}
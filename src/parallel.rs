@@ -0,0 +1,116 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+
+// The number of results per thread that may wait to be output while an earlier one is still being worked on
+const REORDER_WINDOW_PER_JOB : usize = 64;
+
+pub fn default_jobs() -> usize
+{
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// Applies work to each of items using jobs threads, passing the results to output, which returns false to stop early.
+// If ordered, results are output in the same order as the items, otherwise as soon as they are ready.  Items are only
+// taken from the iterator as threads become free to work on them, and at most a fixed number of results are held at
+// once, so that input is streamed to output in bounded memory.
+pub fn map<T, R>(
+    items : impl Iterator<Item = T> + Send,
+    jobs : usize,
+    ordered : bool,
+    work : impl Fn(T) -> R + Sync,
+    mut output : impl FnMut(R) -> bool
+) where
+    T : Send,
+    R : Send
+{
+    if jobs <= 1 {
+        for item in items {
+            if !output(work(item)) {
+                break;
+            }
+        }
+        return;
+    }
+
+    let window = jobs * REORDER_WINDOW_PER_JOB;
+
+    let items = Mutex::new(items.enumerate());
+
+    // Bounded so that results can't pile up when output is slower than the threads
+    let (sender, receiver) = mpsc::sync_channel(window);
+
+    // The number of results output so far, which threads wait on so that, when ordered, one slow item can't cause an
+    // unbounded number of results to pile up behind it
+    let output_count = Mutex::new(0_usize);
+    let output_changed = Condvar::new();
+    let stop = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (items, work, output_count, output_changed, stop) =
+                (&items, &work, &output_count, &output_changed, &stop);
+            scope.spawn(move || loop {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                // Hold the lock only while taking the item, not while working on it
+                let next = items.lock().unwrap().next();
+                let (i, item) = match next {
+                    Some(next) => next,
+                    None => break
+                };
+                if ordered {
+                    let mut count = output_count.lock().unwrap();
+                    while (i >= (*count + window)) && !stop.load(Ordering::Relaxed) {
+                        count = output_changed.wait(count).unwrap();
+                    }
+                }
+                if sender.send((i, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+
+        drop(sender);
+
+        let mut count = 0_usize;
+
+        // Results can arrive out of order, so when ordered, hold on to them until all of those before them have been
+        // output
+        let mut pending = BTreeMap::new();
+
+        for (i, result) in receiver {
+            let proceed = if ordered {
+                pending.insert(i, result);
+                let mut proceed = true;
+                while let Some(result) = pending.remove(&count) {
+                    proceed = output(result);
+                    count += 1;
+                    if !proceed {
+                        break;
+                    }
+                }
+                *output_count.lock().unwrap() = count;
+                proceed
+            }
+            else {
+                output(result)
+            };
+
+            if !proceed {
+                // Set while holding the lock that waiting threads check it under, so that none can miss it
+                let _count = output_count.lock().unwrap();
+                stop.store(true, Ordering::Relaxed);
+                output_changed.notify_all();
+                // Leaving the loop drops the receiver, which makes threads that are waiting to send results give up
+                break;
+            }
+            output_changed.notify_all();
+        }
+    });
+}
@@ -0,0 +1,70 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use solana_program::pubkey::Pubkey as SolanaPubkey;
+
+use crate::{Derived, Pubkey};
+
+// Derives the PDA of seeds in the way that the Solana SDK does, as the result of derive_pda (or of create_pda, if
+// bump_seed is given) would be
+fn solana_derive(
+    program_id : &Pubkey,
+    seeds : &[Vec<u8>],
+    no_bump_seed : bool,
+    bump_seed : Option<u8>
+) -> Option<Derived>
+{
+    let program_id = SolanaPubkey::new_from_array(program_id.0);
+    let seeds = seeds.iter().map(|seed| seed.as_slice()).collect::<Vec<&[u8]>>();
+
+    if no_bump_seed {
+        return SolanaPubkey::create_program_address(&seeds, &program_id)
+            .ok()
+            .map(|pda| Derived { pda : Pubkey(pda.to_bytes()), bump_seed : None });
+    }
+
+    match bump_seed {
+        Some(bump_seed) => {
+            let bump = [bump_seed];
+            let seeds = [seeds.as_slice(), &[&bump]].concat();
+            SolanaPubkey::create_program_address(&seeds, &program_id)
+                .ok()
+                .map(|pda| Derived { pda : Pubkey(pda.to_bytes()), bump_seed : Some(bump_seed) })
+        },
+        None => SolanaPubkey::try_find_program_address(&seeds, &program_id)
+            .map(|(pda, bump_seed)| Derived { pda : Pubkey(pda.to_bytes()), bump_seed : Some(bump_seed) })
+    }
+}
+
+fn describe(result : Option<Derived>) -> String
+{
+    match result {
+        Some(Derived { pda, bump_seed: Some(bump_seed) }) => format!("{} with bump seed {}", pda, bump_seed),
+        Some(Derived { pda, bump_seed: None }) => format!("{} without a bump seed", pda),
+        None => "no PDA".to_string()
+    }
+}
+
+// Checks that the Solana SDK derives the same result from the same inputs, exiting if it doesn't, since then the
+// result can't be trusted
+pub fn cross_check(
+    program_id : &Pubkey,
+    seeds : &[Vec<u8>],
+    no_bump_seed : bool,
+    bump_seed : Option<u8>,
+    result : Option<Derived>
+)
+{
+    let expected = solana_derive(program_id, seeds, no_bump_seed, bump_seed);
+
+    if expected != result {
+        eprintln!("CROSS-CHECK FAILED: the Solana SDK disagrees with solpda");
+        eprintln!("  Program id: {}", program_id);
+        for (i, seed) in seeds.iter().enumerate() {
+            eprintln!("  Seed {}: {:?}", i + 1, seed);
+        }
+        eprintln!("  solpda:     {}", describe(result));
+        eprintln!("  Solana SDK: {}", describe(expected));
+        std::process::exit(-1);
+    }
+}
@@ -0,0 +1,261 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use crate::{
+    config, env_program_id, is_seed, print_pda, read_program_id_as, split_seeds, DeriveOptions, Normalization,
+    ProgramIdFormat, Pubkey, SeedLimits, SEED_PREFIXES
+};
+
+// Where the REPL's history is kept, relative to the home directory, so that it is still there in the next session
+const HISTORY_PATH : &str = ".config/solpda/repl_history";
+
+// The commands that the REPL understands, besides lines of seeds
+const COMMANDS : [&str; 4] = ["program", "help", "quit", "exit"];
+
+const HELP : &str = "\
+Enter seeds, separated by spaces, to derive their PDA with the current program id, optionally preceded by another
+program id to use for that line only.  Tab completes seed types.  Commands:
+
+  program [<PROGRAM_ID>]   set the current program id, or show it if none is given
+  help                     show this help
+  quit, exit               leave (as does Ctrl-D)";
+
+// Completes seed types, and at the start of a line, commands
+struct SeedHelper;
+
+impl Completer for SeedHelper
+{
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line : &str,
+        pos : usize,
+        _ctx : &Context<'_>
+    ) -> rustyline::Result<(usize, Vec<String>)>
+    {
+        // The word being completed starts after the last space that isn't within the brackets of a seed, and only a
+        // word that isn't yet a seed is completed
+        let mut start = 0;
+        let mut depth = 0_usize;
+        for (i, c) in line[..pos].char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth = depth.saturating_sub(1),
+                _ if c.is_whitespace() && (depth == 0) => start = i + c.len_utf8(),
+                _ => ()
+            }
+        }
+        let word = &line[start..pos];
+        if word.contains('[') {
+            return Ok((start, vec![]));
+        }
+
+        let commands = if line[..start].trim().is_empty() { &COMMANDS[..] } else { &[] };
+
+        let candidates = SEED_PREFIXES
+            .iter()
+            .chain(commands)
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| candidate.to_string())
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for SeedHelper
+{
+    type Hint = String;
+}
+
+impl Highlighter for SeedHelper
+{
+}
+
+impl Validator for SeedHelper
+{
+}
+
+impl Helper for SeedHelper
+{
+}
+
+fn history_path() -> Option<PathBuf>
+{
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_PATH))
+}
+
+pub fn repl_command() -> Command
+{
+    Command::new("repl")
+        .about("Derives PDAs interactively, for a program id set once, from seeds entered one line at a time")
+        .arg(
+            Arg::new("program-id")
+                .value_name("PROGRAM_ID")
+                .help("The program id to start with, if not SOLPDA_PROGRAM_ID or the config file's")
+        )
+        .arg(
+            Arg::new("no-bump-seed")
+                .long("no-bump-seed")
+                .action(ArgAction::SetTrue)
+                .overrides_with("bump-seed")
+                .help("Derive addresses without a bump seed")
+        )
+        .arg(
+            Arg::new("bump-seed")
+                .long("bump-seed")
+                .action(ArgAction::SetTrue)
+                .overrides_with("no-bump-seed")
+                .help("Derive addresses with a bump seed, whatever the config file says")
+        )
+        .arg(
+            Arg::new("bytes")
+                .long("bytes")
+                .action(ArgAction::SetTrue)
+                .overrides_with("no-bytes")
+                .help("Output addresses as arrays of bytes")
+        )
+        .arg(
+            Arg::new("no-bytes")
+                .long("no-bytes")
+                .action(ArgAction::SetTrue)
+                .overrides_with("bytes")
+                .help("Output addresses in Base58, whatever the config file says")
+        )
+        .arg(
+            Arg::new("no-limit-check")
+                .long("no-limit-check")
+                .action(ArgAction::SetTrue)
+                .help("Don't check seeds against Solana's limits")
+        )
+}
+
+// Reads a program id for the REPL, in format if it is given, reporting an invalid one and carrying on rather than
+// exiting
+fn read_program_id(
+    program_id : &str,
+    format : Option<ProgramIdFormat>
+) -> Option<Pubkey>
+{
+    read_program_id_as(program_id, format).map_err(|e| eprintln!("Invalid program id: {}", e)).ok()
+}
+
+pub fn repl(matches : &ArgMatches)
+{
+    let config = config::load();
+
+    let options = DeriveOptions {
+        no_bump_seed : matches.get_flag("no-bump-seed") || (config.no_bump_seed && !matches.get_flag("bump-seed")),
+        bump_seed : None,
+        limits : SeedLimits::new(!(matches.get_flag("no-limit-check") || config.no_limit_check), false),
+        cross_check : false,
+        diagnose : false,
+        normalization : config.normalize.unwrap_or(Normalization::None),
+        program_id_format : config.program_id_format,
+        bytes : matches.get_flag("bytes") || (config.bytes && !matches.get_flag("no-bytes"))
+    };
+
+    let mut program_id = match matches.get_one::<String>("program-id") {
+        Some(program_id) => {
+            Some(read_program_id(program_id, options.program_id_format).unwrap_or_else(|| std::process::exit(-1)))
+        },
+        None => env_program_id(options.program_id_format, &config)
+    };
+
+    let mut editor = Editor::<SeedHelper, DefaultHistory>::new().unwrap_or_else(|e| {
+        eprintln!("Cannot start the REPL: {}", e);
+        std::process::exit(-1);
+    });
+    editor.set_helper(Some(SeedHelper));
+
+    // History is a convenience, so failing to read or save it doesn't stop the REPL
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        editor.load_history(path).ok();
+    }
+
+    loop {
+        let line = match editor.readline("solpda> ") {
+            Ok(line) => line,
+            // Ctrl-C abandons the line being entered, as in a shell
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Failed to read input: {}", e);
+                break;
+            }
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line).ok();
+
+        let mut words = split_seeds(line);
+
+        match words[0].as_str() {
+            "quit" | "exit" => break,
+
+            "help" => println!("{}", HELP),
+
+            "program" => match words.get(1) {
+                Some(new_program_id) => {
+                    program_id = read_program_id(new_program_id, options.program_id_format).or(program_id)
+                },
+                None => match program_id {
+                    Some(program_id) => println!("{}", program_id),
+                    None => println!("No program id is set")
+                }
+            },
+
+            _ => {
+                let line_program_id = if is_seed(&words[0]) {
+                    match program_id {
+                        Some(program_id) => program_id,
+                        None => {
+                            eprintln!("No program id is set; set one with program <PROGRAM_ID>");
+                            continue;
+                        }
+                    }
+                }
+                else {
+                    match read_program_id(&words.remove(0), options.program_id_format) {
+                        Some(program_id) => program_id,
+                        None => continue
+                    }
+                };
+
+                if words.is_empty() {
+                    eprintln!("No seeds given");
+                    continue;
+                }
+
+                match options.derive(&line_program_id, &words) {
+                    Ok(Some(derived)) => print_pda(&derived, options.bytes),
+                    Ok(None) => eprintln!("{}", options.no_pda_error()),
+                    Err(e) => eprintln!("{}", e)
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &history_path {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).ok();
+        }
+        editor.save_history(path).ok();
+    }
+}
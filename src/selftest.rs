@@ -0,0 +1,241 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::str::FromStr;
+
+use clap::{ArgMatches, Command};
+use sha2::{Digest, Sha256};
+
+use crate::{create_pda, derive_pda, parse_seeds, Derived, Normalization, Pubkey, SeedLimits};
+
+const TOKEN_PROGRAM : &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const SYSTEM_PROGRAM : &str = "11111111111111111111111111111111";
+
+// A derivation and the result that Solana gives for it, as checked against the Solana SDK
+struct Vector
+{
+    name : &'static str,
+
+    program_id : &'static str,
+
+    seeds : &'static [&'static str],
+
+    no_bump_seed : bool,
+
+    // A specific bump seed to derive with, as create_program_address does, instead of searching for one
+    bump_seed : Option<u8>,
+
+    // The PDA and its bump seed (unless no_bump_seed), or None if there is no PDA
+    expected : Option<(&'static str, Option<u8>)>
+}
+
+const VECTORS : [Vector; 14] = [
+    Vector {
+        name : "empty seed",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["String[]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("29aDZ12ST2mQkLeVfoeKkkm7WA83MCyiGU3Zf8MjDDGV", Some(255)))
+    },
+    Vector {
+        name : "32 byte seed",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["String[aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("9KYV7PK8igxf6M2uYriB9BA1KgRsNgHG2pJTu8gAAyUE", Some(255)))
+    },
+    Vector {
+        name : "canonical bump seed 254",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["String[vault]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("AispKP3Ls5d2m6t411kMtP1xkPemdZPGPJUvemsBqmXA", Some(254)))
+    },
+    Vector {
+        name : "canonical bump seed 249",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["u64[12]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("Er4gGQR3ccVLGa5ffRQDHBzXNccUKt6bN8q27bqDsiBu", Some(249)))
+    },
+    Vector {
+        name : "system program",
+        program_id : SYSTEM_PROGRAM,
+        seeds : &["String[vault]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("58CDQ9Qgw1ZPedjaTtwrR2MSG4EmmguHSPE2bFtyfinD", Some(254)))
+    },
+    Vector {
+        name : "multi-byte numbers",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["u16[1,2]", "u32[3]", "u64[18446744073709551615]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("HuD4VYkBZsqZjMcfXVvpsvfRvwbAbKsNy9R48LM18CZc", Some(254)))
+    },
+    Vector {
+        name : "Pubkey and Sha256 seeds",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["Pubkey[11111111111111111111111111111111]", "Sha256[String[metadata]]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("9Hp3zMzMTL3ivK5QSSp6BzunWvWWT7uvgPFZySS7rXxw", Some(254)))
+    },
+    Vector {
+        name : "Pda seed",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["Pda[TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA, String[vault]]"],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("29WooS6r9ycuv3SFPMwNjWKWos8y67DEKyEtMiTxK9mu", Some(255)))
+    },
+    Vector {
+        name : "15 seeds and a bump seed",
+        program_id : TOKEN_PROGRAM,
+        seeds : &[
+            "u8[1]", "u8[2]", "u8[3]", "u8[4]", "u8[5]", "u8[6]", "u8[7]", "u8[8]", "u8[9]", "u8[10]", "u8[11]",
+            "u8[12]", "u8[13]", "u8[14]", "u8[15]"
+        ],
+        no_bump_seed : false,
+        bump_seed : None,
+        expected : Some(("5bVFdHV3Se3nu2ZaARVfG9SYbmKMJ8ZbkoW6JhDQEmRG", Some(255)))
+    },
+    Vector {
+        name : "16 seeds and no bump seed",
+        program_id : TOKEN_PROGRAM,
+        seeds : &[
+            "u8[1]", "u8[2]", "u8[3]", "u8[4]", "u8[5]", "u8[6]", "u8[7]", "u8[8]", "u8[9]", "u8[10]", "u8[11]",
+            "u8[12]", "u8[13]", "u8[14]", "u8[15]", "u8[1]"
+        ],
+        no_bump_seed : true,
+        bump_seed : None,
+        expected : Some(("7mkbPp3Pogtzga4Swm7RMVzHQ9jnWheUVDJRM6RRvzdC", None))
+    },
+    Vector {
+        name : "no bump seed",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["u8[5,6]", "String[Hello, world!]", "u8[10]"],
+        no_bump_seed : true,
+        bump_seed : None,
+        expected : Some(("3qfAqSh7wBCr8S5eSa9ufYqLqQ7NZw9PZfVxsaWxM15B", None))
+    },
+    Vector {
+        name : "no bump seed, on the curve",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["String[vault]"],
+        no_bump_seed : true,
+        bump_seed : None,
+        expected : None
+    },
+    Vector {
+        name : "non-canonical bump seed",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["String[vault]"],
+        no_bump_seed : false,
+        bump_seed : Some(252),
+        expected : Some(("57DjfLevBMcvKXWwatLsWCh9xLvRm2ix8NxR3L4w2ZHX", Some(252)))
+    },
+    Vector {
+        name : "bump seed on the curve",
+        program_id : TOKEN_PROGRAM,
+        seeds : &["String[vault]"],
+        no_bump_seed : false,
+        bump_seed : Some(253),
+        expected : None
+    }
+];
+
+// The SHA-256 hash of the PDAs of String[vault] for the token program with each bump seed from 0 to 255 in turn, with
+// 32 zero bytes for those on the curve
+const ALL_BUMP_SEEDS_HASH : &str = "6aa3dfd583b057b4e7d13e254d057aeaa03ea8a22dbbed0cecc7f4373a5f93b3";
+
+fn describe(result : Option<Derived>) -> String
+{
+    match result {
+        Some(Derived { pda, bump_seed: Some(bump_seed) }) => format!("{}.{}", pda, bump_seed),
+        Some(Derived { pda, bump_seed: None }) => pda.to_string(),
+        None => "no PDA".to_string()
+    }
+}
+
+// Checks one vector, returning what was derived instead if it doesn't match
+fn check(vector : &Vector) -> Result<(), String>
+{
+    let program_id = Pubkey::from_str(vector.program_id)?;
+    let seeds = vector.seeds.iter().map(|seed| seed.to_string()).collect::<Vec<String>>();
+    let seeds = parse_seeds(&seeds, vector.no_bump_seed, SeedLimits::Solana, Normalization::None, None)?;
+
+    let result = match vector.bump_seed {
+        Some(bump_seed) => {
+            create_pda(&program_id, &seeds, bump_seed).map(|pda| Derived { pda, bump_seed : Some(bump_seed) })
+        },
+        None => derive_pda(&program_id, &seeds, vector.no_bump_seed)
+    };
+
+    let expected = vector
+        .expected
+        .map(|(pda, bump_seed)| Pubkey::from_str(pda).map(|pda| Derived { pda, bump_seed }))
+        .transpose()?;
+
+    if result == expected {
+        Ok(())
+    }
+    else {
+        Err(format!("expected {}, derived {}", describe(expected), describe(result)))
+    }
+}
+
+fn check_all_bump_seeds() -> Result<(), String>
+{
+    let program_id = Pubkey::from_str(TOKEN_PROGRAM)?;
+    let seeds = [b"vault".to_vec()];
+
+    let mut hasher = Sha256::new();
+    for bump_seed in 0..=u8::MAX {
+        hasher.update(create_pda(&program_id, &seeds, bump_seed).map(|pda| pda.0).unwrap_or([0; 32]));
+    }
+    let hash = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+    if hash == ALL_BUMP_SEEDS_HASH {
+        Ok(())
+    }
+    else {
+        Err(format!("expected hash {}, derived {}", ALL_BUMP_SEEDS_HASH, hash))
+    }
+}
+
+pub fn selftest_command() -> Command
+{
+    Command::new("selftest").about("Checks derivations against known vectors, to test this build of solpda")
+}
+
+// Runs every vector, reporting each, and exits with status 0 if all passed or 1 if any failed
+pub fn selftest(_matches : &ArgMatches)
+{
+    let results = VECTORS
+        .iter()
+        .map(|vector| (vector.name, check(vector)))
+        .chain(std::iter::once(("every bump seed", check_all_bump_seeds())))
+        .collect::<Vec<(&str, Result<(), String>)>>();
+
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("ok    {}", name),
+            Err(e) => println!("FAIL  {}: {}", name, e)
+        }
+    }
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed == 0 {
+        println!("All {} tests passed", results.len());
+    }
+    else {
+        println!("{} of {} tests failed; do not trust this build of solpda", failed, results.len());
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,105 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use clap::{Arg, ArgMatches, Command};
+use ed25519_dalek::{ExpandedSecretKey, PublicKey, SecretKey, Signature};
+use zeroize::Zeroizing;
+
+use crate::{fingerprint, key_file_bytes, key_file_to_pubkey, read_program_id, solana_config};
+
+pub fn sign_command() -> Command
+{
+    Command::new("sign")
+        .about("Signs a message with a keypair, and outputs the signature in base58")
+        .arg(
+            Arg::new("keypair")
+                .short('k')
+                .long("keypair")
+                .value_name("KEYPAIR")
+                .help("The key file to sign with, rather than the Solana CLI's default keypair")
+        )
+        .arg(Arg::new("message").value_name("MESSAGE").required(true).help("The message to sign"))
+}
+
+pub fn verify_sig_command() -> Command
+{
+    Command::new("verify-sig")
+        .about("Checks a base58 signature of a message, exiting with status 1 if it is not valid")
+        .arg(Arg::new("pubkey").value_name("PUBKEY").required(true).help("The public key of the signer"))
+        .arg(Arg::new("signature").value_name("SIGNATURE").required(true).help("The signature, in base58"))
+        .arg(Arg::new("message").value_name("MESSAGE").required(true).help("The message that was signed"))
+}
+
+// Reads the keypair in a key file, which must hold a secret key rather than only a public key
+fn read_keypair(path : &str) -> Result<(SecretKey, PublicKey), String>
+{
+    let contents = std::fs::read_to_string(path).map(Zeroizing::new).map_err(|e| format!("{}: {}", path, e))?;
+
+    // This also checks that the public half of the keypair is that of its secret half
+    let pubkey = key_file_to_pubkey(&contents).map_err(|e| format!("{}: {} ({})", path, e, fingerprint(&contents)))?;
+    let bytes = key_file_bytes(&contents).map_err(|e| format!("{}: {}", path, e))?;
+    if bytes.len() != 64 {
+        return Err(format!("{} holds only a public key, which cannot sign", path));
+    }
+
+    let secret_key = SecretKey::from_bytes(&bytes[..32]).map_err(|e| e.to_string())?;
+    let public_key = PublicKey::from_bytes(&pubkey.0).map_err(|e| e.to_string())?;
+
+    Ok((secret_key, public_key))
+}
+
+// Signs a message with the keypair in a key file, or the Solana CLI's default keypair, and outputs the signature in
+// base58, as Solana shows signatures
+pub fn sign(matches : &ArgMatches)
+{
+    let message = matches.get_one::<String>("message").unwrap();
+
+    let keypair_path = matches.get_one::<String>("keypair").cloned().unwrap_or_else(|| {
+        solana_config::keypair_path().unwrap_or_else(|e| {
+            eprintln!("No -k given, and cannot use the Solana CLI's default keypair: {}", e);
+            std::process::exit(-1);
+        })
+    });
+
+    let (secret_key, public_key) = read_keypair(&keypair_path).unwrap_or_else(|e| {
+        eprintln!("Invalid keypair: {}", e);
+        std::process::exit(-1);
+    });
+
+    let signature = ExpandedSecretKey::from(&secret_key).sign(message.as_bytes(), &public_key);
+
+    println!("{}", bs58::encode(signature.to_bytes()).into_string());
+}
+
+// Checks a base58 signature of a message by a public key, exiting with status 0 if it is valid or 1 if not
+pub fn verify_sig(matches : &ArgMatches)
+{
+    let pubkey = read_program_id(matches.get_one::<String>("pubkey").unwrap()).unwrap_or_else(|e| {
+        eprintln!("Invalid public key: {}", e);
+        std::process::exit(-1);
+    });
+    let public_key = PublicKey::from_bytes(&pubkey.0).unwrap_or_else(|e| {
+        eprintln!("Invalid public key: {}", e);
+        std::process::exit(-1);
+    });
+
+    let signature = bs58::decode(matches.get_one::<String>("signature").unwrap())
+        .into_vec()
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| {
+            Signature::try_from(&bytes[..]).map_err(|_| format!("is {} bytes rather than 64", bytes.len()))
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid signature: {}", e);
+            std::process::exit(-1);
+        });
+
+    // Verified strictly, rejecting weak keys and malleable signatures, as Solana verifies transaction signatures
+    if public_key.verify_strict(matches.get_one::<String>("message").unwrap().as_bytes(), &signature).is_ok() {
+        println!("The signature is valid");
+    }
+    else {
+        eprintln!("The signature is not a valid signature of the message by {}", pubkey);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,48 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::path::PathBuf;
+
+// Where the Solana CLI keeps its configuration, relative to the home directory
+const CONFIG_PATH : &str = ".config/solana/cli/config.yml";
+
+// Reads a top level setting of the Solana CLI's configuration file.  The file is YAML, but as written by solana config
+// set, top level settings are always a key and a scalar value on a single line, which may be quoted.
+fn read_setting(key : &str) -> Result<Option<String>, String>
+{
+    let path = std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(CONFIG_PATH))
+        .ok_or("HOME is not set, so the Solana CLI config file cannot be found")?;
+
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(contents
+        .lines()
+        .filter(|line| !line.starts_with([' ', '\t', '#']))
+        .filter_map(|line| line.split_once(':'))
+        .find(|(line_key, _)| line_key.trim() == key)
+        .map(|(_, value)| {
+            let value = value.trim();
+            match value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+                Some(value) => value.replace("\\\"", "\"").replace("\\\\", "\\"),
+                None => match value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')) {
+                    Some(value) => value.replace("''", "'"),
+                    None => value.to_string()
+                }
+            }
+        })
+        .filter(|value| !value.is_empty()))
+}
+
+// The path of the Solana CLI's default keypair, as set by solana config set --keypair
+pub fn keypair_path() -> Result<String, String>
+{
+    let keypair_path = read_setting("keypair_path")?.ok_or("the Solana CLI config file does not set keypair_path")?;
+
+    // Hardware wallets and prompts can't be read as files
+    if keypair_path.contains("://") && !keypair_path.starts_with("file://") {
+        return Err(format!("the Solana CLI's default keypair {} is not a key file", keypair_path));
+    }
+
+    Ok(keypair_path.trim_start_matches("file://").to_string())
+}
@@ -0,0 +1,270 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use crate::Pubkey;
+
+// Minimal parser for the Solana wire format of transactions and messages (legacy and v0).  Only the parts needed to
+// identify account keys and how they are used are retained.
+
+pub struct Message
+{
+    // None for legacy messages, Some(n) for versioned messages
+    pub version : Option<u8>,
+
+    pub num_required_signatures : u8,
+
+    pub num_readonly_signed : u8,
+
+    pub num_readonly_unsigned : u8,
+
+    pub account_keys : Vec<Pubkey>,
+
+    pub instructions : Vec<Instruction>,
+
+    pub address_table_lookups : Vec<AddressTableLookup>
+}
+
+pub struct Instruction
+{
+    pub program_id_index : u8,
+
+    pub accounts : Vec<u8>
+}
+
+pub struct AddressTableLookup
+{
+    pub account_key : Pubkey,
+
+    pub writable_indexes : Vec<u8>,
+
+    pub readonly_indexes : Vec<u8>
+}
+
+struct Reader<'a>
+{
+    bytes : &'a [u8],
+
+    offset : usize
+}
+
+impl<'a> Reader<'a>
+{
+    fn new(bytes : &'a [u8]) -> Self
+    {
+        Reader { bytes, offset : 0 }
+    }
+
+    fn is_empty(&self) -> bool
+    {
+        self.offset == self.bytes.len()
+    }
+
+    fn take(
+        &mut self,
+        len : usize,
+        what : &str
+    ) -> Result<&'a [u8], String>
+    {
+        if (self.bytes.len() - self.offset) < len {
+            return Err(format!("Truncated input reading {} at offset {}", what, self.offset));
+        }
+        let ret = &self.bytes[self.offset..(self.offset + len)];
+        self.offset += len;
+        Ok(ret)
+    }
+
+    fn u8(
+        &mut self,
+        what : &str
+    ) -> Result<u8, String>
+    {
+        Ok(self.take(1, what)?[0])
+    }
+
+    // Solana's "compact-u16" (a.k.a. shortvec) encoding: 7 bits per byte, high bit set on all but the last byte
+    fn compact_u16(
+        &mut self,
+        what : &str
+    ) -> Result<usize, String>
+    {
+        let mut value = 0_usize;
+        for i in 0..3 {
+            let b = self.u8(what)?;
+            value |= ((b & 0x7F) as usize) << (i * 7);
+            if (b & 0x80) == 0 {
+                return if value > (u16::MAX as usize) {
+                    Err(format!("Invalid compact-u16 {} at offset {}", what, self.offset))
+                }
+                else {
+                    Ok(value)
+                };
+            }
+        }
+        Err(format!("Invalid compact-u16 {} at offset {}", what, self.offset))
+    }
+
+    fn pubkey(
+        &mut self,
+        what : &str
+    ) -> Result<Pubkey, String>
+    {
+        Ok(Pubkey(self.take(32, what)?.try_into().unwrap()))
+    }
+
+    fn u8_vec(
+        &mut self,
+        what : &str
+    ) -> Result<Vec<u8>, String>
+    {
+        let len = self.compact_u16(what)?;
+        Ok(self.take(len, what)?.to_vec())
+    }
+}
+
+impl Message
+{
+    // Parses either a full serialized transaction (signatures followed by message) or a bare serialized message.
+    // A transaction is recognized by its signature count matching the message header's required signature count.
+    pub fn from_transaction_or_message(bytes : &[u8]) -> Result<Message, String>
+    {
+        let tx_result = Self::from_transaction(bytes);
+
+        if tx_result.is_ok() {
+            return tx_result;
+        }
+
+        Self::from_message(bytes).map_err(|e| {
+            format!(
+                "Input is neither a serialized transaction ({}) nor a serialized message ({})",
+                tx_result.err().unwrap(),
+                e
+            )
+        })
+    }
+
+    pub fn from_transaction(bytes : &[u8]) -> Result<Message, String>
+    {
+        let mut reader = Reader::new(bytes);
+
+        let num_signatures = reader.compact_u16("signature count")?;
+
+        reader.take(num_signatures * 64, "signatures")?;
+
+        let message = Self::read(&mut reader)?;
+
+        if (message.num_required_signatures as usize) != num_signatures {
+            return Err(format!(
+                "Transaction has {} signatures but its message requires {}",
+                num_signatures, message.num_required_signatures
+            ));
+        }
+
+        Ok(message)
+    }
+
+    pub fn from_message(bytes : &[u8]) -> Result<Message, String>
+    {
+        Self::read(&mut Reader::new(bytes))
+    }
+
+    fn read(reader : &mut Reader) -> Result<Message, String>
+    {
+        let mut first = reader.u8("message header")?;
+
+        let version = if (first & 0x80) == 0 {
+            None
+        }
+        else {
+            let version = first & 0x7F;
+            if version != 0 {
+                return Err(format!("Unsupported message version {}", version));
+            }
+            first = reader.u8("message header")?;
+            Some(version)
+        };
+
+        let num_required_signatures = first;
+        let num_readonly_signed = reader.u8("message header")?;
+        let num_readonly_unsigned = reader.u8("message header")?;
+
+        let num_keys = reader.compact_u16("account key count")?;
+
+        let account_keys = (0..num_keys).map(|_| reader.pubkey("account keys")).collect::<Result<Vec<Pubkey>, _>>()?;
+
+        if (num_required_signatures as usize) > num_keys ||
+            (num_readonly_signed > num_required_signatures) ||
+            ((num_readonly_unsigned as usize) > (num_keys - (num_required_signatures as usize)))
+        {
+            return Err("Message header is inconsistent with its account keys".to_string());
+        }
+
+        reader.take(32, "recent blockhash")?;
+
+        let num_instructions = reader.compact_u16("instruction count")?;
+
+        let instructions = (0..num_instructions)
+            .map(|_| {
+                let program_id_index = reader.u8("instruction program id index")?;
+                let accounts = reader.u8_vec("instruction accounts")?;
+                reader.u8_vec("instruction data")?;
+                Ok(Instruction { program_id_index, accounts })
+            })
+            .collect::<Result<Vec<Instruction>, String>>()?;
+
+        let address_table_lookups = if version.is_some() {
+            let num_lookups = reader.compact_u16("address table lookup count")?;
+            (0..num_lookups)
+                .map(|_| {
+                    Ok(AddressTableLookup {
+                        account_key : reader.pubkey("address table lookup key")?,
+                        writable_indexes : reader.u8_vec("address table lookup writable indexes")?,
+                        readonly_indexes : reader.u8_vec("address table lookup readonly indexes")?
+                    })
+                })
+                .collect::<Result<Vec<AddressTableLookup>, String>>()?
+        }
+        else {
+            vec![]
+        };
+
+        if !reader.is_empty() {
+            return Err(format!("Unexpected trailing bytes at offset {}", reader.offset));
+        }
+
+        Ok(Message {
+            version,
+            num_required_signatures,
+            num_readonly_signed,
+            num_readonly_unsigned,
+            account_keys,
+            instructions,
+            address_table_lookups
+        })
+    }
+
+    pub fn is_signer(
+        &self,
+        index : usize
+    ) -> bool
+    {
+        index < (self.num_required_signatures as usize)
+    }
+
+    pub fn is_writable(
+        &self,
+        index : usize
+    ) -> bool
+    {
+        if self.is_signer(index) {
+            index < ((self.num_required_signatures - self.num_readonly_signed) as usize)
+        }
+        else {
+            index < (self.account_keys.len() - (self.num_readonly_unsigned as usize))
+        }
+    }
+
+    pub fn loaded_address_count(&self) -> usize
+    {
+        self.address_table_lookups.iter().map(|l| l.writable_indexes.len() + l.readonly_indexes.len()).sum()
+    }
+}
@@ -0,0 +1,295 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+#[cfg(feature = "tui")]
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+#[cfg(feature = "tui")]
+use ratatui::layout::{Constraint, Layout};
+#[cfg(feature = "tui")]
+use ratatui::style::{Modifier, Style};
+#[cfg(feature = "tui")]
+use ratatui::text::Line;
+#[cfg(feature = "tui")]
+use ratatui::widgets::{Block, Paragraph};
+#[cfg(feature = "tui")]
+use ratatui::Frame;
+
+#[cfg(feature = "tui")]
+use crate::{
+    config, create_pda, derive_pda, parse_seeds, read_address, read_program_id_as, Normalization, ProgramIdFormat,
+    Pubkey, SeedLimits
+};
+
+pub fn tui_command() -> Command
+{
+    Command::new("tui")
+        .about("Explores derivations interactively, recomputing the PDA and every bump seed's address as seeds change")
+        .arg(Arg::new("program-id").value_name("PROGRAM_ID").help("The program id to start with"))
+        .arg(Arg::new("seeds").value_name("SEED").num_args(1..).help("The seeds to start with"))
+        .arg(
+            Arg::new("no-limit-check")
+                .long("no-limit-check")
+                .action(ArgAction::SetTrue)
+                .help("Don't check seeds against Solana's limits")
+        )
+}
+
+// The state of the explorer: the program id and the seed slots being edited, which are all re-derived on every change
+#[cfg(feature = "tui")]
+struct Explorer
+{
+    // The text of the program id, followed by that of each seed slot
+    fields : Vec<String>,
+
+    // The field being edited
+    selected : usize,
+
+    // The first bump seed shown in the all-bumps panel, counting down from 255
+    scroll : usize,
+
+    limits : SeedLimits,
+
+    normalization : Normalization,
+
+    program_id_format : Option<ProgramIdFormat>
+}
+
+#[cfg(feature = "tui")]
+impl Explorer
+{
+    fn program_id(&self) -> Result<Pubkey, String>
+    {
+        // Only addresses and aliases are read as the program id is typed, so that a partly typed path or URL is never
+        // opened; a key file given on the command line was already read
+        read_address(self.fields[0].trim()).map_err(|e| format!("Invalid program id: {}", e))
+    }
+
+    fn seeds(&self) -> Vec<String>
+    {
+        self.fields[1..].iter().map(|seed| seed.trim().to_string()).filter(|seed| !seed.is_empty()).collect()
+    }
+
+    // Describes the seed in a slot: its length, or why it is invalid
+    fn describe_seed(
+        &self,
+        seed : &str
+    ) -> String
+    {
+        if seed.trim().is_empty() {
+            return String::new();
+        }
+
+        match parse_seeds(
+            &[seed.trim().to_string()],
+            true,
+            SeedLimits::None,
+            self.normalization,
+            self.program_id_format
+        ) {
+            Ok(parsed) => format!("{} bytes", parsed[0].len()),
+            Err(e) => e.lines().next().unwrap_or("").to_string()
+        }
+    }
+
+    // The lines of the PDA panel, and those of the all-bumps panel, which lists the address of each bump seed
+    fn derive(&self) -> (Vec<Line<'static>>, Vec<Line<'static>>)
+    {
+        let program_id = match self.program_id() {
+            Ok(program_id) => program_id,
+            Err(e) => return (vec![Line::from(e)], vec![])
+        };
+
+        let seeds = match parse_seeds(&self.seeds(), false, self.limits, self.normalization, self.program_id_format) {
+            Ok(seeds) => seeds,
+            Err(e) => return (e.lines().map(|line| Line::from(line.to_string())).collect(), vec![])
+        };
+
+        let derived = derive_pda(&program_id, &seeds, false);
+
+        let pda = match &derived {
+            Some(derived) => vec![
+                Line::from(format!("PDA:  {}", derived.pda)),
+                Line::from(format!("Bump: {}", derived.bump_seed.unwrap_or(0))),
+            ],
+            None => vec![Line::from("Every bump seed gives an address on the ed25519 curve, so there is no PDA")]
+        };
+
+        let canonical = derived.and_then(|derived| derived.bump_seed);
+
+        let bumps = (0..=255_u8)
+            .rev()
+            .map(|bump_seed| {
+                let address = match create_pda(&program_id, &seeds, bump_seed) {
+                    Some(pda) => pda.to_string(),
+                    None => "(on the curve)".to_string()
+                };
+                if Some(bump_seed) == canonical {
+                    Line::styled(
+                        format!("{:>3}  {}  canonical", bump_seed, address),
+                        Style::new().add_modifier(Modifier::BOLD)
+                    )
+                }
+                else {
+                    Line::from(format!("{:>3}  {}", bump_seed, address))
+                }
+            })
+            .collect();
+
+        (pda, bumps)
+    }
+
+    fn draw(
+        &self,
+        frame : &mut Frame
+    )
+    {
+        let [fields_area, pda_area, bumps_area, help_area] = Layout::vertical([
+            Constraint::Length(self.fields.len() as u16 + 2),
+            Constraint::Length(4),
+            Constraint::Min(3),
+            Constraint::Length(1)
+        ])
+        .areas(frame.area());
+
+        let fields = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let label = if i == 0 { "Program id".to_string() } else { format!("Seed {}", i) };
+                let description = if i == 0 { String::new() } else { self.describe_seed(field) };
+                let cursor = if i == self.selected { "_" } else { "" };
+                let line = Line::from(format!("{:>10}: {}{}  {}", label, field, cursor, description));
+                if i == self.selected {
+                    line.style(Style::new().add_modifier(Modifier::REVERSED))
+                }
+                else {
+                    line
+                }
+            })
+            .collect::<Vec<Line>>();
+
+        let (pda, bumps) = self.derive();
+
+        frame.render_widget(Paragraph::new(fields).block(Block::bordered().title("Seeds")), fields_area);
+        frame.render_widget(Paragraph::new(pda).block(Block::bordered().title("PDA")), pda_area);
+        frame.render_widget(
+            Paragraph::new(bumps).scroll((self.scroll as u16, 0)).block(Block::bordered().title("All bump seeds")),
+            bumps_area
+        );
+        frame.render_widget(
+            Paragraph::new(
+                "Up/Down: select  Enter: add seed  Ctrl-D: remove seed  PgUp/PgDn: scroll bump seeds  Esc: quit"
+            ),
+            help_area
+        );
+    }
+
+    // Applies a key press, returning false to quit
+    fn key(
+        &mut self,
+        code : KeyCode,
+        modifiers : KeyModifiers
+    ) -> bool
+    {
+        match code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            // The program id can't be removed, only seeds
+            KeyCode::Char('d') if modifiers.contains(KeyModifiers::CONTROL) && (self.selected > 0) => {
+                self.fields.remove(self.selected);
+                if self.fields.len() == 1 {
+                    self.fields.push(String::new());
+                }
+                self.selected = self.selected.min(self.fields.len() - 1);
+            },
+            KeyCode::Char(_) if modifiers.contains(KeyModifiers::CONTROL) => (),
+            KeyCode::Char(c) => self.fields[self.selected].push(c),
+            KeyCode::Backspace => {
+                self.fields[self.selected].pop();
+            },
+            KeyCode::Enter => {
+                self.selected += 1;
+                self.fields.insert(self.selected, String::new());
+            },
+            KeyCode::Up | KeyCode::BackTab => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Tab => self.selected = (self.selected + 1).min(self.fields.len() - 1),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::PageDown => self.scroll = (self.scroll + 10).min(255),
+            _ => ()
+        }
+        true
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn tui(_matches : &ArgMatches)
+{
+    eprintln!("The tui command requires solpda to be built with the tui feature");
+    std::process::exit(-1);
+}
+
+#[cfg(feature = "tui")]
+pub fn tui(matches : &ArgMatches)
+{
+    let config = config::load();
+    let program_id_format = config.program_id_format;
+
+    // A program id given as a key file or other source that can't be read as it is typed is read once, up front, in
+    // the format that the config file or environment give, if any
+    let program_id = matches
+        .get_one::<String>("program-id")
+        .map(|program_id| {
+            read_program_id_as(program_id, program_id_format).map(|pubkey| pubkey.to_string()).unwrap_or_else(|e| {
+                eprintln!("Invalid program id: {}", e);
+                std::process::exit(-1);
+            })
+        })
+        .unwrap_or_default();
+
+    let mut fields = vec![program_id];
+    fields.extend(matches.get_many::<String>("seeds").into_iter().flatten().cloned());
+    if fields.len() == 1 {
+        fields.push(String::new());
+    }
+
+    let mut explorer = Explorer {
+        selected : if fields[0].is_empty() { 0 } else { fields.len() - 1 },
+        fields,
+        scroll : 0,
+        limits : SeedLimits::new(!(matches.get_flag("no-limit-check") || config.no_limit_check), false),
+        normalization : config.normalize.unwrap_or(Normalization::None),
+        program_id_format
+    };
+
+    let mut terminal = ratatui::init();
+
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| explorer.draw(frame)) {
+            break Err(e);
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if !explorer.key(key.code, key.modifiers) {
+                    break Ok(());
+                }
+            },
+            Ok(_) => (),
+            Err(e) => break Err(e)
+        }
+    };
+
+    ratatui::restore();
+
+    if let Err(e) = result {
+        eprintln!("Terminal error: {}", e);
+        std::process::exit(-1);
+    }
+
+    // The command line that derives what was being explored, to carry on with outside of the explorer
+    let seeds =
+        explorer.seeds().iter().map(|seed| format!("'{}'", seed.replace('\'', "'\\''"))).collect::<Vec<String>>();
+    println!("solpda {} {}", explorer.fields[0].trim(), seeds.join(" "));
+}
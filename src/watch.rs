@@ -0,0 +1,169 @@
+/**
+ * LICENSE: Public Domain
+ **/
+use std::io::IsTerminal;
+use std::time::{Duration, SystemTime};
+
+use toml::{Table, Value};
+
+use crate::{print_pda, read_program_id_as, split_seeds, DeriveOptions, ProgramIdFormat, Pubkey};
+
+// How often the spec file is checked for changes
+const POLL_INTERVAL : Duration = Duration::from_millis(250);
+
+// A derivation listed in a spec file
+struct Derivation
+{
+    label : Option<String>,
+
+    program_id : Pubkey,
+
+    seeds : Vec<String>
+}
+
+fn program_id(
+    value : &Value,
+    format : Option<ProgramIdFormat>
+) -> Result<Pubkey, String>
+{
+    let program_id = value.as_str().ok_or("program_id must be a string")?;
+    read_program_id_as(program_id, format).map_err(|e| format!("invalid program_id {}: {}", program_id, e))
+}
+
+// Reads a spec file, which has a [[pda]] table for each derivation, with seeds (an array of seeds, or a string of seeds
+// separated by spaces) and optionally label and program_id.  A program_id at the top level applies to every derivation
+// that doesn't give its own.  Program ids are read in format.
+fn read_spec(
+    path : &str,
+    format : Option<ProgramIdFormat>
+) -> Result<Vec<Derivation>, String>
+{
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let spec = contents.parse::<Table>().map_err(|e| e.to_string())?;
+
+    let mut default_program_id = None;
+    let mut pdas = &vec![];
+
+    for (key, value) in &spec {
+        match key.as_str() {
+            "program_id" => default_program_id = Some(program_id(value, format)?),
+            "pda" => pdas = value.as_array().ok_or("pda must be an array of tables")?,
+            _ => return Err(format!("unexpected key {}", key))
+        }
+    }
+
+    pdas.iter()
+        .enumerate()
+        .map(|(i, pda)| {
+            let invalid = |e : String| format!("pda {}: {}", i + 1, e);
+            let pda = pda.as_table().ok_or_else(|| invalid("must be a table".to_string()))?;
+
+            let mut label = None;
+            let mut pda_program_id = None;
+            let mut seeds = None;
+
+            for (key, value) in pda {
+                match key.as_str() {
+                    "label" => {
+                        label = Some(value.as_str().map(|label| label.to_string()).unwrap_or_else(|| value.to_string()))
+                    },
+                    "program_id" => pda_program_id = Some(program_id(value, format).map_err(invalid)?),
+                    "seeds" => {
+                        seeds = Some(match value {
+                            Value::String(seeds) => split_seeds(seeds),
+                            Value::Array(seeds) => seeds
+                                .iter()
+                                .map(|seed| seed.as_str().map(|seed| seed.to_string()))
+                                .collect::<Option<Vec<String>>>()
+                                .ok_or_else(|| invalid("seeds must be strings".to_string()))?,
+                            _ => return Err(invalid("seeds must be an array or a string".to_string()))
+                        });
+                    },
+                    _ => return Err(invalid(format!("unexpected key {}", key)))
+                }
+            }
+
+            Ok(Derivation {
+                label,
+                program_id : pda_program_id
+                    .or(default_program_id)
+                    .ok_or_else(|| invalid("missing program_id".to_string()))?,
+                seeds : seeds.ok_or_else(|| invalid("missing seeds".to_string()))?
+            })
+        })
+        .collect()
+}
+
+// Derives and prints everything in the spec file, in the same way as the derivations of a matrix
+fn derive_spec(
+    path : &str,
+    options : DeriveOptions
+)
+{
+    let derivations = match read_spec(path, options.program_id_format) {
+        Ok(derivations) => derivations,
+        Err(e) => {
+            eprintln!("Invalid spec file {}: {}", path, e);
+            return;
+        }
+    };
+
+    for derivation in derivations {
+        let error_prefix = derivation.label.as_ref().map(|label| format!("{}: ", label)).unwrap_or_default();
+
+        match options.derive(&derivation.program_id, &derivation.seeds) {
+            Ok(Some(derived)) => {
+                if let Some(label) = &derivation.label {
+                    print!("{} ", label);
+                }
+                print_pda(&derived, options.bytes);
+            },
+            Ok(None) => eprintln!("{}{}", error_prefix, options.no_pda_error()),
+            Err(e) => eprintln!("{}{}", error_prefix, e)
+        }
+    }
+}
+
+// Identifies the version of the spec file, to tell when it has changed; the length is included because modification
+// times may be too coarse to tell apart two quick writes
+fn version(path : &str) -> Option<(SystemTime, u64)>
+{
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// Derives everything in the spec file, and again every time it changes, until interrupted
+pub fn watch(
+    path : &str,
+    options : DeriveOptions
+) -> !
+{
+    let is_terminal = std::io::stdout().is_terminal();
+    let mut last_version = None;
+    let mut first = true;
+
+    loop {
+        // While the file is missing, as it may briefly be while an editor replaces it, wait for it to come back
+        let current_version = version(path);
+        if current_version.is_some() && (current_version != last_version) {
+            last_version = current_version;
+
+            // On a terminal, each run replaces the last on the screen; otherwise runs are separated by a blank line
+            if is_terminal {
+                print!("\x1b[2J\x1b[H");
+            }
+            else if !first {
+                println!();
+            }
+            first = false;
+
+            derive_spec(path, options);
+        }
+        else if first && current_version.is_none() {
+            eprintln!("Failed to read {}", path);
+            std::process::exit(-1);
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}